@@ -1,9 +1,23 @@
 // command line interface
 
-use crate::tui::DbInfo;
-use crate::{Db, Provider, Server};
-use clap::{Parser, Subcommand};
+use crate::core::{DEFAULT_MAX_ROWS, DEFAULT_MAX_SCHEMA_BYTES, DEFAULT_ROW_LIMIT};
+use crate::server::{DEFAULT_DB, DEFAULT_MAX_BODY_SIZE, DEFAULT_QUERY_HISTORY_SIZE, ServeOptions};
+use crate::tui::{DEFAULT_HISTORY_SIZE, DEFAULT_MAX_PROMPT_CHARS, DbInfo, ProfileEntry, Settings};
+use crate::{
+    Ai, AiOptions, Config, CsvOptions, Db, DbConfig, Error, ExportFormat, Output, OutputFormat,
+    Provider, QueryResult, Repl, ReplOptions, Safety, Server, SqlGenerator, StatementKind, Usage,
+    export_rows, parse_time_bound, quote_reserved, redact_literals, redact_url, schema_tables,
+    scope_schema, with_password,
+};
+use chrono::NaiveDate;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use miette::Result;
+use std::ffi::OsString;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "nlql", about = "Talk to your database in plain english")]
@@ -12,18 +26,204 @@ struct Cli {
     #[arg(long, short, env = "DATABASE_URL", global = true)]
     db: Option<String>,
 
-    /// ai provider (claude, openai)
-    #[arg(long, short = 'p', default_value = "claude", global = true)]
+    /// config file to use instead of ~/.config/nlql/config.toml
+    #[arg(long, env = "NLQL_CONFIG", global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// saved connection from the config's [profiles.<name>], --db still wins
+    #[arg(long, env = "NLQL_PROFILE", global = true)]
+    profile: Option<String>,
+
+    /// read-only replica url, SELECTs run here while everything else goes to --db
+    #[arg(long, env = "NLQL_READ_URL", global = true)]
+    read_url: Option<String>,
+
+    /// ai provider (claude, openai, gemini, ollama)
+    #[arg(
+        long,
+        short = 'p',
+        env = "NLQL_PROVIDER",
+        default_value = "claude",
+        global = true
+    )]
     provider: Provider,
 
+    /// model name (default: the provider's standard model)
+    #[arg(long, short = 'm', env = "NLQL_MODEL", global = true)]
+    model: Option<String>,
+
+    /// anthropic-version header for claude requests (default: 2023-06-01)
+    #[arg(long, env = "NLQL_ANTHROPIC_VERSION", global = true)]
+    anthropic_version: Option<String>,
+
+    /// output format for query results
+    #[arg(
+        long,
+        short,
+        value_enum,
+        env = "NLQL_OUTPUT",
+        default_value = "pretty",
+        global = true
+    )]
+    output: OutputFormat,
+
+    /// read the database password from stdin (hidden prompt on a terminal)
+    #[arg(long, global = true)]
+    password_stdin: bool,
+
     /// api key for the ai provider
     #[arg(long, short = 'k', global = true)]
     api_key: Option<String>,
 
-    /// ask for confirmation before running sql
+    /// ask before running sql that isn't a plain SELECT
     #[arg(long, short)]
     confirm: bool,
 
+    /// ask before running any sql, SELECTs included
+    #[arg(long)]
+    confirm_all: bool,
+
+    /// cache SELECT results in the tui for a minute (toggle with C)
+    #[arg(long)]
+    cache: bool,
+
+    /// pick the tables the model gets to see before prompting
+    #[arg(long, global = true)]
+    interactive_schema: bool,
+
+    /// prompt size the tui warns about, its counter turns yellow near it
+    #[arg(long, default_value_t = DEFAULT_MAX_PROMPT_CHARS)]
+    max_prompt_chars: usize,
+
+    /// prompts the tui keeps in ~/.local/share/nlql/history, 0 to keep none
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_HISTORY_SIZE)]
+    history_size: usize,
+
+    /// run `SELECT 1` every SECS seconds in the tui so idle connections aren't dropped
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    db_ping_interval: Option<u64>,
+
+    /// tui: also ask this provider and show both queries side by side (key from its env var)
+    #[arg(long, value_name = "PROVIDER")]
+    compare: Option<Provider>,
+
+    /// model for --compare (default: that provider's standard model)
+    #[arg(long, requires = "compare")]
+    compare_model: Option<String>,
+
+    /// extra header for ai requests, e.g. for a company llm gateway (repeatable,
+    /// one per line in NLQL_AI_HEADERS)
+    // a header value can hold commas but never a newline
+    #[arg(
+        long,
+        value_name = "KEY=VALUE",
+        env = "NLQL_AI_HEADERS",
+        value_delimiter = '\n',
+        value_parser = parse_header,
+        global = true
+    )]
+    ai_header: Vec<(String, String)>,
+
+    /// let the model ask a clarifying question instead of guessing
+    #[arg(long, global = true)]
+    clarify: bool,
+
+    /// postgres schema or mysql database to read tables from (default: public / the url's)
+    #[arg(long, global = true, env = "NLQL_DB_SCHEMA", value_name = "NAME")]
+    db_schema: Option<String>,
+
+    /// give up connecting to the database after SECS seconds
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    db_timeout: u64,
+
+    /// connections kept open to the database (and to --read-url)
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    max_connections: u32,
+
+    /// stop reading a result after N rows so a huge table can't fill memory, 0 keeps all
+    #[arg(long, global = true, value_name = "N", default_value_t = DEFAULT_MAX_ROWS)]
+    max_rows: usize,
+
+    /// refuse sql whose EXPLAIN estimates more rows than this (not sqlite)
+    #[arg(
+        long,
+        global = true,
+        value_name = "ROWS",
+        alias = "explain-cost-threshold"
+    )]
+    cost_threshold: Option<u64>,
+
+    /// cap on schema bytes sent to the model; extra tables are dropped
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_SCHEMA_BYTES)]
+    max_schema_bytes: usize,
+
+    /// instructions put before every prompt (overrides config prompt_prefix)
+    #[arg(long, global = true)]
+    prompt_prefix: Option<String>,
+
+    /// instructions put after every prompt (overrides config prompt_suffix)
+    #[arg(long, global = true)]
+    prompt_suffix: Option<String>,
+
+    /// only consider rows from this point on: 7d, 2w, 3m, 1y or YYYY-MM-DD
+    #[arg(long, global = true, value_parser = parse_bound)]
+    since: Option<NaiveDate>,
+
+    /// only consider rows before this point, same format as --since
+    #[arg(long, global = true, value_parser = parse_bound)]
+    until: Option<NaiveDate>,
+
+    /// let the model offer alternative queries and pick one
+    #[arg(long, global = true)]
+    candidates: bool,
+
+    /// tell the model to only write SELECTs and refuse anything else it returns
+    #[arg(long, global = true)]
+    select_only_prompt: bool,
+
+    /// for "recent" / "latest" prompts, tell the model to order by created_at, updated_at or inserted_at
+    #[arg(long, global = true)]
+    order_hint: bool,
+
+    /// refuse anything that isn't a SELECT or EXPLAIN, in query, serve and the tui;
+    /// turns on --select-only-prompt too
+    #[arg(long, global = true, env = "NLQL_READ_ONLY")]
+    read_only: bool,
+
+    /// add `LIMIT N` to generated SELECTs that come without one
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        default_value_t = DEFAULT_ROW_LIMIT,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    limit: u64,
+
+    /// run generated SELECTs as written, without adding a LIMIT
+    #[arg(long, global = true, conflicts_with = "limit")]
+    no_limit: bool,
+
+    /// run sql with several statements as one transaction instead of refusing it
+    #[arg(long, global = true)]
+    multi_statement: bool,
+
+    /// quote schema names that are reserved words (`order`, `user`) in generated sql
+    #[arg(long, global = true)]
+    quote_reserved: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -33,33 +233,424 @@ enum Commands {
     /// start as http server
     Serve {
         /// port number
-        #[arg(long, short, default_value = "3000")]
+        #[arg(long, default_value = "3000")]
         port: u16,
 
         /// host to bind
         #[arg(long, default_value = "127.0.0.1")]
         host: String,
+
+        /// extra database to serve, picked with `"db": "<name>"` (repeatable)
+        #[arg(long, value_name = "NAME=URL", value_parser = parse_named_db)]
+        named_db: Vec<(String, String)>,
+
+        /// expose prometheus metrics at GET /metrics
+        #[arg(long)]
+        enable_metrics: bool,
+
+        /// largest request body in bytes, bigger ones get a 413
+        #[arg(long, default_value_t = DEFAULT_MAX_BODY_SIZE)]
+        max_body_size: usize,
+
+        /// recent /query calls kept for GET /history, 0 to keep none
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_QUERY_HISTORY_SIZE)]
+        query_history_size: usize,
     },
+
+    /// list the connection profiles in the config file
+    Profiles,
+
+    /// inspect the config file and the settings it merges into
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// generate sql for a prompt and run it
+    Query {
+        /// what you want to know, in plain english
+        prompt: String,
+
+        /// only print the generated sql, don't run it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// print the EXPLAIN plan of the generated sql instead of running it
+        #[arg(long, conflicts_with_all = ["dry_run", "export", "save_table"])]
+        explain: bool,
+
+        /// with --explain, EXPLAIN ANALYZE: runs the query for real timings, so reads only
+        #[arg(long, requires = "explain")]
+        analyze: bool,
+
+        /// print the request that would go to the provider (key redacted) and stop
+        #[arg(long)]
+        show_request: bool,
+
+        /// print the tokens the provider billed to stderr
+        #[arg(long)]
+        stats: bool,
+
+        /// echo the sql that runs to stderr, whatever the output format
+        #[arg(long)]
+        trace_sql: bool,
+
+        /// put the prompt above the sql in pretty and html output, so results explain themselves
+        #[arg(long)]
+        show_prompt: bool,
+
+        /// with --trace-sql, print literals as `?` so values stay out of logs
+        #[arg(long, requires = "trace_sql")]
+        redact_literals: bool,
+
+        /// for DROP, TRUNCATE and DELETE without WHERE, type this instead of y
+        /// (without a value: the table's name)
+        #[arg(long, value_name = "PHRASE", num_args = 0..=1, default_missing_value = "")]
+        confirm_phrase: Option<String>,
+
+        /// max chars per column in pretty output (default: terminal width / columns)
+        #[arg(long)]
+        col_width: Option<usize>,
+
+        /// don't send the schema to the model (faster, but sql may be less accurate)
+        #[arg(long)]
+        no_schema: bool,
+
+        /// field separator for --output csv (`\t` or `tab` for tsv)
+        #[arg(long, value_parser = parse_delimiter)]
+        delimiter: Option<char>,
+
+        /// turn the distinct values of this column into columns
+        #[arg(long, value_name = "COLUMN")]
+        pivot: Option<String>,
+
+        /// also save the result as a new table in the sqlite database
+        #[arg(long, value_name = "TABLE")]
+        save_table: Option<String>,
+
+        /// sqlite file for --save-table (needed when --db isn't sqlite)
+        #[arg(long, value_name = "FILE", requires = "save_table")]
+        out: Option<String>,
+
+        /// stream the rows straight into FILE (`-` for stdout) instead of printing them
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["pivot", "save_table"])]
+        export: Option<PathBuf>,
+
+        /// format for --export (default: from the file extension, csv otherwise)
+        #[arg(long, value_enum, requires = "export")]
+        export_format: Option<ExportFormat>,
+    },
+
+    /// read prompts line by line and print each result, with one connection and one schema
+    Repl {
+        /// start with dry run on, only print the sql (`\dry` toggles it)
+        #[arg(long)]
+        dry_run: bool,
+
+        /// max chars per column (default: terminal width / columns)
+        #[arg(long)]
+        col_width: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// print the effective settings and where each one came from (secrets redacted)
+    Show,
+    /// print where the config file is read from, whether or not it exists yet
+    Path,
+}
+
+impl Cli {
+    // --db-timeout and --max-connections, for every db this run connects to
+    fn db_config(&self) -> DbConfig {
+        DbConfig {
+            max_connections: self.max_connections,
+            connect_timeout: Duration::from_secs(self.db_timeout),
+            max_rows: Some(self.max_rows).filter(|n| *n > 0),
+            ..DbConfig::default()
+        }
+    }
+
+    // --limit, unless --no-limit turned it off
+    fn row_limit(&self) -> Option<u64> {
+        (!self.no_limit).then_some(self.limit)
+    }
 }
 
 pub async fn run() -> Result<()> {
-    let cli = Cli::parse();
+    run_with(std::env::args_os(), None).await
+}
+
+/// `run` with `args` instead of the process's own
+///
+/// a `generator` answers `query` instead of the provider the flags pick
+pub async fn run_with<I, T>(args: I, generator: Option<Arc<dyn SqlGenerator>>) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = Cli::command().get_matches_from(args);
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = match &cli.config {
+        Some(path) => Config::load_from(path)?,
+        None => Config::load()?,
+    };
+    apply_config(&mut cli, &matches, &config);
+
+    // --profile fills in whatever wasn't given on the command line
+    if let Some(name) = &cli.profile {
+        let profile = config.profile(name)?;
+        if cli.db.is_none() {
+            cli.db = Some(profile.connect_url()?);
+        }
+        if cli.read_url.is_none() {
+            cli.read_url = profile.connect_read_url()?;
+        }
+    }
+
+    // before --password-stdin, there's nothing to connect to
+    if let Some(Commands::Config { action }) = &cli.command {
+        match action {
+            ConfigCommand::Show => print!("{}", show_config(&cli, &matches, &config)),
+            ConfigCommand::Path => {
+                let path = cli.config.clone().or_else(Config::path).ok_or_else(|| {
+                    Error::Config("no config location, set HOME or XDG_CONFIG_HOME".to_string())
+                })?;
+                println!("{}", path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    // --password-stdin: keep the password out of the url, env and shell history
+    if cli.password_stdin {
+        let url = cli.db.as_deref().ok_or_else(missing_db)?;
+        cli.db = Some(with_password(url, &read_password()?)?);
+    }
+
+    let db_config = cli.db_config();
+    let limit = cli.row_limit();
+    let ai_options = AiOptions {
+        clarify: cli.clarify,
+        max_schema_bytes: cli.max_schema_bytes,
+        prompt_prefix: cli.prompt_prefix.or(config.prompt_prefix.clone()),
+        prompt_suffix: cli.prompt_suffix.or(config.prompt_suffix.clone()),
+        since: cli.since,
+        until: cli.until,
+        candidates: cli.candidates,
+        model: cli.model,
+        claude_model: config.ai.claude_model.clone(),
+        openai_model: config.ai.openai_model.clone(),
+        gemini_model: config.ai.gemini_model.clone(),
+        ollama_model: config.ai.ollama_model.clone(),
+        anthropic_version: cli
+            .anthropic_version
+            .or(config.ai.anthropic_version.clone()),
+        headers: cli.ai_header,
+        // --read-only asks the model for SELECTs too, not just refuses the rest
+        select_only: cli.select_only_prompt || cli.read_only,
+        order_hint: cli.order_hint,
+    };
 
     match cli.command {
-        Some(Commands::Serve { port, host }) => {
-            // serve mode requires --db
-            let db = cli
-                .db
-                .ok_or_else(|| miette::miette!("database url required (--db or DATABASE_URL)"))?;
-            Ok(Server::run(&db, &host, port).await?)
+        Some(Commands::Serve {
+            port,
+            host,
+            named_db,
+            enable_metrics,
+            max_body_size,
+            query_history_size,
+        }) => {
+            // serve mode requires at least one database
+            let mut dbs = named_db;
+            if let Some(db) = cli.db {
+                dbs.insert(0, (DEFAULT_DB.to_string(), db));
+            }
+            if dbs.is_empty() {
+                return Err(missing_db().into());
+            }
+            let options = ServeOptions {
+                enable_metrics,
+                max_body_size,
+                history_size: query_history_size,
+                cost_threshold: cli.cost_threshold,
+                read_only: cli.read_only,
+                multi_statement: cli.multi_statement,
+                limit,
+                db_schema: cli.db_schema,
+                db_config: db_config.clone(),
+                provider: cli.provider,
+                api_key: cli.api_key,
+                ai_options,
+                config: config.clone(),
+            };
+            Ok(Server::run(&dbs, &host, port, &options).await?)
+        }
+
+        Some(Commands::Config { .. }) => unreachable!("config commands return early"),
+
+        Some(Commands::Profiles) => {
+            if config.profiles.is_empty() {
+                eprintln!("no profiles, add a [profiles.<name>] section with a url to the config");
+            }
+            let width = config.profiles.keys().map(|n| n.len()).max().unwrap_or(0);
+            for (name, profile) in &config.profiles {
+                println!("{name:width$}  {}", profile.url);
+            }
+            Ok(())
+        }
+
+        Some(Commands::Query {
+            prompt,
+            dry_run,
+            explain,
+            analyze,
+            show_request,
+            stats,
+            trace_sql,
+            redact_literals,
+            show_prompt,
+            confirm_phrase,
+            col_width,
+            no_schema,
+            pivot,
+            delimiter,
+            save_table,
+            out,
+            export,
+            export_format,
+        }) => {
+            // nothing is sent with --show-request, so a key isn't needed
+            let api_key = match cli.api_key {
+                None if show_request => Some("<redacted>".to_string()),
+                key => key,
+            };
+            let ai = match generator {
+                Some(generator) => generator,
+                None => Arc::new(Ai::new(cli.provider, api_key)?.with_options(ai_options)),
+            };
+            let opts = QueryOptions {
+                output: cli.output,
+                dry_run,
+                explain,
+                analyze,
+                show_request,
+                stats,
+                trace_sql,
+                redact_literals,
+                show_prompt,
+                confirm_phrase,
+                cost_threshold: cli.cost_threshold,
+                read_only: cli.read_only,
+                multi_statement: cli.multi_statement,
+                limit,
+                db_schema: cli.db_schema,
+                db_config: db_config.clone(),
+                interactive_schema: cli.interactive_schema,
+                quote_reserved: cli.quote_reserved,
+                col_width,
+                no_schema,
+                pivot,
+                save_table,
+                out,
+                export,
+                export_format,
+                csv: CsvOptions {
+                    delimiter: delimiter.unwrap_or(config.csv.delimiter),
+                    ..config.csv
+                },
+            };
+            query(
+                ai.as_ref(),
+                cli.db.as_deref(),
+                cli.read_url.as_deref(),
+                &prompt,
+                &opts,
+            )
+            .await
+        }
+
+        Some(Commands::Repl { dry_run, col_width }) => {
+            use std::io::IsTerminal;
+
+            let url = cli.db.as_deref().ok_or_else(missing_db)?;
+            let db = connect(
+                url,
+                cli.read_url.as_deref(),
+                cli.db_schema.as_deref(),
+                &db_config,
+            )
+            .await?;
+            let schema = db.schema().await?;
+            let ai = Ai::new(cli.provider, cli.api_key)?.with_options(ai_options);
+            let options = ReplOptions {
+                dry_run,
+                read_only: cli.read_only,
+                multi_statement: cli.multi_statement,
+                limit,
+                col_width,
+                show_prompt: std::io::stdin().is_terminal(),
+            };
+            Repl::new(db, schema, Box::new(ai), options)
+                .run(std::io::stdin().lock(), &mut std::io::stdout())
+                .await
+                .map_err(|e| Error::Server(e.to_string()))?;
+            Ok(())
         }
 
         None => {
+            // resolved up front so a bad profile shows its problem in the picker
+            let profiles = config
+                .profiles
+                .iter()
+                .map(|(name, profile)| ProfileEntry {
+                    name: name.clone(),
+                    url: profile.url.clone(),
+                    connect: config
+                        .profile(name)
+                        .and_then(|p| p.connect_url())
+                        .map_err(|e| e.to_string()),
+                })
+                .collect();
+
+            let compare_api_key = cli.compare.and_then(|p| config.api_key(p));
+            let settings = Settings {
+                confirm: cli.confirm || cli.confirm_all,
+                confirm_all: cli.confirm_all,
+                cache: cli.cache,
+                db_schema: cli.db_schema.clone(),
+                db_config: db_config.clone(),
+                max_prompt_chars: cli.max_prompt_chars,
+                history_size: cli.history_size,
+                interactive_schema: cli.interactive_schema,
+                db_ping_interval: cli.db_ping_interval.map(Duration::from_secs),
+                quote_reserved: cli.quote_reserved,
+                read_only: cli.read_only,
+                multi_statement: cli.multi_statement,
+                limit,
+                csv: config.csv,
+                keys: config.keys,
+                profiles,
+                provider: cli.provider,
+                api_key: cli.api_key,
+                ai_options,
+                compare: cli.compare,
+                compare_api_key,
+                compare_model: cli.compare_model,
+            };
+
             // TUI mode - check if we have a database URL
             match cli.db {
                 Some(db) => {
                     // normal mode: connect and run TUI
-                    let db_conn = Db::connect(&db).await?;
+                    let db_conn = connect(
+                        &db,
+                        cli.read_url.as_deref(),
+                        cli.db_schema.as_deref(),
+                        &db_config,
+                    )
+                    .await?;
                     let schema = db_conn.schema().await?;
 
                     let tables = schema.matches("TABLE ").count();
@@ -72,24 +663,816 @@ pub async fn run() -> Result<()> {
                         url: db.clone(),
                     };
 
-                    Ok(crate::tui::run(
-                        Some(db_conn),
-                        Some(schema),
-                        Some(db_info),
-                        cli.confirm,
-                        cli.provider,
-                        cli.api_key,
+                    Ok(
+                        crate::tui::run(Some(db_conn), Some(schema), Some(db_info), settings)
+                            .await?,
                     )
-                    .await?)
                 }
                 None => {
                     // setup mode: launch TUI with interactive setup
-                    Ok(
-                        crate::tui::run(None, None, None, cli.confirm, cli.provider, cli.api_key)
-                            .await?,
-                    )
+                    Ok(crate::tui::run(None, None, None, settings).await?)
                 }
             }
         }
     }
 }
+
+// true when clap only has its built-in default, so the config file may fill it in
+fn unset(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        None | Some(ValueSource::DefaultValue)
+    )
+}
+
+// built-in defaults < config file < env vars < flags: clap already put env
+// under flags, the file only fills what neither of them gave
+fn apply_config(cli: &mut Cli, matches: &ArgMatches, config: &Config) {
+    if unset(matches, "provider")
+        && let Some(provider) = config.provider
+    {
+        cli.provider = provider;
+    }
+    if unset(matches, "model") && config.model.is_some() {
+        cli.model = config.model.clone();
+    }
+    if unset(matches, "output")
+        && let Some(output) = config.output
+    {
+        cli.output = output;
+    }
+    if unset(matches, "db_schema") && config.db_schema.is_some() {
+        cli.db_schema = config.db_schema.clone();
+    }
+    if unset(matches, "cost_threshold") && config.cost_threshold.is_some() {
+        cli.cost_threshold = config.cost_threshold;
+    }
+    if unset(matches, "max_schema_bytes")
+        && let Some(bytes) = config.max_schema_bytes
+    {
+        cli.max_schema_bytes = bytes;
+    }
+    if unset(matches, "max_prompt_chars")
+        && let Some(chars) = config.max_prompt_chars
+    {
+        cli.max_prompt_chars = chars;
+    }
+    if unset(matches, "db_timeout")
+        && let Some(secs) = config.db_timeout.filter(|s| *s > 0)
+    {
+        cli.db_timeout = secs;
+    }
+    if unset(matches, "max_connections")
+        && let Some(n) = config.max_connections.filter(|n| *n > 0)
+    {
+        cli.max_connections = n;
+    }
+    if unset(matches, "limit")
+        && let Some(n) = config.limit.filter(|n| *n > 0)
+    {
+        cli.limit = n;
+    }
+    if unset(matches, "max_rows")
+        && let Some(n) = config.max_rows
+    {
+        cli.max_rows = n;
+    }
+    if unset(matches, "history_size")
+        && let Some(size) = config.history_size
+    {
+        cli.history_size = size;
+    }
+    if let Some(Commands::Serve {
+        query_history_size, ..
+    }) = &mut cli.command
+        && matches
+            .subcommand_matches("serve")
+            .is_some_and(|serve| unset(serve, "query_history_size"))
+        && let Some(size) = config.query_history_size
+    {
+        *query_history_size = size;
+    }
+
+    let flags = [
+        ("confirm", config.confirm, &mut cli.confirm),
+        ("cache", config.cache, &mut cli.cache),
+        ("clarify", config.clarify, &mut cli.clarify),
+        (
+            "quote_reserved",
+            config.quote_reserved,
+            &mut cli.quote_reserved,
+        ),
+        (
+            "select_only_prompt",
+            config.select_only_prompt,
+            &mut cli.select_only_prompt,
+        ),
+        ("order_hint", config.order_hint, &mut cli.order_hint),
+        ("read_only", config.read_only, &mut cli.read_only),
+        ("no_limit", config.no_limit, &mut cli.no_limit),
+        (
+            "multi_statement",
+            config.multi_statement,
+            &mut cli.multi_statement,
+        ),
+    ];
+    for (id, value, flag) in flags {
+        if unset(matches, id)
+            && let Some(value) = value
+        {
+            *flag = value;
+        }
+    }
+
+    // --api-key, then the file's key for the provider, then its env var in `Ai::new`
+    if cli.api_key.is_none() {
+        cli.api_key = config.api_key(cli.provider);
+    }
+}
+
+// `nlql config show`: one `key = value  # source` line per merged setting
+fn show_config(cli: &Cli, matches: &ArgMatches, config: &Config) -> String {
+    let path = cli.config.clone().or_else(Config::path);
+    let mut out = match &path {
+        Some(path) if path.exists() => format!("# config file: {}\n", path.display()),
+        Some(path) => format!("# config file: {} (not found)\n", path.display()),
+        None => "# config file: none\n".to_string(),
+    };
+
+    let quoted = |value: Option<&str>| value.map(|v| format!("{v:?}"));
+    let settings = [
+        (
+            "db",
+            quoted(cli.db.as_deref().map(redact_url).as_deref()),
+            false,
+        ),
+        ("profile", quoted(cli.profile.as_deref()), false),
+        (
+            "read_url",
+            quoted(cli.read_url.as_deref().map(redact_url).as_deref()),
+            false,
+        ),
+        (
+            "provider",
+            quoted(Some(&cli.provider.to_string())),
+            config.provider.is_some(),
+        ),
+        (
+            "model",
+            quoted(cli.model.as_deref()),
+            config.model.is_some(),
+        ),
+        (
+            "api_key",
+            cli.api_key.as_ref().map(|_| "\"<redacted>\"".to_string()),
+            config.api_key(cli.provider).is_some(),
+        ),
+        (
+            "output",
+            quoted(
+                cli.output
+                    .to_possible_value()
+                    .as_ref()
+                    .map(|v| v.get_name()),
+            ),
+            config.output.is_some(),
+        ),
+        (
+            "db_schema",
+            quoted(cli.db_schema.as_deref()),
+            config.db_schema.is_some(),
+        ),
+        (
+            "db_timeout",
+            Some(cli.db_timeout.to_string()),
+            config.db_timeout.is_some(),
+        ),
+        (
+            "max_connections",
+            Some(cli.max_connections.to_string()),
+            config.max_connections.is_some(),
+        ),
+        (
+            "max_rows",
+            Some(cli.max_rows.to_string()),
+            config.max_rows.is_some(),
+        ),
+        (
+            "confirm",
+            Some(cli.confirm.to_string()),
+            config.confirm.is_some(),
+        ),
+        ("cache", Some(cli.cache.to_string()), config.cache.is_some()),
+        (
+            "clarify",
+            Some(cli.clarify.to_string()),
+            config.clarify.is_some(),
+        ),
+        (
+            "quote_reserved",
+            Some(cli.quote_reserved.to_string()),
+            config.quote_reserved.is_some(),
+        ),
+        (
+            "select_only_prompt",
+            Some(cli.select_only_prompt.to_string()),
+            config.select_only_prompt.is_some(),
+        ),
+        (
+            "order_hint",
+            Some(cli.order_hint.to_string()),
+            config.order_hint.is_some(),
+        ),
+        (
+            "read_only",
+            Some(cli.read_only.to_string()),
+            config.read_only.is_some(),
+        ),
+        ("limit", Some(cli.limit.to_string()), config.limit.is_some()),
+        (
+            "no_limit",
+            Some(cli.no_limit.to_string()),
+            config.no_limit.is_some(),
+        ),
+        (
+            "multi_statement",
+            Some(cli.multi_statement.to_string()),
+            config.multi_statement.is_some(),
+        ),
+        (
+            "max_schema_bytes",
+            Some(cli.max_schema_bytes.to_string()),
+            config.max_schema_bytes.is_some(),
+        ),
+        (
+            "max_prompt_chars",
+            Some(cli.max_prompt_chars.to_string()),
+            config.max_prompt_chars.is_some(),
+        ),
+        (
+            "history_size",
+            Some(cli.history_size.to_string()),
+            config.history_size.is_some(),
+        ),
+        (
+            "query_history_size",
+            Some(
+                config
+                    .query_history_size
+                    .unwrap_or(DEFAULT_QUERY_HISTORY_SIZE)
+                    .to_string(),
+            ),
+            config.query_history_size.is_some(),
+        ),
+        (
+            "cost_threshold",
+            cli.cost_threshold.map(|n| n.to_string()),
+            config.cost_threshold.is_some(),
+        ),
+        (
+            "prompt_prefix",
+            quoted(
+                cli.prompt_prefix
+                    .as_deref()
+                    .or(config.prompt_prefix.as_deref()),
+            ),
+            cli.prompt_prefix.is_none() && config.prompt_prefix.is_some(),
+        ),
+        (
+            "prompt_suffix",
+            quoted(
+                cli.prompt_suffix
+                    .as_deref()
+                    .or(config.prompt_suffix.as_deref()),
+            ),
+            cli.prompt_suffix.is_none() && config.prompt_suffix.is_some(),
+        ),
+    ];
+
+    for (key, value, in_config) in settings {
+        // a subcommand's flags, like serve's, aren't among these matches
+        let given = matches
+            .try_contains_id(key)
+            .is_ok()
+            .then(|| matches.value_source(key))
+            .flatten();
+        let source = match given {
+            Some(ValueSource::CommandLine) => "flag",
+            Some(ValueSource::EnvVariable) => "env",
+            _ if in_config => "config",
+            // filled in from --profile
+            _ if value.is_some() && matches!(key, "db" | "read_url") => "profile",
+            _ => "default",
+        };
+        match value {
+            Some(value) => out.push_str(&format!("{key} = {value}  # {source}\n")),
+            None => out.push_str(&format!("# {key} is not set\n")),
+        }
+    }
+    out
+}
+
+// flags for a single `nlql query` run
+struct QueryOptions {
+    output: OutputFormat,
+    dry_run: bool,
+    explain: bool,
+    analyze: bool,
+    show_request: bool,
+    stats: bool,
+    trace_sql: bool,
+    redact_literals: bool,
+    show_prompt: bool,
+    confirm_phrase: Option<String>,
+    cost_threshold: Option<u64>,
+    read_only: bool,
+    multi_statement: bool,
+    limit: Option<u64>,
+    db_schema: Option<String>,
+    db_config: DbConfig,
+    interactive_schema: bool,
+    quote_reserved: bool,
+    col_width: Option<usize>,
+    no_schema: bool,
+    pivot: Option<String>,
+    save_table: Option<String>,
+    out: Option<String>,
+    export: Option<PathBuf>,
+    export_format: Option<ExportFormat>,
+    csv: CsvOptions,
+}
+
+async fn query(
+    ai: &dyn SqlGenerator,
+    db_url: Option<&str>,
+    read_url: Option<&str>,
+    prompt: &str,
+    opts: &QueryOptions,
+) -> Result<()> {
+    // found out before the model is paid for
+    if opts.read_only && opts.save_table.is_some() && opts.out.is_none() {
+        return Err(Error::Blocked(
+            "read-only mode: --save-table needs an --out file.db".to_string(),
+        )
+        .into());
+    }
+
+    // --no-schema with --dry-run or --show-request never needs the database
+    let db = if opts.no_schema && (opts.dry_run || opts.show_request) {
+        None
+    } else {
+        let db_url = db_url.ok_or_else(missing_db)?;
+        Some(connect(db_url, read_url, opts.db_schema.as_deref(), &opts.db_config).await?)
+    };
+
+    let schema = match &db {
+        Some(db) if !opts.no_schema => db.schema().await?,
+        _ => String::new(),
+    };
+    // --interactive-schema: only the picked tables go to the model, also on a retry
+    let scope = if opts.interactive_schema && !schema.is_empty() {
+        pick_tables(&schema)?
+    } else {
+        None
+    };
+    let scoped = |schema: String| match &scope {
+        Some(tables) => scope_schema(&schema, tables),
+        None => schema,
+    };
+    let schema = scoped(schema);
+    if opts.show_request {
+        let request = ai.show_request(prompt, &schema).ok_or_else(|| {
+            Error::Config("this generator doesn't send a request to show".to_string())
+        })?;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&request).unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    let candidates = generate(ai, db.as_ref(), prompt, &schema, opts).await?;
+
+    // dry run just shows what would have run
+    let Some(db) = db.filter(|_| !opts.dry_run) else {
+        println!("{}", candidates.join(";\n\n"));
+        return Ok(());
+    };
+
+    let mut sql = approve(candidates, opts)?;
+    if opts.explain {
+        return explain(&db, &sql, opts).await;
+    }
+    check_cost(&db, &sql, opts).await?;
+    if let Some(path) = &opts.export {
+        return export(&db, &sql, path, opts).await;
+    }
+    let mut result = match execute(&db, &sql, opts).await {
+        // a table the schema named is gone, so try once more with a fresh schema
+        Err(e) if e.is_missing_table() && !opts.no_schema => {
+            let fresh = scoped(db.schema().await?);
+            if fresh == schema {
+                return Err(e.into());
+            }
+            eprintln!("{e}\nschema was stale, generating again");
+            sql = approve(generate(ai, Some(&db), prompt, &fresh, opts).await?, opts)?;
+            check_cost(&db, &sql, opts).await?;
+            execute(&db, &sql, opts).await?
+        }
+        result => result?,
+    };
+    if let Some(column) = &opts.pivot {
+        result = result.pivot(column)?;
+    }
+
+    let shown_prompt = Some(prompt).filter(|_| opts.show_prompt);
+    match opts.output {
+        OutputFormat::Pretty => {
+            if let Some(prompt) = shown_prompt {
+                print!("{}", Output::prompt_comment(prompt));
+            }
+            print!("{}", Output::pretty(&sql, &result, opts.col_width))
+        }
+        OutputFormat::Raw => {
+            // every call counts, including a regeneration after a stale schema
+            let usage = ai.session_usage().filter(|u| *u != Usage::default());
+            println!("{}", Output::raw_with_usage(&sql, &result, usage))
+        }
+        OutputFormat::Csv => print!("{}", result.to_csv(&opts.csv)),
+        OutputFormat::Html => print!("{}", Output::html_with_prompt(shown_prompt, &sql, &result)),
+        OutputFormat::Markdown => print!(
+            "{}",
+            Output::markdown_with_prompt(shown_prompt, &sql, &result)
+        ),
+        OutputFormat::Jsonl => print!("{}", Output::jsonl(&result)),
+        OutputFormat::Count => println!("{}", result.row_count),
+    }
+    // pretty, html, markdown and raw already say so in what they print
+    if result.truncated
+        && matches!(
+            opts.output,
+            OutputFormat::Csv | OutputFormat::Jsonl | OutputFormat::Count
+        )
+    {
+        eprintln!(
+            "only the first {} rows were read, raise --max-rows for more",
+            result.row_count
+        );
+    }
+
+    if let Some(table) = &opts.save_table {
+        match &opts.out {
+            Some(path) => {
+                Db::open_sqlite(path)
+                    .await?
+                    .save_table(table, &result)
+                    .await?
+            }
+            None => db.save_table(table, &result).await?,
+        }
+        eprintln!("saved {} rows to table {table}", result.row_count);
+    }
+
+    Ok(())
+}
+
+// --explain: the plan instead of the rows, only --analyze actually runs the sql
+//
+// reads only either way, sqlite runs whatever follows `EXPLAIN ...;`
+async fn explain(db: &Db, sql: &str, opts: &QueryOptions) -> Result<()> {
+    if opts.analyze && db.dialect_name() == "sqlite" {
+        return Err(Error::Config("--analyze needs postgres or mysql".to_string()).into());
+    }
+    if let Some(reason) = Safety::explain_refusal(sql) {
+        return Err(Error::Blocked(reason).into());
+    }
+    let explain_sql = if opts.analyze {
+        format!("EXPLAIN ANALYZE {sql}")
+    } else {
+        format!("EXPLAIN {sql}")
+    };
+    let plan = db.execute(&explain_sql).await?;
+    print!("{}", Output::pretty(&explain_sql, &plan, opts.col_width));
+    Ok(())
+}
+
+// --export: rows go from the database to the file as they come, never all in memory
+async fn export(db: &Db, sql: &str, path: &Path, opts: &QueryOptions) -> Result<()> {
+    let format = opts
+        .export_format
+        .unwrap_or_else(|| ExportFormat::from_path(path));
+    let mut rows = db.execute_stream(sql);
+
+    let count = if path == Path::new("-") {
+        let mut out = std::io::BufWriter::new(std::io::stdout());
+        export_rows(&mut rows, format, &opts.csv, &mut out).await?
+    } else {
+        let file = std::fs::File::create(path)
+            .map_err(|e| Error::Config(format!("--export {}: {e}", path.display())))?;
+        let mut out = std::io::BufWriter::new(file);
+        export_rows(&mut rows, format, &opts.csv, &mut out).await?
+    };
+    eprintln!("exported {count} rows to {}", path.display());
+    Ok(())
+}
+
+async fn generate(
+    ai: &dyn SqlGenerator,
+    db: Option<&Db>,
+    prompt: &str,
+    schema: &str,
+    opts: &QueryOptions,
+) -> Result<Vec<String>> {
+    let generated = ai.generate_candidates(prompt, schema).await;
+    if opts.stats
+        && let Some(usage) = ai.session_usage()
+    {
+        eprintln!("tokens: {usage}");
+    }
+    let mut candidates = generated?;
+    if opts.quote_reserved
+        && let Some(db) = db
+    {
+        for sql in &mut candidates {
+            *sql = quote_reserved(sql, schema, db.dialect_name());
+        }
+    }
+    // --export and --output count want every row, not the first hundred
+    let all_rows = opts.export.is_some() || opts.output == OutputFormat::Count;
+    if let Some(limit) = opts.limit.filter(|_| !all_rows) {
+        for sql in &mut candidates {
+            if let Some(limited) = Safety::with_limit(sql, limit) {
+                *sql = limited;
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+// --output count: a write's affected rows stand in for the rows it doesn't return
+async fn execute(db: &Db, sql: &str, opts: &QueryOptions) -> Result<QueryResult, Error> {
+    let is_write = matches!(
+        StatementKind::from_sql(sql),
+        StatementKind::Insert | StatementKind::Update | StatementKind::Delete
+    );
+    if opts.output != OutputFormat::Count || !is_write {
+        return db.execute_statements(sql, opts.multi_statement).await;
+    }
+    Ok(QueryResult {
+        columns: Vec::new(),
+        rows: Vec::new(),
+        row_count: db.rows_affected(sql).await? as usize,
+        truncated: false,
+    })
+}
+
+// --cost-threshold: ask before running a plan that estimates too many rows
+async fn check_cost(db: &Db, sql: &str, opts: &QueryOptions) -> Result<()> {
+    let Some(threshold) = opts.cost_threshold else {
+        return Ok(());
+    };
+    // no estimate (sqlite, or EXPLAIN failed) means nothing to check
+    if let Ok(Some(rows)) = db.estimated_rows(sql).await
+        && rows > threshold
+    {
+        let reason = format!("the plan estimates {rows} rows, over --cost-threshold {threshold}");
+        if !ask(&format!("{reason}. run anyway?")) {
+            return Err(Error::Blocked(reason).into());
+        }
+    }
+    Ok(())
+}
+
+// pick one candidate and put it through the safety check, asking when it's dangerous
+fn approve(candidates: Vec<String>, opts: &QueryOptions) -> Result<String> {
+    let sql = pick_candidate(candidates)?;
+    if opts.trace_sql {
+        // the real sql still runs, only the echo is redacted
+        if opts.redact_literals {
+            eprintln!("sql: {}", redact_literals(&sql));
+        } else {
+            eprintln!("sql: {sql}");
+        }
+    }
+
+    // no asking, --read-only is the answer
+    if opts.read_only
+        && let Some(reason) = Safety::read_only_refusal(&sql)
+    {
+        return Err(Error::Blocked(reason).into());
+    }
+
+    let safety = Safety::check(&sql);
+    if safety.is_dangerous {
+        if !opts.trace_sql {
+            eprintln!("{sql}\n");
+        }
+        let confirmed = match &opts.confirm_phrase {
+            Some(phrase) if safety.is_destructive => {
+                let phrase = match phrase.as_str() {
+                    "" => Safety::target_table(&sql).unwrap_or_else(|| "yes I am sure".to_string()),
+                    phrase => phrase.to_string(),
+                };
+                ask_phrase(&safety.reason, &phrase)
+            }
+            _ => ask(&format!("{}. run anyway?", safety.reason)),
+        };
+        if !confirmed {
+            return Err(Error::Blocked(safety.reason).into());
+        }
+    } else if let Some(warning) = &safety.warning {
+        eprintln!("warning: {warning}");
+    }
+    Ok(sql)
+}
+
+// the primary, plus the --read-url replica and --db-schema when given
+async fn connect(
+    url: &str,
+    read_url: Option<&str>,
+    db_schema: Option<&str>,
+    config: &DbConfig,
+) -> Result<Db, Error> {
+    let mut db = Db::connect_with(url, config).await?;
+    if let Some(read_url) = read_url {
+        db = db.with_read_url(read_url).await?;
+    }
+    match db_schema {
+        Some(name) => db.with_db_schema(name),
+        None => Ok(db),
+    }
+}
+
+fn parse_bound(s: &str) -> std::result::Result<NaiveDate, String> {
+    parse_time_bound(s, chrono::Local::now().date_naive())
+}
+
+fn parse_delimiter(s: &str) -> std::result::Result<char, String> {
+    match s {
+        "\\t" | "tab" => Ok('\t'),
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!("delimiter must be a single character, got '{s}'")),
+            }
+        }
+    }
+}
+
+fn parse_named_db(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, url)) if !name.is_empty() && !url.is_empty() => {
+            Ok((name.to_string(), url.to_string()))
+        }
+        _ => Err(format!("expected NAME=URL, got {s}")),
+    }
+}
+
+fn parse_header(s: &str) -> std::result::Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {s}"))?;
+    let name = name.trim();
+    reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("'{name}' isn't a valid header name"))?;
+    reqwest::header::HeaderValue::from_str(value)
+        .map_err(|_| format!("the value for '{name}' isn't a valid header value"))?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn missing_db() -> Error {
+    Error::Config("database url required (--db or DATABASE_URL)".to_string())
+}
+
+// a hidden prompt on a terminal, otherwise the first line of stdin
+fn read_password() -> Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        let mut line = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| Error::Config(format!("read password: {e}")))?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    eprint!("password: ");
+    std::io::stderr().flush().ok();
+
+    enable_raw_mode().map_err(|e| Error::Config(e.to_string()))?;
+    let mut password = String::new();
+    let read = loop {
+        match event::read() {
+            Ok(Event::Key(KeyEvent {
+                code, modifiers, ..
+            })) => match code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(Error::Config("password prompt cancelled".to_string()));
+                }
+                KeyCode::Char(c) => password.push(c),
+                KeyCode::Backspace => {
+                    password.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(Error::Config(e.to_string())),
+        }
+    };
+    disable_raw_mode().ok();
+    eprintln!();
+
+    read?;
+    Ok(password)
+}
+
+// --interactive-schema: number the tables on stderr and keep the picked ones
+//
+// `None` (just enter) means every table
+fn pick_tables(schema: &str) -> Result<Option<Vec<String>>> {
+    let tables = schema_tables(schema);
+    for (i, table) in tables.iter().enumerate() {
+        eprintln!("[{}] {table}", i + 1);
+    }
+    eprint!("use which tables? [e.g. 1,3-5, enter for all] ");
+    std::io::stderr().flush().ok();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok();
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(None);
+    }
+
+    let mut picked: Vec<String> = Vec::new();
+    for part in answer.split([',', ' ']).filter(|p| !p.is_empty()) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let (start, end) = start
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .zip(end.trim().parse::<usize>().ok())
+            .filter(|&(start, end)| 1 <= start && start <= end && end <= tables.len())
+            .ok_or_else(|| Error::Config(format!("no table numbered '{part}'")))?;
+        for table in &tables[start - 1..end] {
+            if !picked.iter().any(|p| p == table) {
+                picked.push(table.to_string());
+            }
+        }
+    }
+    eprintln!("using {} of {} tables", picked.len(), tables.len());
+    Ok(Some(picked))
+}
+
+// several alternatives: list them on stderr and let the user choose by number
+fn pick_candidate(mut candidates: Vec<String>) -> Result<String> {
+    if candidates.len() == 1 {
+        return Ok(candidates.remove(0));
+    }
+
+    for (i, sql) in candidates.iter().enumerate() {
+        eprintln!("[{}] {sql}\n", i + 1);
+    }
+    eprint!("run which? [1-{}, enter for 1] ", candidates.len());
+    std::io::stderr().flush().ok();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok();
+    let answer = answer.trim();
+    let choice = if answer.is_empty() {
+        1
+    } else {
+        answer
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=candidates.len()).contains(n))
+            .ok_or_else(|| Error::Config(format!("no candidate numbered '{answer}'")))?
+    };
+
+    Ok(candidates.remove(choice - 1))
+}
+
+// for --confirm-phrase: only the exact phrase goes ahead
+fn ask_phrase(reason: &str, phrase: &str) -> bool {
+    eprint!("{reason}. type '{phrase}' to run anyway: ");
+    std::io::stderr().flush().ok();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok();
+    answer.trim() == phrase
+}
+
+// y/N prompt on stderr, anything but "y" means no
+fn ask(question: &str) -> bool {
+    eprint!("{question} [y/N] ");
+    std::io::stderr().flush().ok();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok();
+    answer.trim().eq_ignore_ascii_case("y")
+}
@@ -1,15 +1,25 @@
 // ai providers - turns plain english into sql
 
 use crate::Error;
+use crate::core::time::date_context;
+use crate::core::{StatementKind, timestamp_columns};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 /// which ai provider to use
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum Provider {
     #[default]
     Claude,
     #[value(alias = "chatgpt", alias = "gpt")]
+    #[serde(alias = "chatgpt", alias = "gpt")]
     OpenAI,
+    #[value(alias = "google")]
+    #[serde(alias = "google")]
+    Gemini,
+    /// a local `ollama serve`, no api key needed
+    Ollama,
 }
 
 impl std::fmt::Display for Provider {
@@ -17,6 +27,8 @@ impl std::fmt::Display for Provider {
         match self {
             Provider::Claude => write!(f, "claude"),
             Provider::OpenAI => write!(f, "openai"),
+            Provider::Gemini => write!(f, "gemini"),
+            Provider::Ollama => write!(f, "ollama"),
         }
     }
 }
@@ -28,16 +40,552 @@ impl std::str::FromStr for Provider {
         match s.to_lowercase().as_str() {
             "claude" | "anthropic" => Ok(Provider::Claude),
             "openai" | "chatgpt" | "gpt" => Ok(Provider::OpenAI),
+            "gemini" | "google" => Ok(Provider::Gemini),
+            "ollama" => Ok(Provider::Ollama),
             _ => Err(format!("unknown provider: {s}")),
         }
     }
 }
 
+/// prefix the model uses when it wants a clearer question instead of guessing
+const CLARIFY_SENTINEL: &str = "CLARIFY:";
+
+/// prefix the model uses to turn down a write under --select-only-prompt
+const READ_ONLY_SENTINEL: &str = "READ_ONLY:";
+
+/// default cap on schema context, keeps big databases under the model's limit
+pub const DEFAULT_MAX_SCHEMA_BYTES: usize = 20 * 1024;
+
+/// `anthropic-version` header sent to claude unless overridden
+pub const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// where ollama listens unless `OLLAMA_HOST` says otherwise
+const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+
+/// knobs that change what we ask the model for
+#[derive(Debug, Clone)]
+pub struct AiOptions {
+    /// let the model ask a clarifying question instead of guessing
+    pub clarify: bool,
+    /// schema context is cut down to fit this many bytes
+    pub max_schema_bytes: usize,
+    /// standing instructions put before every prompt
+    pub prompt_prefix: Option<String>,
+    /// standing instructions put after every prompt
+    pub prompt_suffix: Option<String>,
+    /// only look at rows from this date on
+    pub since: Option<NaiveDate>,
+    /// only look at rows before this date
+    pub until: Option<NaiveDate>,
+    /// let the model offer alternative queries instead of just one
+    pub candidates: bool,
+    /// model name for whichever provider is used, wins over the per-provider ones
+    pub model: Option<String>,
+    /// claude model when `model` isn't set, `None` uses the built-in default
+    pub claude_model: Option<String>,
+    /// openai model when `model` isn't set
+    pub openai_model: Option<String>,
+    /// gemini model when `model` isn't set
+    pub gemini_model: Option<String>,
+    /// ollama model when `model` isn't set, e.g. `sqlcoder`
+    pub ollama_model: Option<String>,
+    /// `anthropic-version` header, `None` sends `DEFAULT_ANTHROPIC_VERSION`
+    pub anthropic_version: Option<String>,
+    /// extra http headers for every provider request, e.g. for an llm gateway
+    pub headers: Vec<(String, String)>,
+    /// tell the model to only write SELECTs, and refuse anything else it sends back
+    pub select_only: bool,
+    /// for "recent" / "latest" requests, point the model at the schema's created_at style columns
+    pub order_hint: bool,
+}
+
+impl Default for AiOptions {
+    fn default() -> Self {
+        Self {
+            clarify: false,
+            max_schema_bytes: DEFAULT_MAX_SCHEMA_BYTES,
+            prompt_prefix: None,
+            prompt_suffix: None,
+            since: None,
+            until: None,
+            candidates: false,
+            model: None,
+            claude_model: None,
+            openai_model: None,
+            gemini_model: None,
+            ollama_model: None,
+            anthropic_version: None,
+            headers: Vec::new(),
+            select_only: false,
+            order_hint: false,
+        }
+    }
+}
+
+/// tokens a provider billed for a call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+impl std::ops::Sub for Usage {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.saturating_sub(other.input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(other.output_tokens),
+        }
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in / {} out",
+            short_count(self.input_tokens),
+            short_count(self.output_tokens)
+        )
+    }
+}
+
+// 950, 1.2k, 3.4M
+fn short_count(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", n as f64 / 1_000.0),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+/// what one call to the model gave back
+#[derive(Debug, Clone)]
+pub struct GeneratedSql {
+    pub sql: String,
+    /// `None` when the provider didn't report it
+    pub usage: Option<Usage>,
+    /// the model's own 0-100 guess from its `-- confidence: N` line, if it gave one
+    pub confidence: Option<u8>,
+}
+
+impl Provider {
+    /// model used when neither --model nor the config names one
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            Provider::Claude => "claude-sonnet-4-20250514",
+            Provider::OpenAI => "gpt-4o",
+            Provider::Gemini => "gemini-1.5-pro",
+            Provider::Ollama => "llama3",
+        }
+    }
+
+    /// the sql and usage out of a successful response body, an error if the
+    /// model gave back nothing usable
+    pub fn parse_response(&self, body: &str) -> Result<GeneratedSql, Error> {
+        let (text, usage) = match self {
+            Provider::Claude => parse_claude(body),
+            Provider::OpenAI => parse_openai(body),
+            Provider::Gemini => parse_gemini(body),
+            Provider::Ollama => parse_ollama(body),
+        }
+        .map_err(|e| Error::Ai(format!("{self} response: {e}")))?;
+        generated_sql(text, usage)
+    }
+
+    /// text and usage out of one line of a streamed response, see `SqlStream`
+    fn stream_delta(&self, line: &str) -> Result<StreamDelta, String> {
+        let line = line.trim();
+        let data = line.strip_prefix("data:").unwrap_or(line).trim();
+        // sse framing and the openai terminator carry nothing
+        if data.is_empty()
+            || data == "[DONE]"
+            || line.starts_with("event:")
+            || line.starts_with(':')
+        {
+            return Ok(StreamDelta::default());
+        }
+        // a malformed or cut off line is skipped, the rest can still make sense
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(data) else {
+            return Ok(StreamDelta::default());
+        };
+        if let Some(error) = value.get("error").filter(|e| !e.is_null()) {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(format!("{self}: {message}"));
+        }
+
+        let text = |pointer: &str| value.pointer(pointer).and_then(|t| t.as_str());
+        let count = |pointer: &str| value.pointer(pointer).and_then(|n| n.as_u64());
+        let delta = match self {
+            // input tokens come first in message_start, output tokens last in message_delta
+            Provider::Claude => StreamDelta {
+                text: text("/delta/text")
+                    .filter(|_| text("/delta/type") == Some("text_delta"))
+                    .map(str::to_string),
+                input_tokens: count("/message/usage/input_tokens"),
+                output_tokens: count("/usage/output_tokens"),
+            },
+            Provider::OpenAI => StreamDelta {
+                text: text("/choices/0/delta/content").map(str::to_string),
+                input_tokens: count("/usage/prompt_tokens"),
+                output_tokens: count("/usage/completion_tokens"),
+            },
+            Provider::Gemini => StreamDelta {
+                text: value
+                    .pointer("/candidates/0/content/parts")
+                    .and_then(|parts| parts.as_array())
+                    .map(|parts| {
+                        parts
+                            .iter()
+                            .filter_map(|p| p.get("text")?.as_str())
+                            .collect()
+                    }),
+                input_tokens: count("/usageMetadata/promptTokenCount"),
+                output_tokens: count("/usageMetadata/candidatesTokenCount"),
+            },
+            Provider::Ollama => StreamDelta {
+                text: text("/response").map(str::to_string),
+                input_tokens: count("/prompt_eval_count"),
+                output_tokens: count("/eval_count"),
+            },
+        };
+        Ok(delta)
+    }
+}
+
+// what a line of a streamed response adds
+#[derive(Debug, Default)]
+struct StreamDelta {
+    text: Option<String>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+// the sql out of a whole reply, an error if the model gave back nothing usable
+fn generated_sql(text: Option<String>, usage: Option<Usage>) -> Result<GeneratedSql, Error> {
+    // the marker can sit inside or after a fence, so it goes before unwrapping
+    let (text, confidence) = take_confidence(text.as_deref().unwrap_or_default());
+
+    // blank or an empty fence, running it would only give a confusing db error
+    let sql = extract_sql(&text);
+    if sql.is_empty() {
+        return Err(Error::Ai("model returned no SQL".to_string()));
+    }
+    Ok(GeneratedSql {
+        sql,
+        usage,
+        confidence,
+    })
+}
+
+/// a reply as the model writes it, from `Ai::generate_sql_stream`
+pub struct SqlStream<'a> {
+    ai: &'a Ai,
+    response: reqwest::Response,
+    // bytes after the last full line
+    pending: Vec<u8>,
+    text: String,
+    usage: Option<Usage>,
+    ended: bool,
+}
+
+impl SqlStream<'_> {
+    /// the next piece of text, `None` once the reply is complete
+    pub async fn next(&mut self) -> Option<Result<String, Error>> {
+        loop {
+            if let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                match self.line(&String::from_utf8_lossy(&line)) {
+                    Ok(Some(text)) => return Some(Ok(text)),
+                    Ok(None) => continue,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            if self.ended {
+                return None;
+            }
+            match self.response.chunk().await {
+                Ok(Some(bytes)) => self.pending.extend_from_slice(&bytes),
+                Ok(None) => {
+                    self.ended = true;
+                    // a last line without a newline still counts
+                    if !self.pending.is_empty() {
+                        self.pending.push(b'\n');
+                    }
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+
+    /// read what's left and check the whole reply like `Ai::generate` does
+    pub async fn finish(mut self) -> Result<GeneratedSql, Error> {
+        while let Some(text) = self.next().await {
+            text?;
+        }
+        self.ai.accept(generated_sql(Some(self.text), self.usage)?)
+    }
+
+    fn line(&mut self, line: &str) -> Result<Option<String>, Error> {
+        let delta = self.ai.provider.stream_delta(line).map_err(Error::Ai)?;
+        if delta.input_tokens.is_some() || delta.output_tokens.is_some() {
+            let usage = self.usage.get_or_insert_default();
+            // counts are running totals, or only one half of them
+            if let Some(input) = delta.input_tokens {
+                usage.input_tokens = input;
+            }
+            if let Some(output) = delta.output_tokens {
+                usage.output_tokens = output;
+            }
+        }
+        Ok(delta.text.filter(|t| !t.is_empty()).inspect(|text| {
+            self.text.push_str(text);
+        }))
+    }
+}
+
+fn parse_claude(body: &str) -> Result<(Option<String>, Option<Usage>), serde_json::Error> {
+    #[derive(Deserialize)]
+    struct Response {
+        content: Vec<Content>,
+        usage: Option<Usage>,
+    }
+
+    #[derive(Deserialize)]
+    struct Content {
+        #[serde(rename = "type", default)]
+        kind: String,
+        #[serde(default)]
+        text: String,
+    }
+
+    let response: Response = serde_json::from_str(body)?;
+    // extended thinking puts `thinking` blocks before the answer, and tool use
+    // can split the answer over several text blocks: take the last one holding sql
+    let texts: Vec<String> = response
+        .content
+        .into_iter()
+        .filter(|c| c.kind.is_empty() || c.kind == "text")
+        .map(|c| c.text)
+        .collect();
+    let text = texts
+        .iter()
+        .rev()
+        .find(|text| looks_like_sql(text))
+        .or(texts.last())
+        .cloned();
+    Ok((text, response.usage))
+}
+
+// the reply without its `-- confidence: N` lines, and the last N given
+fn take_confidence(text: &str) -> (String, Option<u8>) {
+    let mut confidence = None;
+    let mut kept = Vec::new();
+    for line in text.lines() {
+        // also a trailing comment, `SELECT 1; -- confidence: 80`
+        let marker = line
+            .rfind("--")
+            .and_then(|at| Some((at, parse_confidence(&line[at + 2..])?)));
+        match marker {
+            Some((at, value)) => {
+                confidence = Some(value);
+                let before = line[..at].trim_end();
+                if !before.is_empty() {
+                    kept.push(before);
+                }
+            }
+            None => kept.push(line),
+        }
+    }
+    (kept.join("\n"), confidence)
+}
+
+// ` confidence: 85` or `confidence: 85%`
+fn parse_confidence(comment: &str) -> Option<u8> {
+    let (key, value) = comment.split_once(':')?;
+    if !key.trim().eq_ignore_ascii_case("confidence") {
+        return None;
+    }
+    let value: u8 = value.trim().trim_end_matches('%').trim().parse().ok()?;
+    Some(value.min(100))
+}
+
+// starts with a statement keyword once unwrapped
+fn looks_like_sql(text: &str) -> bool {
+    let sql = extract_sql(text);
+    let word: String = sql
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    STATEMENT_START.contains(&word.to_uppercase().as_str())
+}
+
+fn parse_openai(body: &str) -> Result<(Option<String>, Option<Usage>), serde_json::Error> {
+    #[derive(Deserialize)]
+    struct Response {
+        choices: Vec<Choice>,
+        usage: Option<OpenAiUsage>,
+    }
+
+    #[derive(Deserialize)]
+    struct OpenAiUsage {
+        prompt_tokens: u64,
+        completion_tokens: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct Choice {
+        message: ResponseMessage,
+    }
+
+    #[derive(Deserialize)]
+    struct ResponseMessage {
+        // null when the model refused or only called tools
+        content: Option<String>,
+    }
+
+    let response: Response = serde_json::from_str(body)?;
+    let text = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|c| c.message.content);
+    let usage = response.usage.map(|u| Usage {
+        input_tokens: u.prompt_tokens,
+        output_tokens: u.completion_tokens,
+    });
+    Ok((text, usage))
+}
+
+fn parse_gemini(body: &str) -> Result<(Option<String>, Option<Usage>), serde_json::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Response {
+        #[serde(default)]
+        candidates: Vec<Candidate>,
+        usage_metadata: Option<GeminiUsage>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct GeminiUsage {
+        #[serde(default)]
+        prompt_token_count: u64,
+        #[serde(default)]
+        candidates_token_count: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct Candidate {
+        // missing when the answer was blocked by a safety filter
+        content: Option<CandidateContent>,
+    }
+
+    #[derive(Deserialize)]
+    struct CandidateContent {
+        #[serde(default)]
+        parts: Vec<Part>,
+    }
+
+    #[derive(Deserialize)]
+    struct Part {
+        #[serde(default)]
+        text: String,
+    }
+
+    let response: Response = serde_json::from_str(body)?;
+    // a long answer can come back split over several parts
+    let text = response
+        .candidates
+        .into_iter()
+        .next()
+        .and_then(|c| c.content)
+        .map(|c| c.parts.into_iter().map(|p| p.text).collect::<String>());
+    let usage = response.usage_metadata.map(|u| Usage {
+        input_tokens: u.prompt_token_count,
+        output_tokens: u.candidates_token_count,
+    });
+    Ok((text, usage))
+}
+
+fn parse_ollama(body: &str) -> Result<(Option<String>, Option<Usage>), serde_json::Error> {
+    #[derive(Deserialize)]
+    struct Response {
+        response: Option<String>,
+        prompt_eval_count: Option<u64>,
+        eval_count: Option<u64>,
+    }
+
+    let response: Response = serde_json::from_str(body)?;
+    // counts are left out when ollama answers from its prompt cache
+    let usage = match (response.prompt_eval_count, response.eval_count) {
+        (None, None) => None,
+        (input, output) => Some(Usage {
+            input_tokens: input.unwrap_or_default(),
+            output_tokens: output.unwrap_or_default(),
+        }),
+    };
+    Ok((response.response, usage))
+}
+
+// `OLLAMA_HOST` can be a bare `host:port`, like ollama itself accepts
+fn ollama_host() -> String {
+    let host = std::env::var("OLLAMA_HOST")
+        .ok()
+        .filter(|h| !h.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_OLLAMA_HOST.to_string());
+    let host = host.trim().trim_end_matches('/');
+    if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("http://{host}")
+    }
+}
+
+// everything that goes to a provider, built once so --show-request matches what's sent
+struct ProviderRequest {
+    url: String,
+    headers: Vec<(String, String)>,
+    // header holding the api key, hidden by --show-request
+    secret_header: Option<&'static str>,
+    body: serde_json::Value,
+}
+
+// plain reads only; a WITH that ends in or hides a write doesn't count
+fn is_select(sql: &str) -> bool {
+    StatementKind::from_sql(sql) == StatementKind::Select
+}
+
+// gateway tokens passed with --ai-header shouldn't leak through --show-request either
+fn is_secret(header: &str) -> bool {
+    let header = header.to_lowercase();
+    ["auth", "token", "key", "secret"]
+        .iter()
+        .any(|word| header.contains(word))
+}
+
 /// ai client that can use different providers
 pub struct Ai {
     provider: Provider,
     client: reqwest::Client,
     api_key: String,
+    options: AiOptions,
+    // running total over every call this client made
+    session_usage: std::sync::Mutex<Usage>,
+    // from the latest answer that gave one
+    last_confidence: std::sync::Mutex<Option<u8>>,
 }
 
 impl Ai {
@@ -56,30 +604,257 @@ impl Ai {
                     provider: "openai",
                     env_var: "OPENAI_API_KEY",
                 })?,
+            Provider::Gemini => api_key
+                .or_else(|| std::env::var("GEMINI_API_KEY").ok())
+                .or_else(|| std::env::var("GOOGLE_API_KEY").ok())
+                .ok_or(Error::MissingApiKey {
+                    provider: "gemini",
+                    env_var: "GEMINI_API_KEY",
+                })?,
+            // only sent if given, for an ollama behind an auth proxy
+            Provider::Ollama => api_key.unwrap_or_default(),
         };
 
         Ok(Self {
             provider,
             client: reqwest::Client::new(),
             api_key,
+            options: AiOptions::default(),
+            session_usage: std::sync::Mutex::default(),
+            last_confidence: std::sync::Mutex::default(),
         })
     }
 
+    pub fn with_options(mut self, options: AiOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn provider(&self) -> Provider {
         self.provider
     }
 
+    /// the model we'll ask, --model or the provider's default
+    pub fn model(&self) -> &str {
+        let configured = match self.provider {
+            Provider::Claude => &self.options.claude_model,
+            Provider::OpenAI => &self.options.openai_model,
+            Provider::Gemini => &self.options.gemini_model,
+            Provider::Ollama => &self.options.ollama_model,
+        };
+        self.options
+            .model
+            .as_deref()
+            .or(configured.as_deref())
+            .unwrap_or(self.provider.default_model())
+    }
+
     pub async fn generate_sql(&self, prompt: &str, schema: &str) -> Result<String, Error> {
+        Ok(self.generate(prompt, schema).await?.sql)
+    }
+
+    /// tokens used by every call so far
+    pub fn session_usage(&self) -> Usage {
+        self.session_usage.lock().map(|u| *u).unwrap_or_default()
+    }
+
+    /// the confidence the latest answer reported, `None` if it didn't
+    pub fn last_confidence(&self) -> Option<u8> {
+        self.last_confidence.lock().map(|c| *c).unwrap_or_default()
+    }
+
+    /// like `generate_sql`, plus the tokens the call used
+    pub async fn generate(&self, prompt: &str, schema: &str) -> Result<GeneratedSql, Error> {
+        let prompt = self.wrap_prompt(prompt);
+        let generated = match self.provider {
+            Provider::Claude => self.call_claude(&prompt, schema).await?,
+            Provider::OpenAI => self.call_openai(&prompt, schema).await?,
+            Provider::Gemini => self.call_gemini(&prompt, schema).await?,
+            Provider::Ollama => self.call_ollama(&prompt, schema).await?,
+        };
+        self.accept(generated)
+    }
+
+    /// like `generate`, but the reply comes in piece by piece as the model writes it
+    pub async fn generate_sql_stream(
+        &self,
+        prompt: &str,
+        schema: &str,
+    ) -> Result<SqlStream<'_>, Error> {
+        let response = self
+            .send(self.stream_request(&self.wrap_prompt(prompt), schema))
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await?;
+            return Err(Error::Ai(format!("{} {status}: {error}", self.provider)));
+        }
+
+        Ok(SqlStream {
+            ai: self,
+            response,
+            pending: Vec::new(),
+            text: String::new(),
+            usage: None,
+            ended: false,
+        })
+    }
+
+    /// the streaming version of `generate_candidates`, every piece of text goes to `chunks`
+    pub async fn generate_candidates_streaming(
+        &self,
+        prompt: &str,
+        schema: &str,
+        chunks: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> Result<Vec<String>, Error> {
+        let mut stream = self.generate_sql_stream(prompt, schema).await?;
+        while let Some(text) = stream.next().await {
+            // nobody listening is fine, the answer still counts
+            let _ = chunks.send(text?);
+        }
+        Ok(self.candidates(stream.finish().await?.sql))
+    }
+
+    // the checks every answer goes through, streamed or not
+    fn accept(&self, generated: GeneratedSql) -> Result<GeneratedSql, Error> {
+        let GeneratedSql {
+            sql,
+            usage,
+            confidence,
+        } = generated;
+
+        // counted even if we end up refusing the answer, it was still billed
+        if let Some(usage) = usage
+            && let Ok(mut total) = self.session_usage.lock()
+        {
+            *total += usage;
+        }
+        if let Ok(mut last) = self.last_confidence.lock() {
+            *last = confidence;
+        }
+
+        // the model would rather ask than guess
+        if let Some(question) = sql.strip_prefix(CLARIFY_SENTINEL) {
+            return Err(Error::NeedsClarification(question.trim().to_string()));
+        }
+
+        if self.options.select_only {
+            if let Some(reason) = sql.strip_prefix(READ_ONLY_SENTINEL) {
+                return Err(Error::Blocked(reason.trim().to_string()));
+            }
+            // the prompt asks nicely, this makes sure
+            if !is_select(&sql) {
+                return Err(Error::Blocked(
+                    "--select-only-prompt: the model returned a statement that isn't a SELECT"
+                        .to_string(),
+                ));
+            }
+        }
+
+        Ok(GeneratedSql {
+            sql,
+            usage,
+            confidence,
+        })
+    }
+
+    /// like `generate_sql`, but with --candidates every alternative the model gave
+    pub async fn generate_candidates(
+        &self,
+        prompt: &str,
+        schema: &str,
+    ) -> Result<Vec<String>, Error> {
+        let sql = self.generate_sql(prompt, schema).await?;
+        Ok(self.candidates(sql))
+    }
+
+    // with --candidates, every alternative in one answer
+    fn candidates(&self, sql: String) -> Vec<String> {
+        if !self.options.candidates {
+            return vec![sql];
+        }
+
+        let candidates = split_candidates(&sql);
+        if candidates.len() > 1 {
+            candidates
+        } else {
+            vec![sql]
+        }
+    }
+
+    /// the exact request `generate_sql` would send, with the api key redacted
+    pub fn show_request(&self, prompt: &str, schema: &str) -> serde_json::Value {
+        let request = self.request(&self.wrap_prompt(prompt), schema);
+        let headers: serde_json::Map<String, serde_json::Value> = request
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if request.secret_header == Some(name.as_str()) || is_secret(name) {
+                    "<redacted>".to_string()
+                } else {
+                    value.clone()
+                };
+                (name.clone(), value.into())
+            })
+            .collect();
+
+        serde_json::json!({
+            "url": request.url,
+            "headers": headers,
+            "body": request.body,
+        })
+    }
+
+    fn request(&self, prompt: &str, schema: &str) -> ProviderRequest {
+        let mut request = match self.provider {
+            Provider::Claude => self.claude_request(prompt, schema),
+            Provider::OpenAI => self.openai_request(prompt, schema),
+            Provider::Gemini => self.gemini_request(prompt, schema),
+            Provider::Ollama => self.ollama_request(prompt, schema),
+        };
+
+        // --ai-header replaces a built-in header of the same name instead of doubling it
+        for (name, value) in &self.options.headers {
+            request
+                .headers
+                .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            request.headers.push((name.clone(), value.clone()));
+        }
+        request
+    }
+
+    // `request`, asking the provider to send the answer as it's written
+    fn stream_request(&self, prompt: &str, schema: &str) -> ProviderRequest {
+        let mut request = self.request(prompt, schema);
         match self.provider {
-            Provider::Claude => self.call_claude(prompt, schema).await,
-            Provider::OpenAI => self.call_openai(prompt, schema).await,
+            Provider::Claude | Provider::Ollama => request.body["stream"] = true.into(),
+            Provider::OpenAI => {
+                request.body["stream"] = true.into();
+                // otherwise a streamed reply reports no tokens
+                request.body["stream_options"] = serde_json::json!({ "include_usage": true });
+            }
+            Provider::Gemini => {
+                request.url = request
+                    .url
+                    .replace(":generateContent", ":streamGenerateContent?alt=sse")
+            }
+        }
+        request
+    }
+
+    async fn send(&self, request: ProviderRequest) -> Result<reqwest::Response, Error> {
+        let mut builder = self.client.post(request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
         }
+        Ok(builder.json(&request.body).send().await?)
     }
 
-    async fn call_claude(&self, prompt: &str, schema: &str) -> Result<String, Error> {
+    fn claude_request(&self, prompt: &str, schema: &str) -> ProviderRequest {
         #[derive(Serialize)]
-        struct Request {
-            model: &'static str,
+        struct Request<'a> {
+            model: &'a str,
             max_tokens: u32,
             messages: Vec<Message>,
             system: String,
@@ -91,20 +866,10 @@ impl Ai {
             content: String,
         }
 
-        #[derive(Deserialize)]
-        struct Response {
-            content: Vec<Content>,
-        }
-
-        #[derive(Deserialize)]
-        struct Content {
-            text: String,
-        }
-
-        let system = self.system_prompt(schema);
+        let system = self.system_prompt(prompt, schema);
 
         let request = Request {
-            model: "claude-sonnet-4-20250514",
+            model: self.model(),
             max_tokens: 1024,
             system,
             messages: vec![Message {
@@ -113,15 +878,26 @@ impl Ai {
             }],
         };
 
-        let response = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+        ProviderRequest {
+            url: "https://api.anthropic.com/v1/messages".to_string(),
+            headers: vec![
+                ("x-api-key".to_string(), self.api_key.clone()),
+                (
+                    "anthropic-version".to_string(),
+                    self.options
+                        .anthropic_version
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_ANTHROPIC_VERSION.to_string()),
+                ),
+                ("content-type".to_string(), "application/json".to_string()),
+            ],
+            secret_header: Some("x-api-key"),
+            body: serde_json::to_value(request).unwrap_or_default(),
+        }
+    }
+
+    async fn call_claude(&self, prompt: &str, schema: &str) -> Result<GeneratedSql, Error> {
+        let response = self.send(self.claude_request(prompt, schema)).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -129,20 +905,13 @@ impl Ai {
             return Err(Error::Ai(format!("claude {status}: {error}")));
         }
 
-        let response: Response = response.json().await?;
-        let sql = response
-            .content
-            .first()
-            .map(|c| c.text.trim().to_string())
-            .unwrap_or_default();
-
-        Ok(self.clean_sql(&sql))
+        Provider::Claude.parse_response(&response.text().await?)
     }
 
-    async fn call_openai(&self, prompt: &str, schema: &str) -> Result<String, Error> {
+    fn openai_request(&self, prompt: &str, schema: &str) -> ProviderRequest {
         #[derive(Serialize)]
-        struct Request {
-            model: &'static str,
+        struct Request<'a> {
+            model: &'a str,
             messages: Vec<Message>,
             max_tokens: u32,
         }
@@ -153,25 +922,10 @@ impl Ai {
             content: String,
         }
 
-        #[derive(Deserialize)]
-        struct Response {
-            choices: Vec<Choice>,
-        }
-
-        #[derive(Deserialize)]
-        struct Choice {
-            message: ResponseMessage,
-        }
-
-        #[derive(Deserialize)]
-        struct ResponseMessage {
-            content: String,
-        }
-
-        let system = self.system_prompt(schema);
+        let system = self.system_prompt(prompt, schema);
 
         let request = Request {
-            model: "gpt-4o",
+            model: self.model(),
             max_tokens: 1024,
             messages: vec![
                 Message {
@@ -185,14 +939,22 @@ impl Ai {
             ],
         };
 
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+        ProviderRequest {
+            url: "https://api.openai.com/v1/chat/completions".to_string(),
+            headers: vec![
+                (
+                    "Authorization".to_string(),
+                    format!("Bearer {}", self.api_key),
+                ),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ],
+            secret_header: Some("Authorization"),
+            body: serde_json::to_value(request).unwrap_or_default(),
+        }
+    }
+
+    async fn call_openai(&self, prompt: &str, schema: &str) -> Result<GeneratedSql, Error> {
+        let response = self.send(self.openai_request(prompt, schema)).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -200,18 +962,147 @@ impl Ai {
             return Err(Error::Ai(format!("openai {status}: {error}")));
         }
 
-        let response: Response = response.json().await?;
-        let sql = response
-            .choices
-            .first()
-            .map(|c| c.message.content.trim().to_string())
-            .unwrap_or_default();
+        Provider::OpenAI.parse_response(&response.text().await?)
+    }
+
+    fn gemini_request(&self, prompt: &str, schema: &str) -> ProviderRequest {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Request {
+            contents: Vec<Content>,
+            generation_config: GenerationConfig,
+        }
+
+        #[derive(Serialize)]
+        struct Content {
+            role: &'static str,
+            parts: Vec<Part>,
+        }
+
+        #[derive(Serialize)]
+        struct Part {
+            text: String,
+        }
 
-        Ok(self.clean_sql(&sql))
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct GenerationConfig {
+            max_output_tokens: u32,
+        }
+
+        // gemini has no system role, so the instructions go in as the first part
+        let system = self.system_prompt(prompt, schema);
+
+        let request = Request {
+            contents: vec![Content {
+                role: "user",
+                parts: vec![
+                    Part { text: system },
+                    Part {
+                        text: prompt.to_string(),
+                    },
+                ],
+            }],
+            generation_config: GenerationConfig {
+                max_output_tokens: 1024,
+            },
+        };
+
+        ProviderRequest {
+            url: format!(
+                "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+                self.model()
+            ),
+            headers: vec![
+                ("x-goog-api-key".to_string(), self.api_key.clone()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ],
+            secret_header: Some("x-goog-api-key"),
+            body: serde_json::to_value(request).unwrap_or_default(),
+        }
     }
 
-    fn system_prompt(&self, schema: &str) -> String {
-        format!(
+    async fn call_gemini(&self, prompt: &str, schema: &str) -> Result<GeneratedSql, Error> {
+        let response = self.send(self.gemini_request(prompt, schema)).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await?;
+            return Err(Error::Ai(format!("gemini {status}: {error}")));
+        }
+
+        Provider::Gemini.parse_response(&response.text().await?)
+    }
+
+    fn ollama_request(&self, prompt: &str, schema: &str) -> ProviderRequest {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            model: &'a str,
+            system: String,
+            prompt: &'a str,
+            stream: bool,
+        }
+
+        let request = Request {
+            model: self.model(),
+            system: self.system_prompt(prompt, schema),
+            prompt,
+            stream: false,
+        };
+
+        let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        if !self.api_key.is_empty() {
+            headers.push((
+                "Authorization".to_string(),
+                format!("Bearer {}", self.api_key),
+            ));
+        }
+
+        ProviderRequest {
+            url: format!("{}/api/generate", ollama_host()),
+            headers,
+            secret_header: Some("Authorization"),
+            body: serde_json::to_value(request).unwrap_or_default(),
+        }
+    }
+
+    async fn call_ollama(&self, prompt: &str, schema: &str) -> Result<GeneratedSql, Error> {
+        let response = self.send(self.ollama_request(prompt, schema)).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await?;
+            return Err(Error::Ai(format!("ollama {status}: {error}")));
+        }
+
+        Provider::Ollama.parse_response(&response.text().await?)
+    }
+
+    // add the --prompt-prefix/--prompt-suffix around what the user typed
+    fn wrap_prompt(&self, prompt: &str) -> String {
+        let parts: Vec<&str> = [
+            self.options.prompt_prefix.as_deref(),
+            Some(prompt),
+            self.options.prompt_suffix.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|p| !p.trim().is_empty())
+        .collect();
+        parts.join("\n\n")
+    }
+
+    fn system_prompt(&self, prompt: &str, schema: &str) -> String {
+        let schema = fit_schema(schema, prompt, self.options.max_schema_bytes);
+
+        // --no-schema: let the model know it's working blind
+        let schema = if schema.trim().is_empty() {
+            "(not provided - infer table and column names from the request)"
+        } else {
+            &schema
+        };
+
+        let mut prompt = format!(
             r#"You are a SQL query generator. Given a natural language request, generate a valid SQL query.
 
 Database schema:
@@ -222,16 +1113,281 @@ Rules:
 - Use proper SQL syntax for the database
 - Be precise with table and column names from the schema
 - For SELECT queries, be specific about columns when possible
-- For PostgreSQL: cast timestamp/date columns to text (e.g., created_at::text)
-- Add reasonable LIMIT if none specified (max 100 rows)"#
-        )
+- For PostgreSQL: cast timestamp, date, uuid, numeric and json columns to text (e.g., created_at::text), the driver can't read them otherwise
+- Add reasonable LIMIT if none specified (max 100 rows)
+- For relative time ranges (today, this week, last month) use the database's own date functions (e.g. CURRENT_DATE, NOW(), date('now')) rather than hardcoded dates
+- End with a final line `-- confidence: N`, where N is 0-100 for how sure you are the query answers the request"#
+        );
+
+        if self.options.clarify {
+            prompt.push_str(&format!(
+                "\n- If the request is too ambiguous to answer without guessing, output a single line starting with {CLARIFY_SENTINEL} followed by a short question for the user instead of SQL"
+            ));
+        }
+
+        if self.options.select_only {
+            prompt.push_str(&format!(
+                "\n- Only generate SELECT statements; never generate INSERT, UPDATE, DELETE or DDL. If the request requires modifying data, output a single line starting with {READ_ONLY_SENTINEL} followed by a short explanation instead of SQL"
+            ));
+        }
+
+        // --order-hint: "latest orders" otherwise comes back in whatever order the table has
+        if self.options.order_hint {
+            let columns = timestamp_columns(schema);
+            if !columns.is_empty() {
+                prompt.push_str(&format!(
+                    "\n- For requests about recent, latest, newest or last rows, ORDER BY the table's timestamp column DESC; timestamp columns: {}",
+                    columns.join(", ")
+                ));
+            }
+        }
+
+        if self.options.candidates {
+            prompt.push_str("\n- If the request can reasonably be read more than one way, you may output up to 3 alternative queries, each ending with a semicolon and separated by a blank line");
+        }
+
+        // the model has no clock, without this "last month" is a guess
+        prompt.push_str(&format!("\n\n{}", date_context(&chrono::Local::now())));
+
+        // --since/--until: spell out the bounds, the model can't work out "7 days ago" itself
+        if self.options.since.is_some() || self.options.until.is_some() {
+            if let Some(since) = self.options.since {
+                prompt.push_str(&format!("\n'since' bound is {since}"));
+            }
+            if let Some(until) = self.options.until {
+                prompt.push_str(&format!("\n'until' bound is {until}"));
+            }
+            prompt.push_str(
+                "\nRestrict results to rows on or after the 'since' bound and before the 'until' bound, using the most relevant date/timestamp column.",
+            );
+        }
+
+        prompt
+    }
+}
+
+// tags models like to wrap their answer in
+const WRAPPER_TAGS: &[&str] = &["sql", "answer", "query", "output", "response"];
+
+// tags around reasoning that comes before the answer, never sql to run
+const REASONING_TAGS: &[&str] = &["thinking", "think", "reasoning"];
+
+/// the sql out of a model reply: drops `<thinking>` style reasoning, unwraps
+/// `<sql>` style tags, markdown fences and `{"sql": ...}` json, in any nesting,
+/// and leaves plain sql alone
+pub fn extract_sql(output: &str) -> String {
+    let mut current = strip_reasoning(output).trim().to_string();
+    loop {
+        let next = unwrap_tag(&current)
+            .or_else(|| unwrap_fence(&current))
+            .or_else(|| unwrap_json(&current))
+            .map(|inner| inner.trim().to_string());
+        match next {
+            Some(inner) if inner != current => current = inner,
+            _ => return current,
+        }
+    }
+}
+
+// every closed `<thinking>...</thinking>` (and friends) cut out
+fn strip_reasoning(s: &str) -> String {
+    let mut out = s.to_string();
+    for tag in REASONING_TAGS {
+        let open = format!("<{tag}>");
+        let close = format!("</{tag}>");
+        loop {
+            // ascii only, so byte offsets line up with `out`
+            let lower = out.to_ascii_lowercase();
+            let Some(start) = lower.find(&open) else {
+                break;
+            };
+            let Some(end) = lower[start..].find(&close) else {
+                break;
+            };
+            out.replace_range(start..start + end + close.len(), "");
+        }
     }
+    out
+}
+
+// `<sql>...</sql>`, possibly with prose around it
+fn unwrap_tag(s: &str) -> Option<String> {
+    // ascii only, so byte offsets line up with `s`
+    let lower = s.to_ascii_lowercase();
+    WRAPPER_TAGS.iter().find_map(|tag| {
+        let open = format!("<{tag}>");
+        let close = format!("</{tag}>");
+        let start = lower.find(&open)? + open.len();
+        let end = lower[start..].find(&close).map_or(s.len(), |e| start + e);
+        Some(s[start..end].to_string())
+    })
+}
 
-    fn clean_sql(&self, sql: &str) -> String {
-        sql.trim_start_matches("```sql")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim()
-            .to_string()
+// the first ``` block, skipping a language tag like ```sql
+fn unwrap_fence(s: &str) -> Option<String> {
+    let start = s.find("```")? + 3;
+    let rest = &s[start..];
+    let body_start = match rest.find('\n') {
+        Some(nl) if rest[..nl].trim().chars().all(|c| c.is_ascii_alphanumeric()) => nl + 1,
+        _ => 0,
+    };
+    let body = &rest[body_start..];
+    let end = body.find("```").unwrap_or(body.len());
+    Some(body[..end].to_string())
+}
+
+// `{"sql": "..."}` or `{"query": "..."}`
+fn unwrap_json(s: &str) -> Option<String> {
+    if !s.starts_with('{') {
+        return None;
     }
+    let value: serde_json::Value = serde_json::from_str(s).ok()?;
+    ["sql", "query"]
+        .iter()
+        .find_map(|key| value.get(key)?.as_str().map(str::to_string))
+}
+
+// words that can open a statement
+const STATEMENT_START: &[&str] = &[
+    "SELECT", "WITH", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "TRUNCATE",
+    "EXPLAIN", "SHOW", "DESCRIBE", "PRAGMA", "VALUES",
+];
+
+// words that carry on a statement after a blank line
+const CLAUSE_START: &[&str] = &[
+    "FROM",
+    "WHERE",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "CROSS",
+    "ON",
+    "AND",
+    "OR",
+    "GROUP",
+    "ORDER",
+    "HAVING",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "SET",
+    "RETURNING",
+    "WINDOW",
+];
+
+/// pull every sql statement out of the model's output, dropping commentary
+pub fn split_candidates(output: &str) -> Vec<String> {
+    let first_word = |line: &str| {
+        line.trim_start()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default()
+            .to_uppercase()
+    };
+
+    let mut statements: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+    let mut after_blank = false;
+
+    let mut finish = |current: &mut Option<String>| {
+        if let Some(stmt) = current.take() {
+            let stmt = stmt.trim().trim_end_matches(';').trim().to_string();
+            if !stmt.is_empty() && !statements.contains(&stmt) {
+                statements.push(stmt);
+            }
+        }
+    };
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            finish(&mut current);
+            continue;
+        }
+        if trimmed.is_empty() {
+            after_blank = true;
+            continue;
+        }
+
+        let word = first_word(line);
+        let starts = STATEMENT_START.contains(&word.as_str());
+
+        // a blank line only keeps a statement going if what follows is clearly part of it
+        let continues = current.is_some()
+            && (!after_blank
+                || CLAUSE_START.contains(&word.as_str())
+                || line.starts_with(char::is_whitespace)
+                || trimmed.starts_with(')'));
+
+        if continues && !(after_blank && starts) {
+            if let Some(stmt) = current.as_mut() {
+                stmt.push('\n');
+                stmt.push_str(line);
+            }
+        } else {
+            finish(&mut current);
+            if starts {
+                current = Some(line.to_string());
+            }
+        }
+        after_blank = false;
+
+        // a semicolon at the end closes the statement
+        if trimmed.ends_with(';') {
+            finish(&mut current);
+        }
+    }
+    finish(&mut current);
+
+    statements
+}
+
+// keep the schema under `max_bytes`, preferring tables the prompt mentions
+fn fit_schema<'a>(schema: &'a str, prompt: &str, max_bytes: usize) -> std::borrow::Cow<'a, str> {
+    if schema.len() <= max_bytes {
+        return schema.into();
+    }
+
+    let blocks: Vec<&str> = schema.split("\n\n").collect();
+    let prompt = prompt.to_lowercase();
+    let mentioned = |block: &str| {
+        let name = block
+            .lines()
+            .next()
+            .and_then(|l| l.split_whitespace().nth(1))
+            .unwrap_or_default()
+            .to_lowercase();
+        // "user" in the prompt should still find the users table
+        let singular = name.strip_suffix('s').unwrap_or(&name);
+        !singular.is_empty() && prompt.contains(singular)
+    };
+
+    let (mut ordered, rest): (Vec<&str>, Vec<&str>) =
+        blocks.iter().copied().partition(|b| mentioned(b));
+    ordered.extend(rest);
+
+    // leave room for the note at the end
+    let budget = max_bytes.saturating_sub(64);
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for block in ordered {
+        let len = block.len() + 2;
+        if used + len > budget {
+            continue;
+        }
+        used += len;
+        kept.push(block);
+    }
+
+    format!(
+        "{}\n\n[schema truncated: {} of {} tables shown]",
+        kept.join("\n\n"),
+        kept.len(),
+        blocks.len()
+    )
+    .into()
 }
@@ -0,0 +1,66 @@
+// small in-memory cache of query results, keyed by the exact sql
+// only meant for read-only statements, callers decide what goes in
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::QueryResult;
+
+pub struct ResultCache {
+    entries: VecDeque<(String, Instant, QueryResult)>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            ttl,
+        }
+    }
+
+    /// fresh cached result for this sql, marked as recently used
+    pub fn get(&mut self, sql: &str) -> Option<QueryResult> {
+        let pos = self.entries.iter().position(|(key, _, _)| key == sql)?;
+        let entry = self.entries.remove(pos)?;
+
+        if entry.1.elapsed() > self.ttl {
+            return None;
+        }
+
+        let result = entry.2.clone();
+        self.entries.push_back(entry);
+        Some(result)
+    }
+
+    pub fn put(&mut self, sql: &str, result: QueryResult) {
+        self.entries.retain(|(key, _, _)| key != sql);
+        self.entries
+            .push_back((sql.to_string(), Instant::now(), result));
+
+        // evict least recently used
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self::new(32, Duration::from_secs(60))
+    }
+}
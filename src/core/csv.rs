@@ -0,0 +1,106 @@
+// csv rendering for query results, shared by the tui export and the cli
+
+use serde::Deserialize;
+
+use super::QueryResult;
+
+/// knobs for csv output
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+    /// field separator
+    pub delimiter: char,
+    /// end lines with \r\n (what excel expects) instead of \n
+    pub crlf: bool,
+    /// annotate header cells with the inferred type, e.g. `id:integer`
+    pub type_header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            crlf: false,
+            type_header: false,
+        }
+    }
+}
+
+impl QueryResult {
+    /// rfc 4180 style csv: quoted when needed, nulls empty, bools as true/false
+    pub fn to_csv(&self, opts: &CsvOptions) -> String {
+        let newline = if opts.crlf { "\r\n" } else { "\n" };
+        let sep = opts.delimiter.to_string();
+
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if opts.type_header {
+                    quote(&format!("{c}:{}", self.column_type(i)), opts.delimiter)
+                } else {
+                    quote(c, opts.delimiter)
+                }
+            })
+            .collect();
+
+        let mut csv = header.join(&sep);
+        csv.push_str(newline);
+
+        for row in &self.rows {
+            let values: Vec<String> = row.iter().map(|v| csv_value(v, opts.delimiter)).collect();
+            csv.push_str(&values.join(&sep));
+            csv.push_str(newline);
+        }
+
+        csv
+    }
+
+    /// best guess at a column's type from its non-null values
+    pub fn column_type(&self, index: usize) -> &'static str {
+        let mut kind = "null";
+
+        for value in self.rows.iter().filter_map(|r| r.get(index)) {
+            let this = match value {
+                serde_json::Value::Null => continue,
+                serde_json::Value::Bool(_) => "boolean",
+                serde_json::Value::Number(n) if n.is_f64() => "real",
+                serde_json::Value::Number(_) => "integer",
+                _ => "text",
+            };
+
+            kind = match (kind, this) {
+                ("null", t) => t,
+                (a, b) if a == b => a,
+                // ints and floats mixed is still numeric
+                ("integer", "real") | ("real", "integer") => "real",
+                _ => "text",
+            };
+        }
+
+        kind
+    }
+}
+
+pub(super) fn csv_value(value: &serde_json::Value, delimiter: char) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        // rust's float display never uses exponents or trailing noise like 1e2
+        serde_json::Value::Number(n) => match n.as_f64().filter(|_| n.is_f64()) {
+            Some(f) => f.to_string(),
+            None => n.to_string(),
+        },
+        serde_json::Value::String(s) => quote(s, delimiter),
+        other => quote(&other.to_string(), delimiter),
+    }
+}
+
+pub(super) fn quote(s: &str, delimiter: char) -> String {
+    if s.contains(delimiter) || s.contains('"') || s.contains('\n') || s.contains('\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -2,23 +2,170 @@
 // supports postgres, sqlite, and mysql
 
 use crate::Error;
+use crate::core::StatementKind;
+use crate::core::statement::{is_blank, split_statements};
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
 use serde::Serialize;
-use sqlx::{AnyPool, Column, Row, any::AnyPoolOptions};
+use sqlx::any::{AnyPoolOptions, AnyRow};
+use sqlx::{AnyPool, Column, Row};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 pub struct Db {
     pool: AnyPool,
+    // SELECTs go here when a --read-url replica is set
+    replica: Option<AnyPool>,
     dialect: Dialect,
     host: String,
     database: String,
+    // --db-schema: the postgres schema or mysql database to introspect
+    db_schema: Option<String>,
+    // kept for the replica pool
+    config: DbConfig,
 }
 
-#[derive(Serialize)]
+/// rows a result holds unless --max-rows says otherwise
+pub const DEFAULT_MAX_ROWS: usize = 10_000;
+
+/// pool settings, the defaults are what `Db::connect` uses
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// connections held open at most, per pool
+    pub max_connections: u32,
+    /// give up on reaching the database after this long (--db-timeout)
+    pub connect_timeout: Duration,
+    /// wait at most this long for a free connection once connected
+    pub acquire_timeout: Duration,
+    /// rows `execute` keeps before it stops and marks the result truncated,
+    /// `None` keeps them all
+    pub max_rows: Option<usize>,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            connect_timeout: Duration::from_secs(10),
+            acquire_timeout: Duration::from_secs(30),
+            max_rows: Some(DEFAULT_MAX_ROWS),
+        }
+    }
+}
+
+impl DbConfig {
+    async fn open(&self, url: &str) -> Result<AnyPool, Error> {
+        // the pool gives up first when its acquire timeout is the shorter one
+        let waited = self.connect_timeout.min(self.acquire_timeout);
+        let timed_out = || Error::ConnectTimeout(waited.as_secs());
+        let connect = AnyPoolOptions::new()
+            .max_connections(self.max_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .connect(url);
+        // a wrong host can take minutes to fail on its own
+        match tokio::time::timeout(self.connect_timeout, connect).await {
+            Ok(Ok(pool)) => Ok(pool),
+            Ok(Err(sqlx::Error::PoolTimedOut)) | Err(_) => Err(timed_out()),
+            Ok(Err(e)) => Err(Error::Connection(e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct QueryResult {
     pub columns: Vec<String>,
     pub rows: Vec<Vec<serde_json::Value>>,
     pub row_count: usize,
+    /// rows were capped, there is more than what's here
+    pub truncated: bool,
+}
+
+impl QueryResult {
+    /// what to show instead of a table when there are no rows: a SELECT found
+    /// nothing, anything else (DDL, writes) just ran, `None` when there are rows
+    pub fn status(&self, sql: &str) -> Option<String> {
+        if !self.rows.is_empty() {
+            return None;
+        }
+        match StatementKind::from_sql(sql) {
+            StatementKind::Select | StatementKind::Explain | StatementKind::Other => {
+                Some("no rows".to_string())
+            }
+            kind => Some(format!("{} executed successfully", kind.label())),
+        }
+    }
+}
+
+/// rows as the driver hands them over, for exports too big to hold in memory
+pub struct RowStream<'a> {
+    rows: BoxStream<'a, Result<sqlx::any::AnyRow, sqlx::Error>>,
+    // several statements, given back by the first `next` instead of running
+    refused: Option<Error>,
+    // select list order, known once the first row is in
+    ordinals: Vec<usize>,
+    columns: Option<Vec<String>>,
+}
+
+impl RowStream<'_> {
+    /// the next row in select list order, `None` at the end
+    pub async fn next(&mut self) -> Option<Result<Vec<serde_json::Value>, Error>> {
+        if let Some(e) = self.refused.take() {
+            return Some(Err(e));
+        }
+        let row = match self.rows.next().await? {
+            Ok(row) => row,
+            Err(e) => return Some(Err(e.into())),
+        };
+        if self.columns.is_none() {
+            let mut ordered: Vec<(usize, String)> = row
+                .columns()
+                .iter()
+                .map(|c| (c.ordinal(), c.name().to_string()))
+                .collect();
+            ordered.sort_by_key(|(ordinal, _)| *ordinal);
+            let (ordinals, columns) = ordered.into_iter().unzip();
+            self.ordinals = ordinals;
+            self.columns = Some(columns);
+        }
+        Some(Ok(self
+            .ordinals
+            .iter()
+            .map(|&i| row_value_to_json(&row, i))
+            .collect()))
+    }
+
+    /// column names, `None` until the first row has been read
+    pub fn columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
 }
 
+// one column of a primary or foreign key from information_schema:
+// (table_schema, table, constraint, column, ref_schema, ref_table, ref_column)
+type KeyRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+// `PRAGMA foreign_key_list`, one row per column:
+// (id, seq, table, from, to, on_update, on_delete, match)
+type SqliteFkRow = (
+    i32,
+    i32,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+);
+
+#[derive(PartialEq)]
 enum Dialect {
     Postgres,
     Sqlite,
@@ -27,25 +174,70 @@ enum Dialect {
 
 impl Db {
     pub async fn connect(url: &str) -> Result<Self, Error> {
+        Self::connect_with(url, &DbConfig::default()).await
+    }
+
+    /// `connect` with a pool size and timeouts other than the defaults
+    pub async fn connect_with(url: &str, config: &DbConfig) -> Result<Self, Error> {
         sqlx::any::install_default_drivers();
+        let url = &connection_url(url)?;
 
         // figure out which database we're talking to
         let dialect = detect_dialect(url);
         let (host, database) = parse_connection_url(url);
 
-        let pool = AnyPoolOptions::new()
-            .max_connections(5)
-            .connect(url)
-            .await?;
+        let pool = config.open(url).await?;
 
         Ok(Self {
             pool,
+            replica: None,
             dialect,
             host,
             database,
+            db_schema: None,
+            config: config.clone(),
         })
     }
 
+    /// introspect `name` instead of postgres' `public` or mysql's connected database
+    ///
+    /// tables come out qualified (`name.table`) so the generated sql finds them
+    pub fn with_db_schema(mut self, name: &str) -> Result<Self, Error> {
+        if self.dialect == Dialect::Sqlite {
+            return Err(Error::Config(
+                "--db-schema only applies to postgres and mysql".to_string(),
+            ));
+        }
+        self.db_schema = Some(name.to_string());
+        Ok(self)
+    }
+
+    /// send SELECTs to a read-only replica, everything else still hits the primary
+    pub async fn with_read_url(mut self, url: &str) -> Result<Self, Error> {
+        if detect_dialect(url) != self.dialect {
+            return Err(Error::Config(format!(
+                "--read-url must be the same kind of database as --db ({})",
+                self.dialect_name()
+            )));
+        }
+
+        let replica = self.config.open(url).await?;
+        self.replica = Some(replica);
+        Ok(self)
+    }
+
+    pub fn has_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    // reads go to the replica if there is one, writes always to the primary
+    fn pool_for(&self, sql: &str) -> &AnyPool {
+        match &self.replica {
+            Some(replica) if StatementKind::from_sql(sql).is_read() => replica,
+            _ => &self.pool,
+        }
+    }
+
     pub fn dialect_name(&self) -> &'static str {
         match self.dialect {
             Dialect::Postgres => "postgres",
@@ -63,97 +255,457 @@ impl Db {
     }
 
     // get table and column info so claude knows what to query
+    //
+    // views come out as `VIEW name (..)` blocks, the model can read from them too
     pub async fn schema(&self) -> Result<String, Error> {
+        self.introspect(true).await
+    }
+
+    /// `schema` with only the base tables, views left out
+    pub async fn table_schema(&self) -> Result<String, Error> {
+        self.introspect(false).await
+    }
+
+    async fn introspect(&self, views: bool) -> Result<String, Error> {
         match self.dialect {
-            Dialect::Postgres => self.postgres_schema().await,
-            Dialect::Sqlite => self.sqlite_schema().await,
-            Dialect::Mysql => self.mysql_schema().await,
+            Dialect::Postgres => self.postgres_schema(views).await,
+            Dialect::Sqlite => self.sqlite_schema(views).await,
+            Dialect::Mysql => self.mysql_schema(views).await,
         }
     }
 
-    async fn postgres_schema(&self) -> Result<String, Error> {
+    async fn postgres_schema(&self, views: bool) -> Result<String, Error> {
         let rows: Vec<(String, String, String)> = sqlx::query_as(
             r#"SELECT table_name::text, column_name::text, data_type::text
                FROM information_schema.columns
-               WHERE table_schema = 'public'
+               WHERE table_schema = $1
                ORDER BY table_name, ordinal_position"#,
         )
+        .bind(self.db_schema.as_deref().unwrap_or("public"))
+        .fetch_all(&self.pool)
+        .await?;
+
+        // information_schema.columns has the views' columns too, this tells them apart
+        let view_names: Vec<(String,)> = sqlx::query_as(
+            "SELECT table_name::text FROM information_schema.views WHERE table_schema = $1",
+        )
+        .bind(self.db_schema.as_deref().unwrap_or("public"))
+        .fetch_all(&self.pool)
+        .await?;
+        let (rows, view_names) = self.split_views(rows, view_names, views);
+
+        // fk columns line up with the referenced key's columns by position
+        let keys: Vec<KeyRow> = sqlx::query_as(
+            r#"SELECT kcu.table_schema::text, kcu.table_name::text, kcu.constraint_name::text,
+                      kcu.column_name::text, rkcu.table_schema::text, rkcu.table_name::text,
+                      rkcu.column_name::text
+               FROM information_schema.table_constraints tc
+               JOIN information_schema.key_column_usage kcu
+                 ON kcu.constraint_schema = tc.constraint_schema
+                AND kcu.constraint_name = tc.constraint_name
+                AND kcu.table_name = tc.table_name
+               LEFT JOIN information_schema.referential_constraints rc
+                 ON rc.constraint_schema = tc.constraint_schema
+                AND rc.constraint_name = tc.constraint_name
+               LEFT JOIN information_schema.key_column_usage rkcu
+                 ON rkcu.constraint_schema = rc.unique_constraint_schema
+                AND rkcu.constraint_name = rc.unique_constraint_name
+                AND rkcu.ordinal_position = kcu.position_in_unique_constraint
+               WHERE tc.table_schema = $1
+                 AND tc.constraint_type IN ('PRIMARY KEY', 'FOREIGN KEY')
+               ORDER BY kcu.table_name, tc.constraint_type DESC, kcu.constraint_name,
+                        kcu.ordinal_position"#,
+        )
+        .bind(self.db_schema.as_deref().unwrap_or("public"))
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(format_schema(rows))
+        Ok(format_schema(rows, &self.key_lines(keys), &view_names))
     }
 
-    async fn sqlite_schema(&self) -> Result<String, Error> {
-        let tables: Vec<(String,)> = sqlx::query_as(
-            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
-        )
+    async fn sqlite_schema(&self, views: bool) -> Result<String, Error> {
+        let types = if views { "'table', 'view'" } else { "'table'" };
+        let objects: Vec<(String, String)> = sqlx::query_as(&format!(
+            "SELECT name, type FROM sqlite_master WHERE type IN ({types}) AND name NOT LIKE 'sqlite_%'"
+        ))
         .fetch_all(&self.pool)
         .await?;
+        let view_names: HashSet<String> = objects
+            .iter()
+            .filter(|(_, kind)| kind == "view")
+            .map(|(name, _)| name.clone())
+            .collect();
+        let tables = objects.into_iter().map(|(name, _)| (name,));
 
         let mut result = Vec::new();
+        let mut primary: HashMap<String, Vec<String>> = HashMap::new();
+        let mut foreign = Vec::new();
         for (table,) in tables {
-            let query = format!("PRAGMA table_info(\"{}\")", table);
+            let query = format!("PRAGMA table_info({})", quote_ident(&table));
             let cols: Vec<(i32, String, String, i32, Option<String>, i32)> =
                 sqlx::query_as(&query).fetch_all(&self.pool).await?;
 
-            for (_, name, dtype, _, _, _) in cols {
+            // `pk` is the column's position in the primary key, 0 if it isn't in it
+            let mut pk: Vec<(i32, String)> = Vec::new();
+            for (_, name, dtype, _, _, position) in cols {
+                if position > 0 {
+                    pk.push((position, name.clone()));
+                }
                 result.push((table.clone(), name, dtype));
             }
+            pk.sort();
+            primary.insert(
+                table.clone(),
+                pk.into_iter().map(|(_, name)| name).collect(),
+            );
+
+            let query = format!("PRAGMA foreign_key_list({})", quote_ident(&table));
+            let fks: Vec<SqliteFkRow> = sqlx::query_as(&query).fetch_all(&self.pool).await?;
+            let mut fks: Vec<_> = fks
+                .into_iter()
+                .map(|(id, seq, parent, from, to, ..)| (id, seq, parent, from, to))
+                .collect();
+            fks.sort_by_key(|(id, seq, ..)| (*id, *seq));
+            foreign.push((table, fks));
+        }
+
+        let mut keys: HashMap<String, Vec<String>> = HashMap::new();
+        for (table, columns) in &primary {
+            if !columns.is_empty() {
+                keys.entry(table.clone())
+                    .or_default()
+                    .push(key_line(columns, None));
+            }
+        }
+        for (table, fks) in foreign {
+            let mut fks = fks.into_iter().peekable();
+            while let Some((id, _, parent, from, to)) = fks.next() {
+                let mut from = vec![from];
+                let mut to = vec![to];
+                while let Some((_, _, _, next_from, next_to)) =
+                    fks.next_if(|(next_id, ..)| *next_id == id)
+                {
+                    from.push(next_from);
+                    to.push(next_to);
+                }
+                // no `to` means the parent's primary key
+                let to: Vec<String> = match to.into_iter().collect::<Option<Vec<_>>>() {
+                    Some(to) => to,
+                    None => primary.get(&parent).cloned().unwrap_or_default(),
+                };
+                keys.entry(table.clone())
+                    .or_default()
+                    .push(key_line(&from, Some((&parent, &to))));
+            }
         }
 
-        Ok(format_schema(result))
+        Ok(format_schema(result, &keys, &view_names))
     }
 
-    async fn mysql_schema(&self) -> Result<String, Error> {
-        let rows: Vec<(String, String, String)> = sqlx::query_as(
-            r#"SELECT table_name, column_name, data_type
-               FROM information_schema.columns
-               WHERE table_schema = DATABASE()
-               ORDER BY table_name, ordinal_position"#,
-        )
-        .fetch_all(&self.pool)
-        .await?;
+    async fn mysql_schema(&self, views: bool) -> Result<String, Error> {
+        let rows: Vec<(String, String, String)> = match &self.db_schema {
+            Some(name) => {
+                sqlx::query_as(
+                    r#"SELECT table_name, column_name, data_type
+                   FROM information_schema.columns
+                   WHERE table_schema = ?
+                   ORDER BY table_name, ordinal_position"#,
+                )
+                .bind(name.as_str())
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query_as(
+                    r#"SELECT table_name, column_name, data_type
+                   FROM information_schema.columns
+                   WHERE table_schema = DATABASE()
+                   ORDER BY table_name, ordinal_position"#,
+                )
+                .fetch_all(&self.pool)
+                .await?
+            }
+        };
+
+        let query = "SELECT table_name FROM information_schema.views WHERE table_schema = ";
+        let view_names: Vec<(String,)> = match &self.db_schema {
+            Some(name) => {
+                sqlx::query_as(&format!("{query}?"))
+                    .bind(name.as_str())
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query_as(&format!("{query}DATABASE()"))
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        let (rows, view_names) = self.split_views(rows, view_names, views);
+
+        // mysql names every primary key PRIMARY and keeps the referenced column alongside
+        let query = format!(
+            r#"SELECT table_schema, table_name, constraint_name, column_name,
+                      referenced_table_schema, referenced_table_name, referenced_column_name
+               FROM information_schema.key_column_usage
+               WHERE table_schema = {}
+                 AND (constraint_name = 'PRIMARY' OR referenced_table_name IS NOT NULL)
+               ORDER BY table_name, constraint_name = 'PRIMARY' DESC, constraint_name,
+                        ordinal_position"#,
+            if self.db_schema.is_some() {
+                "?"
+            } else {
+                "DATABASE()"
+            }
+        );
+        let mut keys = sqlx::query_as::<_, KeyRow>(&query);
+        if let Some(name) = &self.db_schema {
+            keys = keys.bind(name.as_str());
+        }
+        let keys = keys.fetch_all(&self.pool).await?;
+
+        Ok(format_schema(rows, &self.key_lines(keys), &view_names))
+    }
+
+    // qualified rows and view names, with the views' columns dropped unless `views`
+    fn split_views(
+        &self,
+        rows: Vec<(String, String, String)>,
+        view_names: Vec<(String,)>,
+        views: bool,
+    ) -> (Vec<(String, String, String)>, HashSet<String>) {
+        let mut rows = self.qualify(rows);
+        let view_names: HashSet<String> = view_names
+            .into_iter()
+            .map(|(name,)| match &self.db_schema {
+                Some(prefix) => format!("{prefix}.{name}"),
+                None => name,
+            })
+            .collect();
+        if !views {
+            rows.retain(|(table, ..)| !view_names.contains(table));
+        }
+        (rows, view_names)
+    }
+
+    // `schema.table` names when --db-schema picked one
+    fn qualify(&self, rows: Vec<(String, String, String)>) -> Vec<(String, String, String)> {
+        let Some(prefix) = &self.db_schema else {
+            return rows;
+        };
+        rows.into_iter()
+            .map(|(table, column, dtype)| (format!("{prefix}.{table}"), column, dtype))
+            .collect()
+    }
 
-        Ok(format_schema(rows))
+    // `PRIMARY KEY (..)` and `FOREIGN KEY (..) REFERENCES ..` lines per (qualified) table
+    fn key_lines(&self, rows: Vec<KeyRow>) -> HashMap<String, Vec<String>> {
+        let mut lines: HashMap<String, Vec<String>> = HashMap::new();
+        let mut rows = rows.into_iter().peekable();
+        while let Some((schema, table, constraint, column, ref_schema, ref_table, ref_column)) =
+            rows.next()
+        {
+            let mut columns = vec![column];
+            let mut ref_columns: Vec<String> = ref_column.into_iter().collect();
+            while let Some((.., column, _, _, ref_column)) =
+                rows.next_if(|(_, t, c, ..)| *t == table && *c == constraint)
+            {
+                columns.push(column);
+                ref_columns.extend(ref_column);
+            }
+
+            let line = match ref_table {
+                Some(ref_table) => {
+                    // another schema's table has to be named with it to be found
+                    let ref_schema = ref_schema.unwrap_or_else(|| schema.clone());
+                    let ref_table = if self.db_schema.is_some() || ref_schema != schema {
+                        format!("{ref_schema}.{ref_table}")
+                    } else {
+                        ref_table
+                    };
+                    key_line(&columns, Some((&ref_table, &ref_columns)))
+                }
+                None => key_line(&columns, None),
+            };
+            let table = match &self.db_schema {
+                Some(prefix) => format!("{prefix}.{table}"),
+                None => table,
+            };
+            lines.entry(table).or_default().push(line);
+        }
+        lines
     }
 
     // run the sql and return results as json
+    //
+    // one statement only, sqlx would run the rest outside any transaction
     pub async fn execute(&self, sql: &str) -> Result<QueryResult, Error> {
-        let rows = sqlx::query(sql).fetch_all(&self.pool).await?;
-
-        if rows.is_empty() {
-            return Ok(QueryResult {
-                columns: vec![],
-                rows: vec![],
-                row_count: 0,
-            });
+        if let Some(e) = several_statements(sql) {
+            return Err(e);
         }
+        let rows = sqlx::query(sql).fetch(self.pool_for(sql));
+        self.collect(rows).await
+    }
 
-        let columns: Vec<String> = rows[0]
-            .columns()
-            .iter()
-            .map(|c| c.name().to_string())
-            .collect();
+    // rows up to `max_rows`, one more tells whether there were others
+    async fn collect(
+        &self,
+        mut rows: BoxStream<'_, Result<AnyRow, sqlx::Error>>,
+    ) -> Result<QueryResult, Error> {
+        let mut kept = Vec::new();
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            if self.config.max_rows.is_some_and(|max| kept.len() >= max) {
+                return Ok(query_result(&kept, true));
+            }
+            kept.push(row);
+        }
+        Ok(query_result(&kept, false))
+    }
+
+    /// run each `;` separated statement of `sql` in one transaction and return
+    /// what the last one returned; an error in any of them rolls back the rest
+    ///
+    /// always on the primary, a script can write after it reads
+    pub async fn execute_script(&self, sql: &str) -> Result<QueryResult, Error> {
+        let statements = statements(sql);
+        let Some((last, before)) = statements.split_last() else {
+            return Ok(query_result(&[], false));
+        };
+        let mut tx = self.pool.begin().await?;
+        for statement in before {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        let result = self.collect(sqlx::query(last).fetch(&mut *tx)).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    /// `execute` for a single statement; several go to `execute_script` when
+    /// `multi_statement` allows it and are refused by `execute` otherwise
+    pub async fn execute_statements(
+        &self,
+        sql: &str,
+        multi_statement: bool,
+    ) -> Result<QueryResult, Error> {
+        if multi_statement && statements(sql).len() > 1 {
+            self.execute_script(sql).await
+        } else {
+            self.execute(sql).await
+        }
+    }
+
+    /// run a write and return how many rows it touched, for --output count
+    pub async fn rows_affected(&self, sql: &str) -> Result<u64, Error> {
+        // the count would only be the last statement's, and nothing rolls back
+        if statements(sql).len() > 1 {
+            return Err(Error::Blocked(
+                "--output count takes a single statement".to_string(),
+            ));
+        }
+        let done = sqlx::query(sql).execute(self.pool_for(sql)).await?;
+        Ok(done.rows_affected())
+    }
+
+    /// like `execute`, but one row at a time instead of all of them at once
+    pub fn execute_stream<'a>(&'a self, sql: &'a str) -> RowStream<'a> {
+        let refused = several_statements(sql);
+        let rows = match refused {
+            Some(_) => futures_util::stream::empty().boxed(),
+            None => sqlx::query(sql).fetch(self.pool_for(sql)),
+        };
+        RowStream {
+            rows,
+            refused,
+            ordinals: Vec::new(),
+            columns: None,
+        }
+    }
+
+    /// open (or create) a sqlite file to save results into
+    pub async fn open_sqlite(path: &str) -> Result<Self, Error> {
+        let path = sqlite_path(path).unwrap_or(path);
+        Self::connect(&format!("sqlite:{path}?mode=rwc")).await
+    }
+
+    /// create `table` from a result, column types are guessed from the values
+    pub async fn save_table(&self, table: &str, result: &QueryResult) -> Result<(), Error> {
+        if !matches!(self.dialect, Dialect::Sqlite) {
+            return Err(Error::Config(
+                "results can only be saved to sqlite, pass an --out file.db".to_string(),
+            ));
+        }
+        if result.columns.is_empty() {
+            return Err(Error::Config(
+                "nothing to save, the result has no columns".to_string(),
+            ));
+        }
 
-        let json_rows: Vec<Vec<serde_json::Value>> = rows
+        let columns: Vec<String> = result
+            .columns
             .iter()
-            .map(|row| {
-                columns
-                    .iter()
-                    .enumerate()
-                    .map(|(i, _)| row_value_to_json(row, i))
-                    .collect()
+            .enumerate()
+            .map(|(i, c)| {
+                let sql_type = match result.column_type(i) {
+                    "integer" | "boolean" => "INTEGER",
+                    "real" => "REAL",
+                    _ => "TEXT",
+                };
+                format!("{} {sql_type}", quote_ident(c))
             })
             .collect();
 
-        let row_count = json_rows.len();
+        let names: Vec<String> = result.columns.iter().map(|c| quote_ident(c)).collect();
+        let placeholders = vec!["?"; names.len()].join(", ");
+        let insert = format!(
+            "INSERT INTO {} ({}) VALUES ({placeholders})",
+            quote_ident(table),
+            names.join(", ")
+        );
+
+        // all or nothing, a half-written table is worse than none
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!(
+            "CREATE TABLE {} ({})",
+            quote_ident(table),
+            columns.join(", ")
+        ))
+        .execute(&mut *tx)
+        .await?;
 
-        Ok(QueryResult {
-            columns,
-            rows: json_rows,
-            row_count,
-        })
+        for row in &result.rows {
+            let mut query = sqlx::query(&insert);
+            for value in row {
+                query = match value {
+                    serde_json::Value::Null => query.bind(None::<String>),
+                    serde_json::Value::Bool(b) => query.bind(i64::from(*b)),
+                    serde_json::Value::Number(n) => match n.as_i64() {
+                        Some(i) => query.bind(i),
+                        None => query.bind(n.as_f64().unwrap_or_default()),
+                    },
+                    serde_json::Value::String(s) => query.bind(s.clone()),
+                    other => query.bind(other.to_string()),
+                };
+            }
+            query.execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// `SELECT 1` on the primary and any replica, owning its pool handles so it can run in the background
+    pub fn ping(&self) -> impl Future<Output = Result<(), Error>> + Send + 'static {
+        let pools: Vec<AnyPool> = std::iter::once(&self.pool)
+            .chain(&self.replica)
+            .cloned()
+            .collect();
+        async move {
+            for pool in &pools {
+                sqlx::query("SELECT 1").execute(pool).await?;
+            }
+            Ok(())
+        }
     }
 
     pub fn pool(&self) -> &AnyPool {
@@ -161,6 +713,94 @@ impl Db {
     }
 }
 
+// fetched rows as json, with their columns in select list order
+fn query_result(rows: &[AnyRow], truncated: bool) -> QueryResult {
+    if rows.is_empty() {
+        return QueryResult {
+            columns: vec![],
+            rows: vec![],
+            row_count: 0,
+            truncated,
+        };
+    }
+
+    // keep the select list's order, csv and export consumers rely on it
+    let mut ordered: Vec<(usize, String)> = rows[0]
+        .columns()
+        .iter()
+        .map(|c| (c.ordinal(), c.name().to_string()))
+        .collect();
+    ordered.sort_by_key(|(ordinal, _)| *ordinal);
+    let (ordinals, columns): (Vec<usize>, Vec<String>) = ordered.into_iter().unzip();
+
+    let json_rows: Vec<Vec<serde_json::Value>> = rows
+        .iter()
+        .map(|row| {
+            ordinals
+                .iter()
+                .map(|&i| row_value_to_json(row, i))
+                .collect()
+        })
+        .collect();
+
+    let row_count = json_rows.len();
+
+    QueryResult {
+        columns,
+        rows: json_rows,
+        row_count,
+        truncated,
+    }
+}
+
+// the statements of a script, leaving out blank ones like after a final `;`
+fn statements(sql: &str) -> Vec<&str> {
+    split_statements(sql)
+        .into_iter()
+        .filter(|s| !is_blank(s))
+        .collect()
+}
+
+// the refusal for sql with more than one statement, only `execute_script` runs those
+fn several_statements(sql: &str) -> Option<Error> {
+    let n = statements(sql).len();
+    (n > 1).then(|| {
+        Error::Blocked(format!(
+            "the sql has {n} statements, --multi-statement runs them in one transaction"
+        ))
+    })
+}
+
+/// `url` if it has a scheme we can talk to, a bare `data.db` style path as a
+/// sqlite url, and an error for anything else (a forgotten `postgres://`)
+/// instead of sqlite failing to open a file that was never meant to be one
+pub fn connection_url(url: &str) -> Result<String, Error> {
+    let known = [
+        "postgres://",
+        "postgresql://",
+        "mysql://",
+        "mariadb://",
+        "sqlite:",
+    ];
+    if known.iter().any(|scheme| url.starts_with(scheme)) {
+        return Ok(url.to_string());
+    }
+
+    let is_sqlite_file = !url.contains("://")
+        && [".db", ".sqlite", ".sqlite3"]
+            .iter()
+            .any(|ext| url.to_ascii_lowercase().ends_with(ext));
+    if is_sqlite_file {
+        return Ok(format!("sqlite:{url}"));
+    }
+
+    // not echoed back, without a scheme there's no telling where a password sits
+    Err(Error::Config(
+        "unrecognized connection string; expected postgres://, mysql://, or sqlite:<path>"
+            .to_string(),
+    ))
+}
+
 // figure out dialect from connection string
 fn detect_dialect(url: &str) -> Dialect {
     if url.starts_with("postgres://") || url.starts_with("postgresql://") {
@@ -168,15 +808,64 @@ fn detect_dialect(url: &str) -> Dialect {
     } else if url.starts_with("mysql://") || url.starts_with("mariadb://") {
         Dialect::Mysql
     } else {
+        // connection_url only lets sqlite: urls through otherwise
         Dialect::Sqlite
     }
 }
 
+/// the file behind a sqlite url, the way sqlx reads it
+///
+/// `sqlite:data.db` and `sqlite://data.db` are relative, `sqlite:///abs/data.db`
+/// is absolute; `?mode=rwc` style options are dropped
+pub fn sqlite_path(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("sqlite://")
+        .or_else(|| url.strip_prefix("sqlite:"))?;
+    Some(rest.split('?').next().unwrap_or(rest))
+}
+
+/// put a password into a `scheme://user@host/db` url, for --password-stdin
+pub fn with_password(url: &str, password: &str) -> Result<String, Error> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .filter(|(scheme, _)| !scheme.starts_with("sqlite"))
+        .ok_or_else(|| {
+            Error::Config("--password-stdin needs a postgres:// or mysql:// url".to_string())
+        })?;
+
+    // only the authority part can hold credentials, not the path or query
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(end);
+    let (user, host) = authority.rsplit_once('@').ok_or_else(|| {
+        Error::Config("--password-stdin needs a user in the url (user@host)".to_string())
+    })?;
+    // drop any password that's already there
+    let user = user.split(':').next().unwrap_or(user);
+
+    Ok(format!(
+        "{scheme}://{user}:{}@{host}{tail}",
+        encode_userinfo(password)
+    ))
+}
+
+// percent-encode everything but unreserved chars so the password can't break the url
+fn encode_userinfo(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
 // parse host and database from connection url
 fn parse_connection_url(url: &str) -> (String, String) {
-    // sqlite: just use the file path
+    // sqlite: just use the file name
     if !url.contains("://") || url.starts_with("sqlite:") {
-        let path = url.strip_prefix("sqlite:").unwrap_or(url);
+        let path = sqlite_path(url).unwrap_or(url);
         let db_name = path.rsplit('/').next().unwrap_or(path);
         return ("local".to_string(), db_name.to_string());
     }
@@ -210,24 +899,61 @@ fn parse_connection_url(url: &str) -> (String, String) {
     (host.to_string(), database.to_string())
 }
 
-// turn schema rows into readable text for claude
-fn format_schema(rows: Vec<(String, String, String)>) -> String {
+// "name" with embedded quotes doubled
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// `PRIMARY KEY (a, b)`, or `FOREIGN KEY (a) REFERENCES t(b)` with a referenced table
+fn key_line(columns: &[String], references: Option<(&str, &[String])>) -> String {
+    let key = match references {
+        Some(_) => "FOREIGN KEY",
+        None => "PRIMARY KEY",
+    };
+    let mut line = format!("{key} ({})", columns.join(", "));
+    if let Some((table, ref_columns)) = references {
+        line.push_str(&format!(" REFERENCES {table}"));
+        if !ref_columns.is_empty() {
+            line.push_str(&format!("({})", ref_columns.join(", ")));
+        }
+    }
+    line
+}
+
+// turn schema rows into readable text for claude, keys after each table's columns
+fn format_schema(
+    rows: Vec<(String, String, String)>,
+    keys: &HashMap<String, Vec<String>>,
+    views: &HashSet<String>,
+) -> String {
     let mut result = String::new();
     let mut current_table = String::new();
+    let close = |result: &mut String, table: &str| {
+        for line in keys.get(table).into_iter().flatten() {
+            result.push_str(&format!("  {line}\n"));
+        }
+        result.push(')');
+    };
 
     for (table, column, dtype) in rows {
         if table != current_table {
             if !current_table.is_empty() {
-                result.push_str(")\n\n");
+                close(&mut result, &current_table);
+                result.push_str("\n\n");
             }
-            result.push_str(&format!("TABLE {table} (\n"));
+            let kind = if views.contains(&table) {
+                "VIEW"
+            } else {
+                "TABLE"
+            };
+            result.push_str(&format!("{kind} {table} (\n"));
             current_table = table;
         }
         result.push_str(&format!("  {column} {dtype}\n"));
     }
 
     if !current_table.is_empty() {
-        result.push(')');
+        close(&mut result, &current_table);
     }
 
     result
@@ -260,7 +986,22 @@ fn row_value_to_json(row: &sqlx::any::AnyRow, index: usize) -> serde_json::Value
     if let Ok(v) = row.try_get::<bool, _>(index) {
         return serde_json::Value::Bool(v);
     }
+    if let Ok(v) = row.try_get::<i16, _>(index) {
+        return serde_json::Value::Number(v.into());
+    }
+    if let Ok(v) = row.try_get::<f32, _>(index) {
+        return serde_json::Number::from_f64(f64::from(v))
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null);
+    }
+    // bytes as postgres prints bytea, `\xdeadbeef`
+    if let Ok(v) = row.try_get::<Vec<u8>, _>(index) {
+        let hex: String = v.iter().map(|b| format!("{b:02x}")).collect();
+        return serde_json::Value::String(format!("\\x{hex}"));
+    }
 
-    // give up - some postgres types just don't work with the any driver
-    serde_json::Value::String("<unsupported>".to_string())
+    // the any driver only carries the types above, so timestamps, uuids and
+    // numerics only get here from a driver that didn't turn them into text
+    let type_name = row.column(index).type_info().to_string();
+    serde_json::Value::String(format!("<unsupported {}>", type_name.to_lowercase()))
 }
@@ -0,0 +1,153 @@
+// query plans: the text lines any database gives, and a tree for postgres
+
+use serde::Serialize;
+
+use super::Db;
+use crate::Error;
+
+/// a query plan the way its dialect reads best
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Plan {
+    /// postgres, nodes nested the way the planner runs them
+    Tree(PlanNode),
+    /// sqlite and mysql, one row per line
+    Lines(Vec<String>),
+}
+
+/// one step of a postgres plan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlanNode {
+    /// e.g. `Seq Scan on users  (cost=0.00..1.05 rows=5 width=36)`
+    pub node: String,
+    /// `Filter:`, `Hash Cond:` and similar lines under the node
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plans: Vec<PlanNode>,
+}
+
+impl Db {
+    /// EXPLAIN `sql` into text, one line per plan row
+    pub async fn explain_lines(&self, sql: &str) -> Result<Vec<String>, Error> {
+        let result = self.execute(&format!("EXPLAIN {sql}")).await?;
+        Ok(result
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|v| match v {
+                        serde_json::Value::String(s) => s.clone(),
+                        _ => v.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            })
+            .collect())
+    }
+
+    /// the planner's row estimate for `sql`, the largest of any step
+    ///
+    /// sqlite's EXPLAIN has no estimates, so it's always `None` there
+    pub async fn estimated_rows(&self, sql: &str) -> Result<Option<u64>, Error> {
+        match self.dialect_name() {
+            "postgres" => Ok(plan_rows(&self.explain_lines(sql).await?)),
+            "mysql" => {
+                let result = self.execute(&format!("EXPLAIN {sql}")).await?;
+                let Some(column) = result
+                    .columns
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case("rows"))
+                else {
+                    return Ok(None);
+                };
+                Ok(result
+                    .rows
+                    .iter()
+                    .filter_map(|row| match row.get(column)? {
+                        serde_json::Value::Number(n) => n.as_u64(),
+                        serde_json::Value::String(s) => s.parse().ok(),
+                        _ => None,
+                    })
+                    .max())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// EXPLAIN `sql`, as a tree for postgres and lines otherwise
+    pub async fn explain(&self, sql: &str) -> Result<Plan, Error> {
+        let lines = self.explain_lines(sql).await?;
+        if self.dialect_name() == "postgres"
+            && let Some(tree) = plan_tree(&lines)
+        {
+            return Ok(Plan::Tree(tree));
+        }
+        Ok(Plan::Lines(lines))
+    }
+}
+
+/// nest postgres' text plan by its `->` markers and indentation
+///
+/// the any driver can't decode `FORMAT JSON` plans, so the text one is parsed
+pub fn plan_tree(lines: &[String]) -> Option<PlanNode> {
+    fn node(text: &str) -> PlanNode {
+        PlanNode {
+            node: text.to_string(),
+            details: Vec::new(),
+            plans: Vec::new(),
+        }
+    }
+
+    // (indent, node) from the root down to the node being filled
+    let mut stack: Vec<(usize, PlanNode)> = Vec::new();
+    let fold = |stack: &mut Vec<(usize, PlanNode)>| {
+        if let Some((_, done)) = stack.pop()
+            && let Some((_, parent)) = stack.last_mut()
+        {
+            parent.plans.push(done);
+        }
+    };
+
+    for line in lines {
+        let indent = line.len() - line.trim_start().len();
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(child) = text.strip_prefix("->") {
+            while stack.len() > 1 && stack.last().is_some_and(|(i, _)| *i >= indent) {
+                fold(&mut stack);
+            }
+            stack.push((indent, node(child.trim_start())));
+        } else if stack.is_empty() {
+            stack.push((indent, node(text)));
+        } else {
+            // details sit deeper than their node's arrow
+            while stack.len() > 1 && stack.last().is_some_and(|(i, _)| *i >= indent) {
+                fold(&mut stack);
+            }
+            if let Some((_, current)) = stack.last_mut() {
+                current.details.push(text.to_string());
+            }
+        }
+    }
+
+    while stack.len() > 1 {
+        fold(&mut stack);
+    }
+    stack.pop().map(|(_, root)| root)
+}
+
+/// the largest `rows=` estimate in a postgres text plan
+pub fn plan_rows(lines: &[String]) -> Option<u64> {
+    lines
+        .iter()
+        .flat_map(|line| line.split("rows=").skip(1))
+        .filter_map(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .max()
+}
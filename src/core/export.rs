@@ -0,0 +1,93 @@
+// streaming exports: rows go to the writer as they arrive, never all in memory
+
+use std::io::Write;
+use std::path::Path;
+
+use super::csv::{csv_value, quote};
+use super::{CsvOptions, RowStream};
+use crate::Error;
+
+/// what a streamed export is written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// csv with the usual [csv] settings
+    Csv,
+    /// one json object per row, keyed by column
+    #[value(alias = "jsonl")]
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// guessed from the file's extension, csv unless it says otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext)
+                if ext.eq_ignore_ascii_case("ndjson") || ext.eq_ignore_ascii_case("jsonl") =>
+            {
+                ExportFormat::Ndjson
+            }
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+/// write every row of `rows` to `out`, returns how many there were
+///
+/// the csv header comes from the first row, so `type_header` types come from
+/// it too; an empty result writes nothing
+pub async fn export_rows(
+    rows: &mut RowStream<'_>,
+    format: ExportFormat,
+    csv: &CsvOptions,
+    out: &mut dyn Write,
+) -> Result<usize, Error> {
+    let newline = if csv.crlf { "\r\n" } else { "\n" };
+    let sep = csv.delimiter.to_string();
+    let io = |e: std::io::Error| Error::Server(format!("export: {e}"));
+
+    let mut count = 0;
+    while let Some(row) = rows.next().await {
+        let row = row?;
+        let columns = rows.columns().unwrap_or_default();
+
+        match format {
+            ExportFormat::Csv => {
+                if count == 0 {
+                    let header: Vec<String> = columns
+                        .iter()
+                        .zip(&row)
+                        .map(|(c, v)| {
+                            if csv.type_header {
+                                quote(&format!("{c}:{}", value_type(v)), csv.delimiter)
+                            } else {
+                                quote(c, csv.delimiter)
+                            }
+                        })
+                        .collect();
+                    write!(out, "{}{newline}", header.join(&sep)).map_err(io)?;
+                }
+                let values: Vec<String> = row.iter().map(|v| csv_value(v, csv.delimiter)).collect();
+                write!(out, "{}{newline}", values.join(&sep)).map_err(io)?;
+            }
+            ExportFormat::Ndjson => {
+                let object: serde_json::Map<String, serde_json::Value> =
+                    columns.iter().cloned().zip(row).collect();
+                writeln!(out, "{}", serde_json::Value::Object(object)).map_err(io)?;
+            }
+        }
+        count += 1;
+    }
+    out.flush().map_err(io)?;
+    Ok(count)
+}
+
+// same names as `QueryResult::column_type`, from a single value
+fn value_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(n) if n.is_f64() => "real",
+        serde_json::Value::Number(_) => "integer",
+        _ => "text",
+    }
+}
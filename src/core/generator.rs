@@ -0,0 +1,88 @@
+// the seam between "turn this prompt into sql" and whoever does it
+
+use crate::Error;
+use crate::core::{Ai, Usage};
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// boxed so the trait works as `dyn SqlGenerator`
+pub type SqlFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'a>>;
+
+/// writes sql for a prompt: the real `Ai`, or a canned one in tests
+pub trait SqlGenerator: Send + Sync {
+    fn generate_sql<'a>(&'a self, prompt: &'a str, schema: &'a str) -> SqlFuture<'a, String>;
+
+    /// every alternative the generator came up with, just the one by default
+    fn generate_candidates<'a>(
+        &'a self,
+        prompt: &'a str,
+        schema: &'a str,
+    ) -> SqlFuture<'a, Vec<String>> {
+        Box::pin(async move { Ok(vec![self.generate_sql(prompt, schema).await?]) })
+    }
+
+    /// `generate_candidates`, sending text to `chunks` as it's written; generators
+    /// that can't stream send nothing and just answer
+    fn generate_candidates_streaming<'a>(
+        &'a self,
+        prompt: &'a str,
+        schema: &'a str,
+        chunks: UnboundedSender<String>,
+    ) -> SqlFuture<'a, Vec<String>> {
+        drop(chunks);
+        self.generate_candidates(prompt, schema)
+    }
+
+    /// tokens used so far, for generators that are billed per token
+    fn session_usage(&self) -> Option<Usage> {
+        None
+    }
+
+    /// how sure the generator was of its latest sql, 0-100, if it said
+    fn last_confidence(&self) -> Option<u8> {
+        None
+    }
+
+    /// the request `generate_sql` would send, for generators that send one
+    fn show_request(&self, _prompt: &str, _schema: &str) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl SqlGenerator for Ai {
+    fn generate_sql<'a>(&'a self, prompt: &'a str, schema: &'a str) -> SqlFuture<'a, String> {
+        Box::pin(Ai::generate_sql(self, prompt, schema))
+    }
+
+    fn generate_candidates<'a>(
+        &'a self,
+        prompt: &'a str,
+        schema: &'a str,
+    ) -> SqlFuture<'a, Vec<String>> {
+        Box::pin(Ai::generate_candidates(self, prompt, schema))
+    }
+
+    fn generate_candidates_streaming<'a>(
+        &'a self,
+        prompt: &'a str,
+        schema: &'a str,
+        chunks: UnboundedSender<String>,
+    ) -> SqlFuture<'a, Vec<String>> {
+        Box::pin(Ai::generate_candidates_streaming(
+            self, prompt, schema, chunks,
+        ))
+    }
+
+    fn session_usage(&self) -> Option<Usage> {
+        Some(Ai::session_usage(self))
+    }
+
+    fn last_confidence(&self) -> Option<u8> {
+        Ai::last_confidence(self)
+    }
+
+    fn show_request(&self, prompt: &str, schema: &str) -> Option<serde_json::Value> {
+        Some(Ai::show_request(self, prompt, schema))
+    }
+}
@@ -1,9 +1,37 @@
 // core logic - ai, database, and safety checks
 
 mod ai;
+mod cache;
+mod csv;
 mod db;
+mod explain;
+mod export;
+mod generator;
+mod pivot;
+mod quote;
+mod redact;
 mod safety;
+mod schema;
+mod sort;
+mod statement;
+mod time;
 
-pub use ai::{Ai, Provider};
-pub use db::{Db, QueryResult};
-pub use safety::Safety;
+pub use ai::{
+    Ai, AiOptions, DEFAULT_ANTHROPIC_VERSION, DEFAULT_MAX_SCHEMA_BYTES, GeneratedSql, Provider,
+    SqlStream, Usage, extract_sql, split_candidates,
+};
+pub use cache::ResultCache;
+pub use csv::CsvOptions;
+pub use db::{
+    DEFAULT_MAX_ROWS, Db, DbConfig, QueryResult, RowStream, connection_url, sqlite_path,
+    with_password,
+};
+pub use explain::{Plan, PlanNode, plan_rows, plan_tree};
+pub use export::{ExportFormat, export_rows};
+pub use generator::{SqlFuture, SqlGenerator};
+pub use quote::quote_reserved;
+pub use redact::redact_literals;
+pub use safety::{DEFAULT_ROW_LIMIT, Safety};
+pub use schema::{schema_tables, scope_schema, timestamp_columns};
+pub use statement::StatementKind;
+pub use time::{date_context, parse_time_bound};
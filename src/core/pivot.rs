@@ -0,0 +1,72 @@
+// client-side pivot of long results into a wide table
+
+use std::collections::HashMap;
+
+use super::QueryResult;
+use crate::Error;
+
+impl QueryResult {
+    /// spread the distinct values of `column` out into columns
+    ///
+    /// the last other column holds the values, the rest identify a row;
+    /// combinations that never show up are left as empty strings
+    pub fn pivot(&self, column: &str) -> Result<Self, Error> {
+        let pivot = self
+            .columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(column))
+            .ok_or_else(|| Error::Config(format!("--pivot: no column named '{column}'")))?;
+
+        let value = (0..self.columns.len())
+            .rev()
+            .find(|&i| i != pivot)
+            .ok_or_else(|| Error::Config("--pivot needs at least two columns".to_string()))?;
+        let keys: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| i != pivot && i != value)
+            .collect();
+
+        // new columns, in the order they first appear
+        let mut headers: Vec<String> = Vec::new();
+        let mut header_index: HashMap<String, usize> = HashMap::new();
+        for row in &self.rows {
+            let name = header(&row[pivot]);
+            if !header_index.contains_key(&name) {
+                header_index.insert(name.clone(), headers.len());
+                headers.push(name);
+            }
+        }
+
+        let mut rows: Vec<Vec<serde_json::Value>> = Vec::new();
+        let mut row_index: HashMap<Vec<String>, usize> = HashMap::new();
+        for row in &self.rows {
+            let key: Vec<String> = keys.iter().map(|&i| row[i].to_string()).collect();
+            let at = *row_index.entry(key).or_insert_with(|| {
+                let mut wide: Vec<serde_json::Value> =
+                    keys.iter().map(|&i| row[i].clone()).collect();
+                wide.extend(std::iter::repeat_n("".into(), headers.len()));
+                rows.push(wide);
+                rows.len() - 1
+            });
+
+            let col = keys.len() + header_index[&header(&row[pivot])];
+            rows[at][col] = row[value].clone();
+        }
+
+        let mut columns: Vec<String> = keys.iter().map(|&i| self.columns[i].clone()).collect();
+        columns.extend(headers);
+
+        Ok(Self {
+            columns,
+            row_count: rows.len(),
+            rows,
+            truncated: self.truncated,
+        })
+    }
+}
+
+fn header(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,290 @@
+// quote schema identifiers that clash with reserved words, e.g. a table called `order`
+
+use crate::core::schema::is_key_line;
+use std::collections::HashSet;
+
+// reserved in every dialect we talk to
+const COMMON: &[&str] = &[
+    "all",
+    "and",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "case",
+    "check",
+    "column",
+    "constraint",
+    "create",
+    "default",
+    "delete",
+    "desc",
+    "distinct",
+    "drop",
+    "else",
+    "end",
+    "exists",
+    "foreign",
+    "from",
+    "group",
+    "having",
+    "in",
+    "index",
+    "insert",
+    "into",
+    "is",
+    "join",
+    "key",
+    "like",
+    "limit",
+    "not",
+    "null",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "references",
+    "select",
+    "set",
+    "table",
+    "then",
+    "to",
+    "union",
+    "unique",
+    "update",
+    "values",
+    "when",
+    "where",
+];
+
+const POSTGRES: &[&str] = &[
+    "analyse",
+    "analyze",
+    "array",
+    "both",
+    "cast",
+    "collate",
+    "current_date",
+    "current_time",
+    "current_user",
+    "do",
+    "fetch",
+    "grant",
+    "leading",
+    "offset",
+    "only",
+    "placing",
+    "returning",
+    "session_user",
+    "some",
+    "symmetric",
+    "trailing",
+    "user",
+    "window",
+    "with",
+];
+
+const MYSQL: &[&str] = &[
+    "condition",
+    "database",
+    "databases",
+    "describe",
+    "div",
+    "interval",
+    "keys",
+    "match",
+    "mod",
+    "option",
+    "range",
+    "rank",
+    "read",
+    "release",
+    "rows",
+    "schema",
+    "show",
+    "signal",
+    "status",
+    "usage",
+    "window",
+    "write",
+];
+
+const SQLITE: &[&str] = &[
+    "abort",
+    "action",
+    "autoincrement",
+    "commit",
+    "conflict",
+    "glob",
+    "indexed",
+    "offset",
+    "plan",
+    "pragma",
+    "query",
+    "raise",
+    "regexp",
+    "transaction",
+    "vacuum",
+    "view",
+    "virtual",
+];
+
+// words after which a bare name must be a table
+const TABLE_BEFORE: &[&str] = &["from", "join", "into", "update", "table"];
+
+// words or symbols that can come right before a column
+const COLUMN_BEFORE: &[&str] = &[
+    "select", ",", "(", "where", "and", "or", "on", "by", "set", "distinct", "not",
+];
+
+// ...and right after one
+const COLUMN_AFTER: &[&str] = &[
+    ",", ")", "=", "<", ">", "!", "+", "-", "*", "/", ";", "is", "in", "like", "between", "from",
+    "as", "asc", "desc", "and", "or", "limit", "order", "group",
+];
+
+/// quote bare references to schema identifiers that are reserved words in `dialect`
+///
+/// heuristic: only names that exist in the schema are touched, and only where
+/// they sit in a table or column position, so `ORDER BY` stays a keyword
+pub fn quote_reserved(sql: &str, schema: &str, dialect: &str) -> String {
+    let dialect_words = match dialect {
+        "postgres" => POSTGRES,
+        "mysql" => MYSQL,
+        _ => SQLITE,
+    };
+    let reserved: HashSet<&str> = COMMON.iter().chain(dialect_words).copied().collect();
+
+    let clashing: HashSet<String> = schema_identifiers(schema)
+        .into_iter()
+        .filter(|name| reserved.contains(name.as_str()))
+        .collect();
+    if clashing.is_empty() {
+        return sql.to_string();
+    }
+
+    let (open, close) = if dialect == "mysql" {
+        ('`', '`')
+    } else {
+        ('"', '"')
+    };
+
+    let tokens = tokenize(sql);
+    let lower = |i: usize| tokens.get(i).map(|t| t.text.to_lowercase());
+
+    let mut out = String::with_capacity(sql.len() + 8);
+    let mut last = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if !token.word || !clashing.contains(&token.text.to_lowercase()) {
+            continue;
+        }
+
+        let prev = i.checked_sub(1).and_then(lower);
+        let next = lower(i + 1);
+        let is = |t: &Option<String>, set: &[&str]| t.as_deref().is_some_and(|t| set.contains(&t));
+
+        let dotted = prev.as_deref() == Some(".") || next.as_deref() == Some(".");
+        let table = is(&prev, TABLE_BEFORE);
+        let column = is(&prev, COLUMN_BEFORE) && (next.is_none() || is(&next, COLUMN_AFTER));
+
+        if dotted || table || column {
+            out.push_str(&sql[last..token.start]);
+            out.push(open);
+            out.push_str(token.text);
+            out.push(close);
+            last = token.start + token.text.len();
+        }
+    }
+    out.push_str(&sql[last..]);
+    out
+}
+
+// table and column names out of the `TABLE name (\n  col type\n)` schema text
+fn schema_identifiers(schema: &str) -> Vec<String> {
+    schema
+        .lines()
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            if let Some(rest) = trimmed
+                .strip_prefix("TABLE ")
+                .or_else(|| trimmed.strip_prefix("VIEW "))
+            {
+                rest.split_whitespace().next()
+            } else if line.starts_with("  ") && !is_key_line(line) {
+                trimmed.split_whitespace().next()
+            } else {
+                None
+            }
+        })
+        .map(|name| name.to_lowercase())
+        .collect()
+}
+
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    word: bool,
+}
+
+// words and single symbols; strings, quoted names and comments are skipped
+fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'\'' | b'"' | b'`' => {
+                // skip to the closing quote, doubled quotes are escapes
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == c {
+                        if bytes.get(i + 1) == Some(&c) {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                // a quoted name still counts as a token for its neighbours
+                tokens.push(Token {
+                    text: "",
+                    start: i,
+                    word: false,
+                });
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            _ if c.is_ascii_alphanumeric() || c == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push(Token {
+                    text: &sql[start..i],
+                    start,
+                    word: !c.is_ascii_digit(),
+                });
+            }
+            _ if c.is_ascii_whitespace() => i += 1,
+            _ => {
+                // one symbol, keeping multi-byte chars whole
+                let len = sql[i..].chars().next().map_or(1, char::len_utf8);
+                tokens.push(Token {
+                    text: &sql[i..i + len],
+                    start: i,
+                    word: false,
+                });
+                i += len;
+            }
+        }
+    }
+
+    tokens
+}
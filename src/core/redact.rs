@@ -0,0 +1,78 @@
+// strip literal values out of sql before it's logged, keeping its shape
+
+/// `sql` with string and number literals swapped for `?`
+///
+/// quoted identifiers and comments stay as they are, so
+/// `WHERE "email" = 'a@b.c' AND id > 42` logs as `WHERE "email" = ? AND id > ?`
+pub fn redact_literals(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    // last char was part of a word, so a digit belongs to a name like `t2`
+    let mut in_word = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // '' inside a string is an escaped quote, not the end
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push('?');
+                in_word = false;
+            }
+            '"' | '`' => {
+                out.push(c);
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if next == c {
+                        break;
+                    }
+                }
+                in_word = false;
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                out.push(c);
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if next == '\n' {
+                        break;
+                    }
+                }
+                in_word = false;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                out.push(c);
+                let mut last = ' ';
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if last == '*' && next == '/' {
+                        break;
+                    }
+                    last = next;
+                }
+                in_word = false;
+            }
+            c if c.is_ascii_digit() && !in_word => {
+                // 42, 4.2, 4e2, 0x2a
+                while chars
+                    .peek()
+                    .is_some_and(|n| n.is_ascii_alphanumeric() || *n == '.')
+                {
+                    chars.next();
+                }
+                out.push('?');
+            }
+            c => {
+                out.push(c);
+                in_word = c.is_alphanumeric() || c == '_' || c == '$';
+            }
+        }
+    }
+    out
+}
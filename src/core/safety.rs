@@ -1,29 +1,62 @@
-// basic sql safety checks
+// basic sql safety checks, statement by statement
 // catches obvious dangerous stuff but not everything
 
+use crate::core::StatementKind;
+use crate::core::statement::{
+    has_limit, has_line_comment, has_where, is_blank, reads_only, split_statements,
+};
+
+/// rows a generated SELECT is capped at unless --limit or --no-limit say otherwise
+pub const DEFAULT_ROW_LIMIT: u64 = 100;
+
 pub struct Safety {
     pub is_dangerous: bool,
+    /// DROP, TRUNCATE or DELETE without WHERE, data is gone for good
+    pub is_destructive: bool,
     pub reason: String,
     pub warning: Option<String>,
 }
 
 impl Safety {
     pub fn check(sql: &str) -> Self {
-        let sql_upper = sql.to_uppercase();
+        // each statement by its structure, so an `is_deleted` column or a
+        // `'DROP it'` string isn't a statement
+        let statements: Vec<(StatementKind, bool)> = split_statements(sql)
+            .into_iter()
+            .map(|part| {
+                let kind = StatementKind::from_sql(part);
+                (kind, has_where(part, kind))
+            })
+            .filter(|(kind, _)| *kind != StatementKind::Other)
+            .collect();
+        let has = |kind| statements.iter().any(|(k, _)| *k == kind);
 
         // these are almost always bad news
         let dangerous = [
-            ("DROP ", "DROP can permanently delete tables"),
-            ("TRUNCATE ", "TRUNCATE deletes all data"),
-            ("ALTER ", "ALTER modifies table structure"),
-            ("; DROP", "looks like sql injection"),
-            ("--", "sql comment, possible injection"),
+            (
+                StatementKind::Drop,
+                "DROP can permanently delete tables",
+                true,
+            ),
+            (StatementKind::Truncate, "TRUNCATE deletes all data", true),
+            (
+                StatementKind::Alter,
+                "ALTER modifies table structure",
+                false,
+            ),
         ];
 
-        for (pattern, reason) in dangerous {
-            if sql_upper.contains(pattern) {
+        for (kind, reason, destructive) in dangerous {
+            if has(kind) {
+                // a drop tacked on after something else
+                let reason = if kind == StatementKind::Drop && statements[0].0 != kind {
+                    "looks like sql injection"
+                } else {
+                    reason
+                };
                 return Self {
                     is_dangerous: true,
+                    is_destructive: destructive,
                     reason: reason.to_string(),
                     warning: None,
                 };
@@ -31,37 +64,169 @@ impl Safety {
         }
 
         // delete/update without where = wipe everything
-        if sql_upper.contains("DELETE") && !sql_upper.contains("WHERE") {
+        if statements.contains(&(StatementKind::Delete, false)) {
             return Self {
                 is_dangerous: true,
+                is_destructive: true,
                 reason: "DELETE without WHERE deletes all rows".to_string(),
                 warning: None,
             };
         }
 
-        if sql_upper.contains("UPDATE") && !sql_upper.contains("WHERE") {
+        if statements.contains(&(StatementKind::Update, false)) {
             return Self {
                 is_dangerous: true,
+                is_destructive: false,
                 reason: "UPDATE without WHERE updates all rows".to_string(),
                 warning: None,
             };
         }
 
         // not dangerous but worth mentioning
-        let warning = if sql_upper.contains("DELETE") {
+        let warning = if has(StatementKind::Delete) {
             Some("this will delete data".to_string())
-        } else if sql_upper.contains("UPDATE") {
+        } else if has(StatementKind::Update) {
             Some("this will update data".to_string())
-        } else if sql_upper.contains("INSERT") {
+        } else if has(StatementKind::Insert) {
             Some("this will insert data".to_string())
+        } else if has_line_comment(sql) {
+            // what it hides was already left out of the checks above
+            Some("has a -- comment, check nothing was commented out".to_string())
         } else {
             None
         };
 
         Self {
             is_dangerous: false,
+            is_destructive: false,
             reason: String::new(),
             warning,
         }
     }
+
+    /// every statement only reads: a SELECT (`WITH` included) or an EXPLAIN,
+    /// with nothing in it that writes, for --read-only
+    pub fn is_read_only(sql: &str) -> bool {
+        split_statements(sql).into_iter().all(reads_only)
+    }
+
+    /// why --read-only won't run `sql`, `None` when it may
+    pub fn read_only_refusal(sql: &str) -> Option<String> {
+        (!Self::is_read_only(sql)).then(|| {
+            format!(
+                "read-only mode: refusing {}, only SELECT and EXPLAIN can run",
+                StatementKind::from_sql(sql).label()
+            )
+        })
+    }
+
+    /// one statement that only reads, so its result can be answered again from a cache
+    pub fn is_single_read(sql: &str) -> bool {
+        let statements: Vec<&str> = split_statements(sql)
+            .into_iter()
+            .filter(|s| !is_blank(s))
+            .collect();
+        matches!(statements[..], [statement] if reads_only(statement))
+    }
+
+    /// why `sql` can't go after an EXPLAIN, `None` when it's one statement that
+    /// only reads: sqlite runs whatever follows `EXPLAIN ...;`, and postgres's
+    /// `EXPLAIN ANALYZE` runs the statement itself
+    pub fn explain_refusal(sql: &str) -> Option<String> {
+        let statements: Vec<&str> = split_statements(sql)
+            .into_iter()
+            .filter(|s| !is_blank(s))
+            .collect();
+        match statements[..] {
+            [] => Some("there is no statement to explain".to_string()),
+            [statement] if reads_only(statement) => None,
+            [statement] => Some(format!(
+                "only a SELECT is explained, not {}",
+                StatementKind::from_sql(statement).label()
+            )),
+            _ => Some(format!(
+                "the sql has {} statements, EXPLAIN takes one",
+                statements.len()
+            )),
+        }
+    }
+
+    /// `sql` with `LIMIT limit` added when it's a single SELECT that doesn't
+    /// bound its rows yet, `None` when it's left alone
+    pub fn with_limit(sql: &str, limit: u64) -> Option<String> {
+        let statement = single_select(sql).filter(|s| !has_limit(s))?;
+        // on its own line so a trailing -- comment doesn't swallow it
+        let separator = if has_line_comment(statement) {
+            "\n"
+        } else {
+            " "
+        };
+        Some(format!("{statement}{separator}LIMIT {limit}"))
+    }
+
+    /// a single SELECT wrapped in `SELECT * FROM (...) LIMIT .. OFFSET ..`
+    /// for paging through it, `None` for anything else
+    pub fn paginate(sql: &str, limit: Option<u64>, offset: Option<u64>) -> Option<String> {
+        let statement = single_select(sql)?;
+        // the newline keeps a trailing -- comment off the closing paren
+        let mut paged = format!("SELECT * FROM (\n{statement}\n) AS page");
+        // sqlite and mysql only take an OFFSET after a LIMIT
+        if let Some(limit) = limit.or(offset.map(|_| i64::MAX as u64)) {
+            paged.push_str(&format!(" LIMIT {limit}"));
+        }
+        if let Some(offset) = offset {
+            paged.push_str(&format!(" OFFSET {offset}"));
+        }
+        Some(paged)
+    }
+
+    /// the table a DROP TABLE, TRUNCATE or DELETE FROM acts on, as written
+    pub fn target_table(sql: &str) -> Option<String> {
+        let spaced = sql.replace(';', " ; ");
+        let words: Vec<&str> = spaced.split_whitespace().collect();
+        let is = |i: usize, keyword: &str| {
+            words
+                .get(i)
+                .is_some_and(|w| w.eq_ignore_ascii_case(keyword))
+        };
+
+        for i in 0..words.len() {
+            let mut at = if is(i, "DROP") && is(i + 1, "TABLE") {
+                i + 2
+            } else if is(i, "TRUNCATE") {
+                if is(i + 1, "TABLE") { i + 2 } else { i + 1 }
+            } else if is(i, "DELETE") && is(i + 1, "FROM") {
+                i + 2
+            } else {
+                continue;
+            };
+            if is(at, "IF") && is(at + 1, "EXISTS") {
+                at += 2;
+            }
+            if let Some(name) = words.get(at).filter(|w| **w != ";") {
+                let name = name
+                    .trim_end_matches(',')
+                    .trim_matches(['"', '`', '[', ']']);
+                return Some(name.to_string());
+            }
+        }
+        None
+    }
+}
+
+// the one statement of `sql` when it's a plain read, trailing space and `;` gone
+fn single_select(sql: &str) -> Option<&str> {
+    let statements: Vec<&str> = split_statements(sql)
+        .into_iter()
+        .filter(|s| !is_blank(s))
+        .collect();
+    match statements[..] {
+        [statement]
+            if StatementKind::from_sql(statement) == StatementKind::Select
+                && reads_only(statement) =>
+        {
+            Some(statement.trim())
+        }
+        _ => None,
+    }
 }
@@ -0,0 +1,58 @@
+// helpers on the `TABLE name (\n  col type\n)` schema text (views are `VIEW name (`)
+
+/// table names in the order the schema lists them
+pub fn schema_tables(schema: &str) -> Vec<&str> {
+    schema.split("\n\n").filter_map(table_name).collect()
+}
+
+/// only the blocks for `tables` (matched case-insensitively), for --interactive-schema
+pub fn scope_schema(schema: &str, tables: &[String]) -> String {
+    schema
+        .split("\n\n")
+        .filter(|block| {
+            table_name(block)
+                .is_some_and(|name| tables.iter().any(|t| t.eq_ignore_ascii_case(name)))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// `table.column` for every created_at, updated_at or inserted_at column
+pub fn timestamp_columns(schema: &str) -> Vec<String> {
+    const NAMES: &[&str] = &["created_at", "updated_at", "inserted_at"];
+    let mut found = Vec::new();
+    for block in schema.split("\n\n") {
+        let Some(table) = table_name(block) else {
+            continue;
+        };
+        // column lines are `  name type`, after the `TABLE name (` line and before the keys
+        for line in block
+            .trim_start()
+            .lines()
+            .skip(1)
+            .filter(|l| !is_key_line(l))
+        {
+            if let Some(column) = line.split_whitespace().next()
+                && NAMES.iter().any(|name| name.eq_ignore_ascii_case(column))
+            {
+                found.push(format!("{table}.{column}"));
+            }
+        }
+    }
+    found
+}
+
+fn table_name(block: &str) -> Option<&str> {
+    let block = block.trim_start();
+    block
+        .strip_prefix("TABLE ")
+        .or_else(|| block.strip_prefix("VIEW "))?
+        .split_whitespace()
+        .next()
+}
+
+/// a `  PRIMARY KEY (..)` or `  FOREIGN KEY (..)` line rather than a column
+pub(crate) fn is_key_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("PRIMARY KEY (") || line.starts_with("FOREIGN KEY (")
+}
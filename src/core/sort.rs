@@ -0,0 +1,72 @@
+// client-side sorting, with numbers the driver handed back as text
+
+use std::cmp::Ordering;
+
+use super::QueryResult;
+use crate::Error;
+
+impl QueryResult {
+    /// per column, whether every non-null value reads as a number
+    ///
+    /// the any driver surfaces some numeric types as strings, this only
+    /// changes how they compare, the values themselves are left alone
+    pub fn numeric_columns(&self) -> Vec<bool> {
+        (0..self.columns.len())
+            .map(|i| {
+                let mut values = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .filter(|v| !v.is_null())
+                    .peekable();
+                values.peek().is_some() && values.all(|v| as_number(v).is_some())
+            })
+            .collect()
+    }
+
+    /// sort the rows by `column`, nulls always last
+    pub fn sort_by(&mut self, column: &str, descending: bool) -> Result<(), Error> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(column))
+            .ok_or_else(|| Error::Config(format!("sort: no column named '{column}'")))?;
+        let numeric = self.numeric_columns()[index];
+
+        self.rows.sort_by(|a, b| {
+            match (a.get(index), b.get(index)) {
+                (Some(a), Some(b)) if !a.is_null() && !b.is_null() => {
+                    let ord = if numeric {
+                        let (a, b) = (as_number(a), as_number(b));
+                        a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                    } else {
+                        as_text(a).cmp(&as_text(b))
+                    };
+                    if descending { ord.reverse() } else { ord }
+                }
+                // one or both null
+                (a, b) => {
+                    let null = |v: Option<&serde_json::Value>| v.is_none_or(|v| v.is_null());
+                    null(a).cmp(&null(b))
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+fn as_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        // "inf" and "nan" parse too, but nobody means those
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn as_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,282 @@
+// what kind of statement a piece of sql is, decided in one place
+
+/// the statement a piece of sql starts with, past comments and CTEs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Drop,
+    Truncate,
+    Alter,
+    Create,
+    Explain,
+    Other,
+}
+
+impl StatementKind {
+    /// classify the first statement in `sql`
+    ///
+    /// leading `--` and `/* */` comments and parens are skipped; a `WITH` is
+    /// whatever follows its CTEs, unless a CTE itself writes
+    /// (`WITH d AS (DELETE ...) SELECT ...` is a DELETE)
+    pub fn from_sql(sql: &str) -> Self {
+        let tokens = tokens(sql);
+        let mut words = tokens.iter().filter_map(|t| match t {
+            Token::Word(w) => Some(w.as_str()),
+            _ => None,
+        });
+        let Some(first) = words.next() else {
+            return StatementKind::Other;
+        };
+        if first != "WITH" {
+            return Self::keyword(first);
+        }
+
+        // the main statement is the first keyword back at depth 0
+        let mut depth = 0usize;
+        let mut main = StatementKind::Other;
+        let mut cte_write = None;
+        let mut after_paren = false;
+        for token in &tokens[1..] {
+            match token {
+                Token::Open => {
+                    depth += 1;
+                    after_paren = true;
+                    continue;
+                }
+                Token::Close => depth = depth.saturating_sub(1),
+                Token::Word(w) => {
+                    let kind = Self::keyword(w);
+                    if after_paren && cte_write.is_none() && kind.is_write() {
+                        cte_write = Some(kind);
+                    }
+                    if depth == 0 && kind != StatementKind::Other {
+                        main = kind;
+                        break;
+                    }
+                }
+            }
+            after_paren = false;
+        }
+        match (main, cte_write) {
+            (StatementKind::Select | StatementKind::Other, Some(write)) => write,
+            (main, _) => main,
+        }
+    }
+
+    /// upper case name, `QUERY` for anything unrecognised
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatementKind::Select => "SELECT",
+            StatementKind::Insert => "INSERT",
+            StatementKind::Update => "UPDATE",
+            StatementKind::Delete => "DELETE",
+            StatementKind::Drop => "DROP",
+            StatementKind::Truncate => "TRUNCATE",
+            StatementKind::Alter => "ALTER",
+            StatementKind::Create => "CREATE",
+            StatementKind::Explain => "EXPLAIN",
+            StatementKind::Other => "QUERY",
+        }
+    }
+
+    /// only reads data, so it may go to a replica or be cached
+    pub fn is_read(&self) -> bool {
+        matches!(self, StatementKind::Select | StatementKind::Explain)
+    }
+
+    fn is_write(&self) -> bool {
+        matches!(
+            self,
+            StatementKind::Insert
+                | StatementKind::Update
+                | StatementKind::Delete
+                | StatementKind::Drop
+                | StatementKind::Truncate
+                | StatementKind::Alter
+                | StatementKind::Create
+        )
+    }
+
+    fn keyword(word: &str) -> Self {
+        match word {
+            "SELECT" | "VALUES" | "TABLE" => StatementKind::Select,
+            "INSERT" | "REPLACE" | "MERGE" => StatementKind::Insert,
+            "UPDATE" => StatementKind::Update,
+            "DELETE" => StatementKind::Delete,
+            "DROP" => StatementKind::Drop,
+            "TRUNCATE" => StatementKind::Truncate,
+            "ALTER" => StatementKind::Alter,
+            "CREATE" => StatementKind::Create,
+            "EXPLAIN" => StatementKind::Explain,
+            _ => StatementKind::Other,
+        }
+    }
+}
+
+/// each `;` separated statement, a `;` in quotes or a comment doesn't split
+pub(crate) fn split_statements(sql: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut chars = sql.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        match c {
+            ';' => {
+                statements.push(&sql[start..at]);
+                start = at + 1;
+            }
+            '-' if chars.peek().is_some_and(|(_, c)| *c == '-') => {
+                while chars.next().is_some_and(|(_, c)| c != '\n') {}
+            }
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '*') => {
+                chars.next();
+                let mut last = ' ';
+                for (_, c) in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '\'' | '"' | '`' => while chars.next().is_some_and(|(_, next)| next != c) {},
+            _ => {}
+        }
+    }
+    statements.push(&sql[start..]);
+    statements
+}
+
+/// nothing but whitespace and comments, like what follows a final `;`
+pub(crate) fn is_blank(sql: &str) -> bool {
+    tokens(sql).is_empty() && !sql.contains(['\'', '"', '`'])
+}
+
+// words that write or take locks even inside a read, `SELECT ... INTO t`,
+// `FOR UPDATE` or `EXPLAIN ANALYZE DELETE ...`
+const WRITE_WORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "DROP", "TRUNCATE", "ALTER", "CREATE", "INTO", "GRANT",
+    "REVOKE",
+];
+
+/// a SELECT or EXPLAIN with no write keyword anywhere in it; empty counts too
+pub(crate) fn reads_only(sql: &str) -> bool {
+    let tokens = tokens(sql);
+    let mut words = tokens.iter().filter_map(|t| match t {
+        Token::Word(w) => Some(w.as_str()),
+        _ => None,
+    });
+    if words.clone().next().is_none() {
+        return true;
+    }
+    StatementKind::from_sql(sql).is_read() && !words.any(|w| WRITE_WORDS.contains(&w))
+}
+
+/// a `--` comment outside of quoted text
+pub(crate) fn has_line_comment(sql: &str) -> bool {
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '-' if chars.peek() == Some(&'-') => return true,
+            '\'' | '"' | '`' => while chars.next().is_some_and(|next| next != c) {},
+            _ => {}
+        }
+    }
+    false
+}
+
+/// whether the outer query already bounds its rows with LIMIT, FETCH or OFFSET
+/// (a LIMIT in a subquery or CTE doesn't count); a locking `FOR ...` also
+/// counts, a LIMIT can't go after it
+pub(crate) fn has_limit(sql: &str) -> bool {
+    let mut depth = 0usize;
+    for token in tokens(sql) {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => depth = depth.saturating_sub(1),
+            Token::Word(w)
+                if depth == 0 && matches!(w.as_str(), "LIMIT" | "FETCH" | "OFFSET" | "FOR") =>
+            {
+                return true;
+            }
+            Token::Word(_) => {}
+        }
+    }
+    false
+}
+
+/// whether the first `kind` statement in `sql` (a DELETE or UPDATE) has a WHERE
+/// of its own, not just one in a subquery or another CTE
+pub(crate) fn has_where(sql: &str, kind: StatementKind) -> bool {
+    let tokens = tokens(sql);
+    let mut depth = 0usize;
+    // depth of the statement once seen
+    let mut write_depth = None;
+    for token in &tokens {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => {
+                // the statement holding the write has ended
+                if write_depth.is_some_and(|d| depth <= d) {
+                    return false;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            Token::Word(w) => match write_depth {
+                None if StatementKind::keyword(w) == kind => write_depth = Some(depth),
+                Some(d) if d == depth && w == "WHERE" => return true,
+                _ => {}
+            },
+        }
+    }
+    false
+}
+
+enum Token {
+    // upper cased
+    Word(String),
+    Open,
+    Close,
+}
+
+// words and parens of the first statement, without comments or quoted text
+fn tokens(sql: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => break,
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '-' if chars.peek() == Some(&'-') => while chars.next().is_some_and(|c| c != '\n') {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '\'' | '"' | '`' => {
+                // a doubled quote just ends one run and starts the next
+                while chars.next().is_some_and(|next| next != c) {}
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_uppercase().to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    word.extend(next.to_uppercase());
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
@@ -0,0 +1,36 @@
+// relative time windows for prompts like "last 7 days"
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone};
+
+/// turn `7d`, `2w`, `3m`, `1y` or a plain `YYYY-MM-DD` into a date, counting back from `today`
+pub fn parse_time_bound(s: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let s = s.trim();
+
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let err = || format!("invalid time bound '{s}', expected e.g. 7d, 2w, 3m, 1y or YYYY-MM-DD");
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+    let (n, unit) = s.split_at(split);
+    let n: u32 = n.parse().map_err(|_| err())?;
+
+    let date = match unit {
+        "d" => today.checked_sub_signed(Duration::days(n.into())),
+        "w" => today.checked_sub_signed(Duration::weeks(n.into())),
+        "m" => today.checked_sub_months(chrono::Months::new(n)),
+        "y" => today.checked_sub_months(chrono::Months::new(n.saturating_mul(12))),
+        _ => None,
+    };
+
+    date.ok_or_else(err)
+}
+
+/// the date, weekday, time and utc offset, as told to the model
+pub fn date_context<Tz: TimeZone>(now: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    now.format("Current date is %Y-%m-%d (%A), local time %H:%M, timezone UTC%:z.")
+        .to_string()
+}
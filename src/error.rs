@@ -12,7 +12,28 @@ pub enum Error {
         code(nlql::db::error),
         help("check your query syntax and database connection")
     )]
-    Database(#[from] sqlx::Error),
+    Database(sqlx::Error),
+
+    #[error("column {column} has a type the database driver can't read ({type_name})")]
+    #[diagnostic(
+        code(nlql::db::unsupported_type),
+        help("cast it to text in the sql, e.g. created_at::text or CAST(created_at AS TEXT)")
+    )]
+    UnsupportedType { column: String, type_name: String },
+
+    #[error("could not connect to database: {0}")]
+    #[diagnostic(
+        code(nlql::db::connect),
+        help("check the connection url and that the database is reachable")
+    )]
+    Connection(sqlx::Error),
+
+    #[error("connection timed out after {0}s")]
+    #[diagnostic(
+        code(nlql::db::timeout),
+        help("check the host and port, or allow longer with --db-timeout")
+    )]
+    ConnectTimeout(u64),
 
     #[error("ai api failed: {0}")]
     #[diagnostic(
@@ -21,6 +42,13 @@ pub enum Error {
     )]
     Ai(String),
 
+    #[error("the model needs more detail: {0}")]
+    #[diagnostic(
+        code(nlql::ai::clarify),
+        help("refine your prompt with the missing detail and try again")
+    )]
+    NeedsClarification(String),
+
     #[error("no api key found for {provider}")]
     #[diagnostic(
         code(nlql::ai::no_key),
@@ -42,4 +70,106 @@ pub enum Error {
     #[error("server error: {0}")]
     #[diagnostic(code(nlql::server))]
     Server(String),
+
+    #[error("blocked: {0}")]
+    #[diagnostic(
+        code(nlql::safety::blocked),
+        help("rephrase the prompt, or confirm when asked to run it anyway")
+    )]
+    Blocked(String),
+
+    #[error("{0}")]
+    #[diagnostic(code(nlql::config))]
+    Config(String),
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        // the any driver only carries ints, floats, text, bytes and bools, and
+        // refuses the whole row over a column like a timestamp
+        if let sqlx::Error::ColumnDecode { index, source } = &e {
+            let message = source.to_string();
+            if message.contains("does not support")
+                && let Some((_, type_name)) = message.split_once(" type ")
+            {
+                // `... the SQLite type SqliteTypeInfo(Datetime)` -> `Datetime`
+                let type_name = match type_name.split_once("TypeInfo(") {
+                    Some((_, inner)) => inner.strip_suffix(')').unwrap_or(inner),
+                    None => type_name,
+                };
+                return Error::UnsupportedType {
+                    column: index.clone(),
+                    type_name: type_name.to_lowercase(),
+                };
+            }
+        }
+        Error::Database(e)
+    }
+}
+
+impl Error {
+    /// process exit code for scripts
+    ///
+    /// | code | meaning                        |
+    /// |------|--------------------------------|
+    /// | 1    | anything else                  |
+    /// | 2    | database connection failed     |
+    /// | 3    | ai provider or api key problem |
+    /// | 4    | query blocked by safety check  |
+    /// | 5    | sql execution failed           |
+    /// | 6    | bad or missing configuration   |
+    /// | 7    | model asked for clarification  |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Connection(_) | Error::ConnectTimeout(_) => 2,
+            Error::Ai(_) | Error::MissingApiKey { .. } | Error::Http(_) => 3,
+            Error::Blocked(_) => 4,
+            Error::Database(_) | Error::UnsupportedType { .. } => 5,
+            Error::Config(_) => 6,
+            Error::NeedsClarification(_) => 7,
+            Error::Json(_) | Error::Server(_) => 1,
+        }
+    }
+
+    /// the database doesn't know a table the sql uses, usually a stale schema
+    pub fn is_missing_table(&self) -> bool {
+        let Error::Database(e) = self else {
+            return false;
+        };
+        let message = e.to_string().to_lowercase();
+        // sqlite, postgres and mysql in that order
+        message.contains("no such table")
+            || (message.contains("relation") && message.contains("does not exist"))
+            || (message.contains("table") && message.contains("doesn't exist"))
+    }
+
+    /// short label for grouping errors, e.g. in server metrics
+    pub fn category(&self) -> &'static str {
+        match self {
+            Error::Connection(_) | Error::ConnectTimeout(_) => "connection",
+            Error::Ai(_) | Error::MissingApiKey { .. } | Error::Http(_) => "ai",
+            Error::NeedsClarification(_) => "clarification",
+            Error::Blocked(_) => "blocked",
+            Error::Database(_) | Error::UnsupportedType { .. } => "database",
+            Error::Config(_) => "config",
+            Error::Json(_) | Error::Server(_) => "other",
+        }
+    }
+
+    /// what the server reports as `error_kind`, so clients can tell a blocked
+    /// query (rephrase) from an ai outage (retry) without reading the message
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Connection(_)
+            | Error::ConnectTimeout(_)
+            | Error::Database(
+                sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed,
+            ) => "connection",
+            Error::Database(_) | Error::UnsupportedType { .. } => "sql",
+            Error::Ai(_) | Error::MissingApiKey { .. } | Error::Http(_) => "ai",
+            Error::NeedsClarification(_) => "clarification",
+            Error::Blocked(_) => "safety",
+            Error::Config(_) | Error::Json(_) | Error::Server(_) => "other",
+        }
+    }
 }
@@ -1,11 +1,28 @@
 // nlql library - natural language to sql
 
 pub mod cli;
+mod config;
 mod core;
 mod error;
+mod metrics;
+mod output;
+mod repl;
 mod server;
 pub mod tui;
+mod util;
 
-pub use core::{Ai, Db, Provider, QueryResult, Safety};
+pub use config::{AiConfig, Config, Profile, redact_url};
+pub use core::{
+    Ai, AiOptions, CsvOptions, DEFAULT_ANTHROPIC_VERSION, DEFAULT_ROW_LIMIT, Db, DbConfig,
+    ExportFormat, GeneratedSql, Plan, PlanNode, Provider, QueryResult, ResultCache, RowStream,
+    Safety, SqlFuture, SqlGenerator, SqlStream, StatementKind, Usage, connection_url, date_context,
+    export_rows, extract_sql, parse_time_bound, plan_rows, plan_tree, quote_reserved,
+    redact_literals, schema_tables, scope_schema, split_candidates, sqlite_path, timestamp_columns,
+    with_password,
+};
 pub use error::Error;
-pub use server::Server;
+pub use metrics::Metrics;
+pub use output::{Output, OutputFormat};
+pub use repl::{Repl, ReplOptions};
+pub use server::{ServeOptions, Server};
+pub use util::{display_width, pad, pad_left, truncate};
@@ -14,9 +14,21 @@ fn main() -> miette::Result<()> {
         )
     }))?;
 
-    tokio::runtime::Builder::new_multi_thread()
+    let result = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap()
-        .block_on(async { cli::run().await })
+        .block_on(async { cli::run().await });
+
+    // distinct exit codes so scripts can tell failures apart
+    if let Err(report) = result {
+        let code = report
+            .downcast_ref::<nlql::Error>()
+            .map(|e| e.exit_code())
+            .unwrap_or(1);
+        eprintln!("{report:?}");
+        std::process::exit(code);
+    }
+
+    Ok(())
 }
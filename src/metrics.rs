@@ -0,0 +1,132 @@
+// prometheus counters for `serve --enable-metrics`, rendered by hand to stay dependency free
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// upper bounds in seconds, ai calls sit at the slow end and db calls at the fast end
+const BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// request counters and latency histograms for the http server
+#[derive(Default)]
+pub struct Metrics {
+    queries: AtomicU64,
+    blocked: AtomicU64,
+    ai_latency: Histogram,
+    db_latency: Histogram,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    // per bucket, not cumulative; the last slot is +Inf
+    counts: [AtomicU64; BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let slot = BUCKETS
+            .iter()
+            .position(|&le| secs <= le)
+            .unwrap_or(BUCKETS.len());
+        self.counts[slot].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+
+        let mut total = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            total += count.load(Ordering::Relaxed);
+            let le = BUCKETS
+                .get(i)
+                .map_or("+Inf".to_string(), |le| le.to_string());
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {total}");
+        }
+
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {total}");
+    }
+}
+
+impl Metrics {
+    /// a /query request came in
+    pub fn query(&self) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the safety check stopped a query
+    pub fn blocked(&self) {
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_ai(&self, elapsed: Duration) {
+        self.ai_latency.observe(elapsed);
+    }
+
+    pub fn observe_db(&self, elapsed: Duration) {
+        self.db_latency.observe(elapsed);
+    }
+
+    /// count a failed request under `kind` (ai, database, config, ...)
+    pub fn error(&self, kind: &'static str) {
+        if let Ok(mut errors) = self.errors.lock() {
+            *errors.entry(kind).or_default() += 1;
+        }
+    }
+
+    /// everything in the prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP nlql_queries_total queries received");
+        let _ = writeln!(out, "# TYPE nlql_queries_total counter");
+        let _ = writeln!(
+            out,
+            "nlql_queries_total {}",
+            self.queries.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP nlql_queries_blocked_total queries stopped by the safety check"
+        );
+        let _ = writeln!(out, "# TYPE nlql_queries_blocked_total counter");
+        let _ = writeln!(
+            out,
+            "nlql_queries_blocked_total {}",
+            self.blocked.load(Ordering::Relaxed)
+        );
+
+        self.ai_latency.render(
+            &mut out,
+            "nlql_ai_latency_seconds",
+            "time spent generating sql",
+        );
+        self.db_latency.render(
+            &mut out,
+            "nlql_db_latency_seconds",
+            "time spent running sql",
+        );
+
+        let _ = writeln!(out, "# HELP nlql_errors_total failed requests by kind");
+        let _ = writeln!(out, "# TYPE nlql_errors_total counter");
+        if let Ok(errors) = self.errors.lock() {
+            for (kind, count) in errors.iter() {
+                let _ = writeln!(out, "nlql_errors_total{{kind=\"{kind}\"}} {count}");
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,300 @@
+// output formatting for the cli query command
+
+use crate::core::{QueryResult, Usage};
+use crate::util::{display_width, pad, pad_left, truncate};
+
+/// how query results are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// aligned table for humans
+    #[default]
+    Pretty,
+    /// json with the sql and result
+    #[value(alias = "json")]
+    #[serde(alias = "json")]
+    Raw,
+    /// rfc 4180 csv, see --delimiter
+    Csv,
+    /// standalone html page with the sql and a table
+    Html,
+    /// fenced sql and a github-flavored table, for issues and chat
+    #[value(alias = "md")]
+    #[serde(alias = "md")]
+    Markdown,
+    /// one json object per row, keyed by column, for piping into jq
+    #[value(alias = "ndjson")]
+    #[serde(alias = "ndjson")]
+    Jsonl,
+    /// just the number of rows returned, or affected by a write
+    Count,
+}
+
+pub struct Output;
+
+impl Output {
+    /// sql followed by an aligned table; `col_width` overrides the per-column cap
+    pub fn pretty(sql: &str, result: &QueryResult, col_width: Option<usize>) -> String {
+        let mut output = format!("{sql}\n\n");
+
+        if let Some(status) = result.status(sql) {
+            output.push_str(&format!("{status}\n"));
+            return output;
+        }
+
+        let cap = col_width.unwrap_or_else(|| default_col_width(result.columns.len()));
+
+        // size each column to its widest value, up to the cap
+        let mut widths: Vec<usize> = result.columns.iter().map(|c| display_width(c)).collect();
+        for row in &result.rows {
+            for (i, val) in row.iter().enumerate() {
+                if i < widths.len() {
+                    widths[i] = widths[i].max(display_width(&format_value(val)));
+                }
+            }
+        }
+        for w in &mut widths {
+            *w = (*w).min(cap);
+        }
+
+        // numbers line up on the right, everything else (and all-null columns) on the left
+        let numeric = result.numeric_columns();
+        let align = |s: &str, i: usize, w: usize| {
+            let s = truncate(s, w, "...");
+            if numeric.get(i).copied().unwrap_or(false) {
+                pad_left(&s, w)
+            } else {
+                pad(&s, w)
+            }
+        };
+
+        // header
+        let header: Vec<String> = result
+            .columns
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (c, &w))| align(c, i, w))
+            .collect();
+        output.push_str(header.join("  ").trim_end());
+        output.push('\n');
+
+        // separator
+        let sep: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+        output.push_str(&sep.join("  "));
+        output.push('\n');
+
+        // rows
+        for row in &result.rows {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (v, &w))| align(&format_value(v), i, w))
+                .collect();
+            output.push_str(cells.join("  ").trim_end());
+            output.push('\n');
+        }
+
+        if result.truncated {
+            output.push_str(&format!("\n({} rows, truncated)\n", result.row_count));
+        } else {
+            output.push_str(&format!("\n({} rows)\n", result.row_count));
+        }
+        output
+    }
+
+    /// the sql and full result as pretty-printed json
+    pub fn raw(sql: &str, result: &QueryResult) -> String {
+        Self::raw_with_usage(sql, result, None)
+    }
+
+    /// `raw` with the tokens spent generating the sql, when the provider said
+    pub fn raw_with_usage(sql: &str, result: &QueryResult, usage: Option<Usage>) -> String {
+        let mut value = serde_json::json!({
+            "sql": sql,
+            "result": result,
+        });
+        if let Some(usage) = usage {
+            value["usage"] = serde_json::json!(usage);
+        }
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    }
+
+    /// one `{"column": value}` line per row, columns in result order
+    pub fn jsonl(result: &QueryResult) -> String {
+        let mut out = String::new();
+        for row in &result.rows {
+            // written by hand, a json map would sort the keys
+            let fields: Vec<String> = result
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| {
+                    format!("{}:{value}", serde_json::Value::from(column.as_str()))
+                })
+                .collect();
+            out.push_str(&format!("{{{}}}\n", fields.join(",")));
+        }
+        out
+    }
+
+    /// the prompt as `-- ` comment lines, for putting above pretty output (--show-prompt)
+    pub fn prompt_comment(prompt: &str) -> String {
+        prompt.lines().map(|line| format!("-- {line}\n")).collect()
+    }
+
+    /// a self-contained html page: the sql in a `<pre>` and the result as a table
+    pub fn html(sql: &str, result: &QueryResult) -> String {
+        Self::html_with_prompt(None, sql, result)
+    }
+
+    /// `html` with the prompt that produced the sql above it (--show-prompt)
+    pub fn html_with_prompt(prompt: Option<&str>, sql: &str, result: &QueryResult) -> String {
+        let mut out = String::from(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>nlql result</title>
+<style>
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+pre { background: #f5f5f5; padding: 1em; border-radius: 4px; white-space: pre-wrap; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: 4px 10px; text-align: left; }
+th { background: #f0f0f0; }
+tr:nth-child(even) td { background: #fafafa; }
+blockquote { margin: 0 0 1em; padding-left: 1em; border-left: 3px solid #ddd; color: #555; white-space: pre-wrap; }
+td.null { color: #999; font-style: italic; }
+td.num { text-align: right; }
+</style>
+</head>
+<body>
+"#,
+        );
+
+        if let Some(prompt) = prompt {
+            out.push_str(&format!(
+                "<blockquote>{}</blockquote>\n",
+                escape_html(prompt)
+            ));
+        }
+        out.push_str(&format!("<pre>{}</pre>\n", escape_html(sql)));
+
+        if let Some(status) = result.status(sql).filter(|_| result.columns.is_empty()) {
+            out.push_str(&format!("<p>{status}</p>\n"));
+        } else {
+            out.push_str("<table>\n<thead><tr>");
+            for column in &result.columns {
+                out.push_str(&format!("<th>{}</th>", escape_html(column)));
+            }
+            out.push_str("</tr></thead>\n<tbody>\n");
+
+            for row in &result.rows {
+                out.push_str("<tr>");
+                for val in row {
+                    let class = match val {
+                        serde_json::Value::Null => " class=\"null\"",
+                        serde_json::Value::Number(_) => " class=\"num\"",
+                        _ => "",
+                    };
+                    out.push_str(&format!(
+                        "<td{class}>{}</td>",
+                        escape_html(&format_value(val))
+                    ));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</tbody>\n</table>\n");
+        }
+
+        let truncated = if result.truncated { ", truncated" } else { "" };
+        out.push_str(&format!(
+            "<p>({} rows{truncated})</p>\n</body>\n</html>\n",
+            result.row_count
+        ));
+        out
+    }
+
+    /// the sql in a ```sql fence followed by a markdown table
+    pub fn markdown(sql: &str, result: &QueryResult) -> String {
+        Self::markdown_with_prompt(None, sql, result)
+    }
+
+    /// `markdown` with the prompt above it as a quote (--show-prompt)
+    pub fn markdown_with_prompt(prompt: Option<&str>, sql: &str, result: &QueryResult) -> String {
+        let mut out = String::new();
+        if let Some(prompt) = prompt {
+            for line in prompt.lines() {
+                out.push_str(&format!("> {line}\n"));
+            }
+            out.push('\n');
+        }
+        out.push_str(&format!("```sql\n{}\n```\n\n", sql.trim_end()));
+
+        if let Some(status) = result.status(sql).filter(|_| result.columns.is_empty()) {
+            out.push_str(&format!("{status}\n"));
+            return out;
+        }
+        if result.rows.is_empty() {
+            out.push_str("_no rows_\n");
+            return out;
+        }
+
+        let header: Vec<String> = result.columns.iter().map(|c| escape_cell(c)).collect();
+        out.push_str(&format!("| {} |\n", header.join(" | ")));
+        out.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+        for row in &result.rows {
+            let cells: Vec<String> = row.iter().map(|v| escape_cell(&format_value(v))).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+
+        if result.truncated {
+            out.push_str(&format!("\n({} rows, truncated)\n", result.row_count));
+        }
+        out
+    }
+}
+
+// a cell has to stay on one line and can't end it early with a `|`
+fn escape_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace("\r\n", " ")
+        .replace(['\n', '\r'], " ")
+}
+
+// enough for text and attribute values
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// split the terminal width evenly between columns
+fn default_col_width(num_cols: usize) -> usize {
+    let term_width = crossterm::terminal::size()
+        .map(|(w, _)| w as usize)
+        .unwrap_or(80);
+    let spacing = 2 * num_cols.saturating_sub(1);
+
+    (term_width.saturating_sub(spacing) / num_cols.max(1)).max(4)
+}
+
+fn format_value(val: &serde_json::Value) -> String {
+    match val {
+        serde_json::Value::Null => "null".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        _ => val.to_string(),
+    }
+}
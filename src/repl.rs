@@ -0,0 +1,155 @@
+// `nlql repl`: prompts in, tables out, one connection and one schema for the session
+
+use crate::core::{Db, Safety, SqlGenerator};
+use crate::output::Output;
+use std::io::{BufRead, Write};
+
+const HELP: &str = "\\schema  print the schema the model sees
+\\sql     print the last sql
+\\dry     toggle dry run, sql is only printed
+\\history print this session's prompts
+\\q       quit
+";
+
+/// flags that shape a repl session
+#[derive(Debug, Clone, Default)]
+pub struct ReplOptions {
+    /// start with dry run on, `\dry` toggles it
+    pub dry_run: bool,
+    /// --read-only, anything but SELECT and EXPLAIN is refused
+    pub read_only: bool,
+    /// --multi-statement, run several statements as one transaction
+    pub multi_statement: bool,
+    /// --limit for generated SELECTs without one
+    pub limit: Option<u64>,
+    /// max chars per column, like `query --col-width`
+    pub col_width: Option<usize>,
+    /// print `nlql> ` before each line, for a terminal
+    pub show_prompt: bool,
+}
+
+pub struct Repl {
+    db: Db,
+    schema: String,
+    generator: Box<dyn SqlGenerator>,
+    options: ReplOptions,
+    /// prompts of this session, oldest first
+    pub history: Vec<String>,
+    /// the sql generated for the last prompt
+    pub last_sql: Option<String>,
+}
+
+impl Repl {
+    pub fn new(
+        db: Db,
+        schema: String,
+        generator: Box<dyn SqlGenerator>,
+        options: ReplOptions,
+    ) -> Self {
+        Self {
+            db,
+            schema,
+            generator,
+            options,
+            history: Vec::new(),
+            last_sql: None,
+        }
+    }
+
+    /// read lines from `input` until `\q` or the end of it
+    ///
+    /// a failed prompt is reported to `out` and the session goes on; only a
+    /// write to `out` failing ends it early
+    pub async fn run(&mut self, input: impl BufRead, out: &mut impl Write) -> std::io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if self.options.show_prompt {
+                eprint!("nlql> ");
+                std::io::stderr().flush().ok();
+            }
+            let Some(line) = lines.next().transpose()? else {
+                return Ok(());
+            };
+            let line = line.trim();
+            match line {
+                "" => {}
+                "\\q" | "\\quit" => return Ok(()),
+                "\\?" | "\\help" => write!(out, "{HELP}")?,
+                "\\schema" => writeln!(out, "{}", self.schema)?,
+                "\\sql" => match &self.last_sql {
+                    Some(sql) => writeln!(out, "{sql}")?,
+                    None => writeln!(out, "no sql yet")?,
+                },
+                "\\dry" => {
+                    self.options.dry_run = !self.options.dry_run;
+                    let state = if self.options.dry_run { "on" } else { "off" };
+                    writeln!(out, "dry run {state}")?;
+                }
+                "\\history" => {
+                    for (i, prompt) in self.history.iter().enumerate() {
+                        writeln!(out, "{:>3}  {prompt}", i + 1)?;
+                    }
+                }
+                command if command.starts_with('\\') => {
+                    writeln!(out, "unknown command {command}, \\? lists them")?
+                }
+                prompt => {
+                    self.history.push(prompt.to_string());
+                    self.prompt(prompt, &mut lines, out).await?;
+                }
+            }
+        }
+    }
+
+    // one prompt: generate, check, then run or just print it
+    async fn prompt(
+        &mut self,
+        prompt: &str,
+        lines: &mut impl Iterator<Item = std::io::Result<String>>,
+        out: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut sql = match self.generator.generate_sql(prompt, &self.schema).await {
+            Ok(sql) => sql,
+            Err(e) => return writeln!(out, "error: {e}"),
+        };
+        if let Some(limited) = self.options.limit.and_then(|n| Safety::with_limit(&sql, n)) {
+            sql = limited;
+        }
+        self.last_sql = Some(sql.clone());
+
+        if self.options.dry_run {
+            return writeln!(out, "{sql}");
+        }
+        if self.options.read_only
+            && let Some(reason) = Safety::read_only_refusal(&sql)
+        {
+            return writeln!(out, "{sql}\nblocked: {reason}");
+        }
+
+        // the answer comes from the same input as the prompts
+        let safety = Safety::check(&sql);
+        if safety.is_dangerous {
+            write!(out, "{sql}\n{}. run anyway? [y/N] ", safety.reason)?;
+            out.flush()?;
+            let answer = lines.next().transpose()?.unwrap_or_default();
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                return writeln!(out, "blocked: {}", safety.reason);
+            }
+        } else if let Some(warning) = &safety.warning {
+            writeln!(out, "warning: {warning}")?;
+        }
+
+        match self
+            .db
+            .execute_statements(&sql, self.options.multi_statement)
+            .await
+        {
+            Ok(result) => write!(
+                out,
+                "{}",
+                Output::pretty(&sql, &result, self.options.col_width)
+            ),
+            Err(e) => writeln!(out, "{sql}\nerror: {e}"),
+        }
+    }
+}
@@ -2,31 +2,119 @@
 
 use axum::{
     Json, Router,
-    extract::State,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
     routing::{get, post},
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tower_http::cors::CorsLayer;
 
 use crate::core::QueryResult;
-use crate::{Ai, Db, Error, Provider, Safety};
+use crate::{
+    Ai, AiOptions, Config, Db, DbConfig, Error, Metrics, Plan, Provider, Safety, SqlGenerator,
+};
+
+/// name used for the plain `--db` url
+pub const DEFAULT_DB: &str = "default";
+
+/// request bodies larger than this get a 413 unless --max-body-size says otherwise
+pub const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// /query calls kept for GET /history unless --history-size says otherwise
+pub const DEFAULT_QUERY_HISTORY_SIZE: usize = 100;
 
 struct AppState {
+    targets: HashMap<String, Target>,
+    // always counted, only exposed with --enable-metrics
+    metrics: Metrics,
+    // answers requests that don't pick another provider: the fixed generator,
+    // or the --provider client built once at boot (why it couldn't be, otherwise)
+    generator: Result<Arc<dyn SqlGenerator>, String>,
+    // a fixed generator answers every request, whatever provider it names
+    fixed: bool,
+    // --cost-threshold, checked with EXPLAIN before running
+    cost_threshold: Option<u64>,
+    // --read-only, checked before anything else about the sql
+    read_only: bool,
+    // --multi-statement, scripts run in a transaction instead of being refused
+    multi_statement: bool,
+    // --limit, added to generated SELECTs without one
+    limit: Option<u64>,
+    // --provider, for requests that don't name one
+    provider: Provider,
+    ai_options: AiOptions,
+    // `[providers.<name>] api_key` for the providers requests name
+    config: Config,
+    // the last --history-size /query calls, oldest first
+    history: Mutex<VecDeque<HistoryEntry>>,
+    history_size: usize,
+}
+
+// one connected database and its cached schema
+struct Target {
     db: Db,
     schema: String,
 }
 
+impl AppState {
+    // requests without a db name go to the default, or the only one there is
+    fn target(&self, name: Option<&str>) -> Option<&Target> {
+        match name {
+            Some(name) => self.targets.get(name),
+            None if self.targets.len() == 1 => self.targets.values().next(),
+            None => self.targets.get(DEFAULT_DB),
+        }
+    }
+
+    // keep the entry, dropping the oldest once the buffer is full
+    fn record(&self, entry: HistoryEntry) {
+        if self.history_size == 0 {
+            return;
+        }
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        if history.len() >= self.history_size {
+            history.pop_front();
+        }
+        history.push_back(entry);
+    }
+
+    // the boot client, or an `Ai` for a request that names another provider
+    fn generator(&self, provider: Option<Provider>) -> Result<Arc<dyn SqlGenerator>, Error> {
+        match provider {
+            Some(provider) if !self.fixed && provider != self.provider => {
+                // --model names a model of --provider, another provider keeps its own default
+                let options = AiOptions {
+                    model: None,
+                    ..self.ai_options.clone()
+                };
+                let api_key = self.config.api_key(provider);
+                Ok(Arc::new(Ai::new(provider, api_key)?.with_options(options)))
+            }
+            _ => self.generator.clone().map_err(Error::Ai),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct QueryRequest {
     prompt: String,
     #[serde(default)]
+    db: Option<String>,
+    #[serde(default)]
     provider: Option<String>,
     #[serde(default)]
     dry_run: bool,
     #[serde(default)]
     run_dangerous: bool,
+    /// page through the result: the sql is wrapped in a LIMIT/OFFSET query
+    #[serde(default)]
+    limit: Option<u64>,
+    #[serde(default)]
+    offset: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -38,6 +126,48 @@ struct QueryResponse {
     warning: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    /// `safety`, `ai`, `sql`, `connection`, ... whenever `error` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<&'static str>,
+}
+
+/// one /query call as GET /history returns it
+#[derive(Clone, Serialize)]
+struct HistoryEntry {
+    prompt: String,
+    /// empty when the call failed before there was any
+    sql: String,
+    /// `None` for a dry run or a failure
+    row_count: Option<usize>,
+    duration_ms: u64,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExplainRequest {
+    /// sql to explain as is
+    #[serde(default)]
+    sql: Option<String>,
+    /// or a prompt to generate it from
+    #[serde(default)]
+    prompt: Option<String>,
+    #[serde(default)]
+    db: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExplainResponse {
+    sql: String,
+    dialect: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<Plan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// `safety`, `ai`, `sql`, `connection`, ... whenever `error` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<&'static str>,
 }
 
 #[derive(Serialize)]
@@ -45,21 +175,73 @@ struct HealthResponse {
     status: &'static str,
 }
 
-pub struct Server;
+#[derive(Deserialize)]
+struct SchemaParams {
+    db: Option<String>,
+}
 
-impl Server {
-    pub async fn run(db_url: &str, host: &str, port: u16) -> Result<(), Error> {
-        let db = Db::connect(db_url).await?;
-        let schema = db.schema().await?;
+/// `serve` flags that shape the routes
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// add GET /metrics
+    pub enable_metrics: bool,
+    /// bodies over this many bytes get a 413
+    pub max_body_size: usize,
+    /// /query calls GET /history keeps, 0 keeps none
+    pub history_size: usize,
+    /// refuse sql whose plan estimates more rows than this
+    pub cost_threshold: Option<u64>,
+    /// refuse anything but SELECT and EXPLAIN, whatever the request says
+    pub read_only: bool,
+    /// run sql with several statements as one transaction
+    pub multi_statement: bool,
+    /// `LIMIT` added to generated SELECTs that have none, `None` runs them as written
+    pub limit: Option<u64>,
+    /// postgres schema or mysql database to introspect, for every served database
+    pub db_schema: Option<String>,
+    /// pool size and connect timeout, for every served database
+    pub db_config: DbConfig,
+    /// provider for requests that don't pick one
+    pub provider: Provider,
+    /// --api-key or the config's key for `provider`, its env var otherwise
+    pub api_key: Option<String>,
+    /// --model and the other prompt settings, for every request
+    pub ai_options: AiOptions,
+    /// the loaded config file, for the api key of a provider a request names
+    pub config: Config,
+}
 
-        let state = Arc::new(AppState { db, schema });
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            enable_metrics: false,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            history_size: DEFAULT_QUERY_HISTORY_SIZE,
+            cost_threshold: None,
+            read_only: false,
+            multi_statement: false,
+            limit: None,
+            db_schema: None,
+            db_config: DbConfig::default(),
+            provider: Provider::default(),
+            api_key: None,
+            ai_options: AiOptions::default(),
+            config: Config::default(),
+        }
+    }
+}
 
-        let app = Router::new()
-            .route("/health", get(health))
-            .route("/query", post(query))
-            .route("/schema", get(get_schema))
-            .layer(CorsLayer::permissive())
-            .with_state(state);
+pub struct Server;
+
+impl Server {
+    /// serve every `(name, url)` database; requests pick one with a `db` field
+    pub async fn run(
+        dbs: &[(String, String)],
+        host: &str,
+        port: u16,
+        options: &ServeOptions,
+    ) -> Result<(), Error> {
+        let app = Self::router(dbs, None, options).await?;
 
         let addr = format!("{host}:{port}");
         println!("server running at http://{addr}");
@@ -74,31 +256,189 @@ impl Server {
 
         Ok(())
     }
+
+    /// connect every database and build the routes without binding anything
+    ///
+    /// a `generator` answers every /query instead of the provider in the request
+    ///
+    /// routes: `GET /health`, `POST /query` (prompt -> sql and result),
+    /// `POST /explain` (sql or prompt -> `{sql, dialect, plan}`), `GET /schema`,
+    /// `GET /history` (recent /query calls), and `GET /metrics` with `enable_metrics`
+    pub async fn router(
+        dbs: &[(String, String)],
+        generator: Option<Arc<dyn SqlGenerator>>,
+        options: &ServeOptions,
+    ) -> Result<Router, Error> {
+        let mut targets = HashMap::new();
+        for (name, url) in dbs {
+            if targets.contains_key(name) {
+                return Err(Error::Config(format!("database {name} given twice")));
+            }
+            let mut db = Db::connect_with(url, &options.db_config).await?;
+            if let Some(name) = &options.db_schema {
+                db = db.with_db_schema(name)?;
+            }
+            let schema = db.schema().await?;
+            println!("connected {name} ({})", db.dialect_name());
+            targets.insert(name.clone(), Target { db, schema });
+        }
+
+        // built once, a missing key then fails each /query with a 500 instead of the boot
+        let fixed = generator.is_some();
+        let generator =
+            match generator {
+                Some(generator) => Ok(generator),
+                None => match Ai::new(options.provider, options.api_key.clone()) {
+                    Ok(ai) => Ok(Arc::new(ai.with_options(options.ai_options.clone()))
+                        as Arc<dyn SqlGenerator>),
+                    Err(e) => {
+                        let reason = match &e {
+                            Error::MissingApiKey { env_var, .. } => {
+                                format!("{e}, set {env_var} and restart the server")
+                            }
+                            _ => e.to_string(),
+                        };
+                        eprintln!("warning: {reason}, /query and /explain prompts will fail");
+                        Err(format!(
+                            "the server's {} client isn't set up: {reason}",
+                            options.provider
+                        ))
+                    }
+                },
+            };
+
+        let state = Arc::new(AppState {
+            targets,
+            metrics: Metrics::default(),
+            generator,
+            fixed,
+            cost_threshold: options.cost_threshold,
+            read_only: options.read_only,
+            multi_statement: options.multi_statement,
+            limit: options.limit,
+            provider: options.provider,
+            ai_options: options.ai_options.clone(),
+            config: options.config.clone(),
+            history: Mutex::new(VecDeque::new()),
+            history_size: options.history_size,
+        });
+
+        let mut app = Router::new()
+            .route("/health", get(health))
+            .route("/query", post(query))
+            .route("/explain", post(explain))
+            .route("/schema", get(get_schema))
+            .route("/history", get(history));
+        if options.enable_metrics {
+            app = app.route("/metrics", get(metrics));
+        }
+        Ok(app
+            .layer(DefaultBodyLimit::max(options.max_body_size))
+            .layer(CorsLayer::permissive())
+            .with_state(state))
+    }
 }
 
 async fn health() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
 
-async fn get_schema(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
-    Json(serde_json::json!({ "schema": state.schema }))
+async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+async fn history(State(state): State<Arc<AppState>>) -> Json<Vec<HistoryEntry>> {
+    let history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+    Json(history.iter().cloned().collect())
+}
+
+async fn get_schema(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SchemaParams>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.target(params.db.as_deref()) {
+        Some(target) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "schema": target.schema })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": unknown_db(params.db.as_deref()) })),
+        ),
+    }
+}
+
+fn unknown_db(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("unknown database: {name}"),
+        None => "several databases are served, pick one with \"db\"".to_string(),
+    }
+}
+
+// the provider a request names; a typo is refused rather than answered by another
+fn requested_provider(name: Option<&str>) -> Result<Option<Provider>, String> {
+    name.map(str::parse).transpose()
 }
 
 async fn query(
     State(state): State<Arc<AppState>>,
     Json(req): Json<QueryRequest>,
 ) -> (StatusCode, Json<QueryResponse>) {
-    // parse provider (default to claude)
-    let provider = req
-        .provider
-        .as_deref()
-        .map(|s| s.parse().unwrap_or(Provider::Claude))
-        .unwrap_or(Provider::Claude);
-
-    // get ai ready
-    let ai = match Ai::new(provider, None) {
-        Ok(c) => c,
+    let started = Instant::now();
+    let prompt = req.prompt.clone();
+    let (status, Json(response)) = run_query(&state, req).await;
+    state.record(HistoryEntry {
+        prompt,
+        sql: response.sql.clone(),
+        row_count: response.result.as_ref().map(|r| r.row_count),
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: response.error.clone(),
+    });
+    (status, Json(response))
+}
+
+async fn run_query(state: &AppState, req: QueryRequest) -> (StatusCode, Json<QueryResponse>) {
+    state.metrics.query();
+
+    let Some(target) = state.target(req.db.as_deref()) else {
+        state.metrics.error("unknown_db");
+        return (
+            StatusCode::NOT_FOUND,
+            Json(QueryResponse {
+                sql: String::new(),
+                result: None,
+                warning: None,
+                error: Some(unknown_db(req.db.as_deref())),
+                error_kind: Some("request"),
+            }),
+        );
+    };
+
+    let provider = match requested_provider(req.provider.as_deref()) {
+        Ok(provider) => provider,
+        Err(error) => {
+            state.metrics.error("request");
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(QueryResponse {
+                    sql: String::new(),
+                    result: None,
+                    warning: None,
+                    error: Some(error),
+                    error_kind: Some("request"),
+                }),
+            );
+        }
+    };
+
+    // get ai ready, unless one generator answers everything
+    let ai = match state.generator(provider) {
+        Ok(ai) => ai,
         Err(e) => {
+            state.metrics.error(e.category());
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(QueryResponse {
@@ -106,15 +446,43 @@ async fn query(
                     result: None,
                     warning: None,
                     error: Some(e.to_string()),
+                    error_kind: Some(e.kind()),
                 }),
             );
         }
     };
 
     // generate the sql
-    let sql = match ai.generate_sql(&req.prompt, &state.schema).await {
-        Ok(s) => s,
+    let started = Instant::now();
+    let generated = ai.generate_sql(&req.prompt, &target.schema).await;
+    state.metrics.observe_ai(started.elapsed());
+    let paged = req.limit.is_some() || req.offset.is_some();
+    let sql = match generated {
+        // the page is the limit, --limit would cut what's being paged through
+        Ok(s) if paged => match Safety::paginate(&s, req.limit, req.offset) {
+            Some(sql) => sql,
+            None => {
+                state.metrics.error("request");
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(QueryResponse {
+                        sql: s,
+                        result: None,
+                        warning: None,
+                        error: Some(
+                            "limit and offset only page through a single SELECT".to_string(),
+                        ),
+                        error_kind: Some("request"),
+                    }),
+                );
+            }
+        },
+        Ok(s) => match state.limit {
+            Some(limit) => Safety::with_limit(&s, limit).unwrap_or(s),
+            None => s,
+        },
         Err(e) => {
+            state.metrics.error(e.category());
             return (
                 StatusCode::BAD_REQUEST,
                 Json(QueryResponse {
@@ -122,14 +490,33 @@ async fn query(
                     result: None,
                     warning: None,
                     error: Some(e.to_string()),
+                    error_kind: Some(e.kind()),
                 }),
             );
         }
     };
 
+    // --read-only: not even run_dangerous gets a write through
+    if state.read_only
+        && let Some(reason) = Safety::read_only_refusal(&sql)
+    {
+        state.metrics.blocked();
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(QueryResponse {
+                sql,
+                result: None,
+                warning: None,
+                error: Some(reason),
+                error_kind: Some("read_only"),
+            }),
+        );
+    }
+
     // check if it's safe
     let safety = Safety::check(&sql);
     if safety.is_dangerous && !req.run_dangerous {
+        state.metrics.blocked();
         return (
             StatusCode::BAD_REQUEST,
             Json(QueryResponse {
@@ -137,6 +524,27 @@ async fn query(
                 result: None,
                 warning: None,
                 error: Some(format!("blocked: {}", safety.reason)),
+                error_kind: Some("safety"),
+            }),
+        );
+    }
+
+    // too expensive by the planner's estimate
+    if let Some(threshold) = state.cost_threshold
+        && let Ok(Some(rows)) = target.db.estimated_rows(&sql).await
+        && rows > threshold
+    {
+        state.metrics.blocked();
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(QueryResponse {
+                sql,
+                result: None,
+                warning: None,
+                error: Some(format!(
+                    "blocked: the plan estimates {rows} rows, over the cost threshold of {threshold}"
+                )),
+                error_kind: Some("safety"),
             }),
         );
     }
@@ -150,12 +558,19 @@ async fn query(
                 result: None,
                 warning: safety.warning,
                 error: None,
+                error_kind: None,
             }),
         );
     }
 
     // run it
-    match state.db.execute(&sql).await {
+    let started = Instant::now();
+    let executed = target
+        .db
+        .execute_statements(&sql, state.multi_statement)
+        .await;
+    state.metrics.observe_db(started.elapsed());
+    match executed {
         Ok(result) => (
             StatusCode::OK,
             Json(QueryResponse {
@@ -163,16 +578,140 @@ async fn query(
                 result: Some(result),
                 warning: safety.warning,
                 error: None,
+                error_kind: None,
             }),
         ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(QueryResponse {
+        Err(e) => {
+            state.metrics.error(e.category());
+            (
+                StatusCode::BAD_REQUEST,
+                Json(QueryResponse {
+                    sql,
+                    result: None,
+                    warning: safety.warning,
+                    error: Some(e.to_string()),
+                    error_kind: Some(e.kind()),
+                }),
+            )
+        }
+    }
+}
+
+async fn explain(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ExplainRequest>,
+) -> (StatusCode, Json<ExplainResponse>) {
+    let failed = |status, sql: String, dialect, kind, error: String| {
+        (
+            status,
+            Json(ExplainResponse {
                 sql,
-                result: None,
-                warning: safety.warning,
-                error: Some(e.to_string()),
+                dialect,
+                plan: None,
+                error: Some(error),
+                error_kind: Some(kind),
+            }),
+        )
+    };
+
+    let Some(target) = state.target(req.db.as_deref()) else {
+        let error = unknown_db(req.db.as_deref());
+        return failed(StatusCode::NOT_FOUND, String::new(), "", "request", error);
+    };
+    let dialect = target.db.dialect_name();
+    let provider = match requested_provider(req.provider.as_deref()) {
+        Ok(provider) => provider,
+        Err(error) => {
+            state.metrics.error("request");
+            return failed(
+                StatusCode::BAD_REQUEST,
+                String::new(),
+                dialect,
+                "request",
+                error,
+            );
+        }
+    };
+
+    // sql as given, otherwise generated from the prompt
+    let sql = match (req.sql, req.prompt) {
+        (Some(sql), _) => sql,
+        (None, Some(prompt)) => {
+            let ai = match state.generator(provider) {
+                Ok(ai) => ai,
+                Err(e) => {
+                    state.metrics.error(e.category());
+                    return failed(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        String::new(),
+                        dialect,
+                        e.kind(),
+                        e.to_string(),
+                    );
+                }
+            };
+            let generated = ai.generate_sql(&prompt, &target.schema).await;
+            match generated {
+                Ok(sql) => sql,
+                Err(e) => {
+                    state.metrics.error(e.category());
+                    return failed(
+                        StatusCode::BAD_REQUEST,
+                        String::new(),
+                        dialect,
+                        e.kind(),
+                        e.to_string(),
+                    );
+                }
+            }
+        }
+        (None, None) => {
+            let error = "give \"sql\" or a \"prompt\" to explain".to_string();
+            return failed(
+                StatusCode::BAD_REQUEST,
+                String::new(),
+                dialect,
+                "request",
+                error,
+            );
+        }
+    };
+
+    // --read-only as on /query, EXPLAIN runs what follows it on some databases
+    if state.read_only
+        && let Some(reason) = Safety::read_only_refusal(&sql)
+    {
+        state.metrics.blocked();
+        return failed(StatusCode::BAD_REQUEST, sql, dialect, "read_only", reason);
+    }
+
+    // one statement that only reads, anything else would run for real
+    if let Some(reason) = Safety::explain_refusal(&sql) {
+        state.metrics.blocked();
+        let error = format!("blocked: {reason}");
+        return failed(StatusCode::BAD_REQUEST, sql, dialect, "safety", error);
+    }
+
+    match target.db.explain(&sql).await {
+        Ok(plan) => (
+            StatusCode::OK,
+            Json(ExplainResponse {
+                sql,
+                dialect,
+                plan: Some(plan),
+                error: None,
+                error_kind: None,
             }),
         ),
+        Err(e) => {
+            state.metrics.error(e.category());
+            failed(
+                StatusCode::BAD_REQUEST,
+                sql,
+                dialect,
+                e.kind(),
+                e.to_string(),
+            )
+        }
     }
 }
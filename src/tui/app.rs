@@ -1,8 +1,14 @@
 // app state for the tui
 
-use crate::core::QueryResult;
-use crate::tui::theme::{Theme, ThemeKind, detect_theme};
 use crate::Provider;
+use crate::core::Safety;
+use crate::core::{
+    CsvOptions, QueryResult, StatementKind, Usage, schema_tables, scope_schema, sqlite_path,
+};
+use crate::tui::history::DEFAULT_HISTORY_SIZE;
+use crate::tui::keys::KeyMap;
+use crate::tui::theme::{Theme, ThemeKind, detect_theme};
+use std::path::PathBuf;
 use std::time::Instant;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,6 +35,64 @@ pub enum Popup {
     SetupDbDetails,
     SetupProvider,
     SetupApiKey,
+    Command,
+    Describe,
+    Candidates,
+    SetupProfile,
+    Help,
+    Tables,
+    /// drawn in the sql panel, not over it
+    Compare,
+}
+
+/// providers offered by the setup popup, in order, with their labels
+pub const SETUP_PROVIDERS: [(Provider, &str); 4] = [
+    (Provider::Claude, "Claude (Anthropic)"),
+    (Provider::OpenAI, "OpenAI (GPT-4)"),
+    (Provider::Gemini, "Gemini (Google)"),
+    (Provider::Ollama, "Ollama (local, no key)"),
+];
+
+/// prompt length the counter warns about unless --max-prompt-chars says otherwise
+pub const DEFAULT_MAX_PROMPT_CHARS: usize = 4000;
+
+/// how the prompt's length compares to `max_prompt_chars`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptSize {
+    Fine,
+    /// past 80% of the limit
+    Near,
+    Over,
+}
+
+/// one sql run, kept for the session report
+#[derive(Debug, Clone)]
+pub struct SessionEntry {
+    /// `None` when the sql didn't come from a prompt
+    pub prompt: Option<String>,
+    pub sql: String,
+    /// the first `SESSION_ROWS` rows (`row_count` is the full count), or the error
+    pub outcome: Result<QueryResult, String>,
+}
+
+/// rows of each result kept for the session report
+pub const SESSION_ROWS: usize = 20;
+
+/// sql waiting on the confirm popup, tied to the connection it was written for
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSql {
+    pub sql: String,
+    pub db_url: String,
+}
+
+/// a saved connection offered on the setup screen
+#[derive(Debug, Clone)]
+pub struct ProfileEntry {
+    pub name: String,
+    /// the url as written in the config, safe to show
+    pub url: String,
+    /// the url to connect with, or why it can't be used
+    pub connect: Result<String, String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -59,6 +123,48 @@ impl DbType {
     }
 }
 
+/// minimum level shown in the logs panel, cycled with `L`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFilter {
+    #[default]
+    All,
+    Warnings,
+    Errors,
+}
+
+impl LogFilter {
+    pub fn next(self) -> Self {
+        match self {
+            LogFilter::All => LogFilter::Warnings,
+            LogFilter::Warnings => LogFilter::Errors,
+            LogFilter::Errors => LogFilter::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogFilter::All => "all",
+            LogFilter::Warnings => "warnings+",
+            LogFilter::Errors => "errors",
+        }
+    }
+
+    pub fn allows(self, level: LogLevel) -> bool {
+        match self {
+            LogFilter::All => true,
+            LogFilter::Warnings => matches!(level, LogLevel::Warn | LogLevel::Error),
+            LogFilter::Errors => level == LogLevel::Error,
+        }
+    }
+}
+
+/// `:save <table> [file.db]` from the command line
+#[derive(Debug, Clone)]
+pub struct SaveRequest {
+    pub table: String,
+    pub out: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Ok,
@@ -76,35 +182,20 @@ pub enum RiskLevel {
 
 impl RiskLevel {
     pub fn from_sql(sql: &str) -> Self {
-        let upper = sql.to_uppercase();
-        let trimmed = upper.trim();
-
-        if trimmed.starts_with("DROP")
-            || trimmed.starts_with("TRUNCATE")
-            || trimmed.starts_with("ALTER")
-        {
-            return RiskLevel::Danger;
-        }
-
-        if trimmed.starts_with("DELETE") {
-            if upper.contains("WHERE") {
-                return RiskLevel::Moderate;
+        match StatementKind::from_sql(sql) {
+            StatementKind::Drop | StatementKind::Truncate | StatementKind::Alter => {
+                RiskLevel::Danger
             }
-            return RiskLevel::Danger;
-        }
-
-        if trimmed.starts_with("UPDATE") {
-            if upper.contains("WHERE") {
-                return RiskLevel::Moderate;
+            StatementKind::Delete | StatementKind::Update => {
+                if sql.to_uppercase().contains("WHERE") {
+                    RiskLevel::Moderate
+                } else {
+                    RiskLevel::Danger
+                }
             }
-            return RiskLevel::Danger;
-        }
-
-        if trimmed.starts_with("INSERT") {
-            return RiskLevel::Moderate;
+            StatementKind::Insert => RiskLevel::Moderate,
+            _ => RiskLevel::Safe,
         }
-
-        RiskLevel::Safe
     }
 
     pub fn label(&self) -> &'static str {
@@ -116,28 +207,25 @@ impl RiskLevel {
     }
 
     pub fn sql_type(&self, sql: &str) -> &'static str {
-        let upper = sql.to_uppercase();
-        let trimmed = upper.trim();
-
-        if trimmed.starts_with("SELECT") {
-            "SELECT"
-        } else if trimmed.starts_with("INSERT") {
-            "INSERT"
-        } else if trimmed.starts_with("UPDATE") {
-            "UPDATE"
-        } else if trimmed.starts_with("DELETE") {
-            "DELETE"
-        } else if trimmed.starts_with("DROP") {
-            "DROP"
-        } else if trimmed.starts_with("TRUNCATE") {
-            "TRUNCATE"
-        } else if trimmed.starts_with("ALTER") {
-            "ALTER"
-        } else if trimmed.starts_with("CREATE") {
-            "CREATE"
-        } else {
-            "QUERY"
-        }
+        StatementKind::from_sql(sql).label()
+    }
+}
+
+/// where the EXPLAIN plan for the current sql stands
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainState {
+    /// nothing run for this sql yet
+    Empty,
+    Ready(String),
+    /// the sql ran again since, the plan may be out of date
+    Stale(String),
+    Failed(String),
+}
+
+impl ExplainState {
+    /// only a fresh plan is good enough to show without re-running
+    pub fn needs_run(&self) -> bool {
+        !matches!(self, ExplainState::Ready(_))
     }
 }
 
@@ -162,6 +250,23 @@ pub struct AgentInfo {
     pub model: String,
 }
 
+/// one provider's answer in compare mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareSide {
+    /// provider and model, e.g. `openai (gpt-4o)`
+    pub label: String,
+    /// the sql, or why there isn't any
+    pub sql: Result<String, String>,
+}
+
+/// two providers' sql for the same prompt, side by side
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub sides: [CompareSide; 2],
+    /// 0 left, 1 right
+    pub picked: usize,
+}
+
 pub struct App {
     pub running: bool,
     pub mode: Mode,
@@ -173,7 +278,19 @@ pub struct App {
 
     // settings
     pub confirm_before_run: bool,
+    pub confirm_all: bool,
+    /// --read-only, anything but SELECT and EXPLAIN is refused
+    pub read_only: bool,
+    /// --multi-statement, several statements run as one transaction
+    pub multi_statement: bool,
+    /// --limit for generated SELECTs without one, `None` leaves them alone
+    pub row_limit: Option<u64>,
+    pub csv_options: CsvOptions,
+    pub keymap: KeyMap,
+    pub ai_ready: bool,
+    pub ai_error: Option<String>,
     pub cache_enabled: bool,
+    pub max_prompt_chars: usize,
 
     // database info
     pub db_info: DbInfo,
@@ -187,14 +304,40 @@ pub struct App {
     pub connection_input: String,
     pub connection_cursor: usize,
 
+    // `:` command line and the describe popup it opens
+    pub command_input: String,
+    pub command_cursor: usize,
+    pub describe: Option<(String, Option<String>)>,
+
+    // cached schema text, used for quick lookups without the ai
+    pub schema: String,
+
     // sql state
     pub sql: Option<String>,
+    /// set while the confirm popup is up
+    pub pending_confirm: Option<PendingSql>,
     pub sql_status: Option<String>,
     pub latency_ms: Option<u64>,
     pub confidence: Option<u8>,
     pub risk: Option<RiskLevel>,
     pub show_explain: bool,
-    pub explain_result: Option<String>,
+    pub explain: ExplainState,
+    pub clarification: Option<String>,
+    pub candidates: Vec<String>,
+    pub candidate_index: usize,
+    /// --compare's provider and model, `None` when there's nothing to compare with
+    pub compare_label: Option<String>,
+    /// ask both providers on submit, toggled with `:compare`
+    pub compare_enabled: bool,
+    pub comparison: Option<Comparison>,
+    /// tables the prompt context is cut down to, `None` sends them all
+    pub schema_scope: Option<Vec<String>>,
+    /// the table picker's rows, (table, checked)
+    pub table_picks: Vec<(String, bool)>,
+    pub table_pick_index: usize,
+    // tokens for the last prompt and the whole session
+    pub last_usage: Option<Usage>,
+    pub session_usage: Option<Usage>,
 
     // results
     pub result: Option<QueryResult>,
@@ -202,9 +345,12 @@ pub struct App {
 
     // logs
     pub logs: Vec<LogEntry>,
+    pub log_filter: LogFilter,
 
     // state
     pub loading: bool,
+    /// what the model has written so far, shown while it streams
+    pub partial_sql: String,
     pub reconnecting: bool,
     pub query_start: Option<Instant>,
 
@@ -212,10 +358,18 @@ pub struct App {
     pub result_scroll: usize,
     pub log_scroll: usize,
     pub theme_scroll: usize,
+    pub help_scroll: usize,
 
     // history
     pub history: Vec<String>,
+    /// every sql run this session, for the session report
+    pub session: Vec<SessionEntry>,
+    // the prompt the current sql came from
+    last_prompt: Option<String>,
     pub history_index: Option<usize>,
+    // set by `load_history`, prompts are appended there as they're submitted
+    pub(super) history_file: Option<PathBuf>,
+    pub(super) history_limit: usize,
 
     // setup mode state
     pub in_setup_mode: bool,
@@ -242,6 +396,8 @@ pub struct App {
     pub setup_api_key_input: String,
     pub setup_api_key_cursor: usize,
     pub setup_error: Option<String>,
+    pub profiles: Vec<ProfileEntry>,
+    pub profile_index: usize,
 }
 
 impl App {
@@ -258,7 +414,16 @@ impl App {
             theme_kind,
             theme: Theme::from_kind(theme_kind),
             confirm_before_run,
+            confirm_all: false,
+            read_only: false,
+            multi_statement: false,
+            row_limit: None,
+            csv_options: CsvOptions::default(),
+            keymap: KeyMap::default(),
+            ai_ready: false,
+            ai_error: None,
             cache_enabled: false,
+            max_prompt_chars: DEFAULT_MAX_PROMPT_CHARS,
             db_info: db_info.clone(),
             agent_info: AgentInfo {
                 name: "nlql-agent".to_string(),
@@ -268,24 +433,47 @@ impl App {
             prompt_cursor: 0,
             connection_input,
             connection_cursor: 0,
+            command_input: String::new(),
+            command_cursor: 0,
+            describe: None,
+            schema: schema.clone(),
             sql: None,
+            pending_confirm: None,
             sql_status: None,
             latency_ms: None,
             confidence: None,
             risk: None,
             show_explain: false,
-            explain_result: None,
+            explain: ExplainState::Empty,
+            clarification: None,
+            candidates: Vec::new(),
+            candidate_index: 0,
+            compare_label: None,
+            compare_enabled: false,
+            comparison: None,
+            schema_scope: None,
+            table_picks: Vec::new(),
+            table_pick_index: 0,
+            last_usage: None,
+            session_usage: None,
             result: None,
             error: None,
             logs: Vec::new(),
+            log_filter: LogFilter::default(),
             loading: false,
+            partial_sql: String::new(),
             reconnecting: false,
             query_start: None,
             result_scroll: 0,
             log_scroll: 0,
             theme_scroll: theme_kind.index(),
+            help_scroll: 0,
             history: Vec::new(),
+            session: Vec::new(),
+            last_prompt: None,
             history_index: None,
+            history_file: None,
+            history_limit: DEFAULT_HISTORY_SIZE,
 
             // setup mode (not active when using normal constructor)
             in_setup_mode: false,
@@ -309,6 +497,8 @@ impl App {
             setup_api_key_input: String::new(),
             setup_api_key_cursor: 0,
             setup_error: None,
+            profiles: Vec::new(),
+            profile_index: 0,
         };
 
         // initial log
@@ -341,7 +531,16 @@ impl App {
             theme_kind,
             theme: Theme::from_kind(theme_kind),
             confirm_before_run: false,
+            confirm_all: false,
+            read_only: false,
+            multi_statement: false,
+            row_limit: None,
+            csv_options: CsvOptions::default(),
+            keymap: KeyMap::default(),
+            ai_ready: false,
+            ai_error: None,
             cache_enabled: false,
+            max_prompt_chars: DEFAULT_MAX_PROMPT_CHARS,
             db_info: DbInfo {
                 dialect: String::new(),
                 host: String::new(),
@@ -357,24 +556,47 @@ impl App {
             prompt_cursor: 0,
             connection_input: String::new(),
             connection_cursor: 0,
+            command_input: String::new(),
+            command_cursor: 0,
+            describe: None,
+            schema: String::new(),
             sql: None,
+            pending_confirm: None,
             sql_status: None,
             latency_ms: None,
             confidence: None,
             risk: None,
             show_explain: false,
-            explain_result: None,
+            explain: ExplainState::Empty,
+            clarification: None,
+            candidates: Vec::new(),
+            candidate_index: 0,
+            compare_label: None,
+            compare_enabled: false,
+            comparison: None,
+            schema_scope: None,
+            table_picks: Vec::new(),
+            table_pick_index: 0,
+            last_usage: None,
+            session_usage: None,
             result: None,
             error: None,
             logs: Vec::new(),
+            log_filter: LogFilter::default(),
             loading: false,
+            partial_sql: String::new(),
             reconnecting: false,
             query_start: None,
             result_scroll: 0,
             log_scroll: 0,
             theme_scroll: theme_kind.index(),
+            help_scroll: 0,
             history: Vec::new(),
+            session: Vec::new(),
+            last_prompt: None,
             history_index: None,
+            history_file: None,
+            history_limit: DEFAULT_HISTORY_SIZE,
 
             // setup mode active
             in_setup_mode: true,
@@ -398,6 +620,8 @@ impl App {
             setup_api_key_input: String::new(),
             setup_api_key_cursor: 0,
             setup_error: None,
+            profiles: Vec::new(),
+            profile_index: 0,
         }
     }
 
@@ -478,6 +702,12 @@ impl App {
     }
 
     pub fn setup_db_insert_char(&mut self, c: char) {
+        // the port field only takes digits
+        if self.setup_db_type != DbType::SQLite && self.setup_db_field == 1 && !c.is_ascii_digit() {
+            self.setup_error = Some("port must be a number".to_string());
+            return;
+        }
+
         let (field, cursor) = self.current_field_mut();
         field.insert(*cursor, c);
         *cursor += 1;
@@ -538,7 +768,12 @@ impl App {
                     self.setup_error = Some("file path required".to_string());
                     return None;
                 }
-                Some(format!("sqlite:{}", self.setup_db_file))
+                let url = format!("sqlite:{}", self.setup_db_file);
+                if let Err(e) = validate_url(&url) {
+                    self.setup_error = Some(e);
+                    return None;
+                }
+                Some(url)
             }
             DbType::PostgreSQL | DbType::MySQL => {
                 if self.setup_db_host.trim().is_empty() {
@@ -575,6 +810,10 @@ impl App {
                     format!("{}://{}:{}@{}:{}/{}", scheme, user, pass, host, port, name)
                 };
 
+                if let Err(e) = validate_url(&url) {
+                    self.setup_error = Some(e);
+                    return None;
+                }
                 Some(url)
             }
         }
@@ -584,22 +823,14 @@ impl App {
     pub fn setup_provider_up(&mut self) {
         if self.setup_provider_index > 0 {
             self.setup_provider_index -= 1;
-            self.setup_provider = if self.setup_provider_index == 0 {
-                Provider::Claude
-            } else {
-                Provider::OpenAI
-            };
+            self.setup_provider = SETUP_PROVIDERS[self.setup_provider_index].0;
         }
     }
 
     pub fn setup_provider_down(&mut self) {
-        if self.setup_provider_index < 1 {
+        if self.setup_provider_index + 1 < SETUP_PROVIDERS.len() {
             self.setup_provider_index += 1;
-            self.setup_provider = if self.setup_provider_index == 0 {
-                Provider::Claude
-            } else {
-                Provider::OpenAI
-            };
+            self.setup_provider = SETUP_PROVIDERS[self.setup_provider_index].0;
         }
     }
 
@@ -609,7 +840,8 @@ impl App {
 
     // setup api key input editing
     pub fn setup_api_key_insert_char(&mut self, c: char) {
-        self.setup_api_key_input.insert(self.setup_api_key_cursor, c);
+        self.setup_api_key_input
+            .insert(self.setup_api_key_cursor, c);
         self.setup_api_key_cursor += 1;
         self.setup_error = None;
     }
@@ -658,12 +890,51 @@ impl App {
         Some(self.setup_api_key_input.clone())
     }
 
+    /// setup mode, starting from the profile list when there are profiles
+    pub fn new_setup_with_profiles(profiles: Vec<ProfileEntry>) -> Self {
+        let mut app = Self::new_setup();
+        if !profiles.is_empty() {
+            app.popup = Popup::SetupProfile;
+        }
+        app.profiles = profiles;
+        app
+    }
+
+    // one past the last profile is "new connection"
+    pub fn profile_up(&mut self) {
+        self.profile_index = self.profile_index.saturating_sub(1);
+    }
+
+    pub fn profile_down(&mut self) {
+        if self.profile_index < self.profiles.len() {
+            self.profile_index += 1;
+        }
+    }
+
+    /// the url to connect to, or `None` after moving on to manual setup or an error.
+    /// a manual connection isn't saved back as a profile, those only come from the config
+    pub fn profile_select(&mut self) -> Option<String> {
+        self.setup_error = None;
+        let Some(profile) = self.profiles.get(self.profile_index) else {
+            self.popup = Popup::SetupDbType;
+            return None;
+        };
+        match &profile.connect {
+            Ok(url) => Some(url.clone()),
+            Err(e) => {
+                self.setup_error = Some(e.clone());
+                None
+            }
+        }
+    }
+
     pub fn setup_set_error(&mut self, error: String) {
         self.setup_error = Some(error);
     }
 
     pub fn finish_setup(&mut self, db_info: DbInfo, schema: &str) {
         self.in_setup_mode = false;
+        self.schema = schema.to_string();
         self.popup = Popup::None;
         self.db_info = db_info.clone();
         self.connection_input = db_info.url.clone();
@@ -689,6 +960,12 @@ impl App {
         }
     }
 
+    // entries are kept, the filter only changes what's drawn
+    pub fn cycle_log_filter(&mut self) {
+        self.log_filter = self.log_filter.next();
+        self.log_scroll = 0;
+    }
+
     pub fn set_theme(&mut self, kind: ThemeKind) {
         self.theme_kind = kind;
         self.theme = Theme::from_kind(kind);
@@ -706,6 +983,23 @@ impl App {
         self.connection_cursor = self.connection_input.len();
     }
 
+    pub fn open_help_popup(&mut self) {
+        self.popup = Popup::Help;
+        self.help_scroll = 0;
+    }
+
+    pub fn help_scroll_up(&mut self) {
+        self.help_scroll = self.help_scroll.saturating_sub(1);
+    }
+
+    pub fn help_scroll_down(&mut self) {
+        // a title and a blank line around each section
+        let lines: usize = self.keymap.help().iter().map(|s| s.entries.len() + 2).sum();
+        if self.help_scroll + 1 < lines {
+            self.help_scroll += 1;
+        }
+    }
+
     pub fn close_popup(&mut self) {
         self.popup = Popup::None;
     }
@@ -729,18 +1023,235 @@ impl App {
         self.close_popup();
     }
 
+    // with --confirm only writes ask first, --confirm-all asks for everything
+    pub fn needs_confirm(&self, sql: &str) -> bool {
+        // it's refused when it runs, a yes here would change nothing
+        if !self.confirm_before_run || (self.read_only && !Safety::is_read_only(sql)) {
+            return false;
+        }
+        if self.confirm_all {
+            return true;
+        }
+
+        // every statement, so a write after a SELECT still asks
+        !Safety::is_read_only(sql)
+    }
+
     pub fn show_confirm(&mut self, sql: String) {
+        self.pending_confirm = Some(PendingSql {
+            sql: sql.clone(),
+            db_url: self.db_info.url.clone(),
+        });
         self.sql = Some(sql);
         self.popup = Popup::Confirm;
     }
 
+    /// the sql the user said yes to, unless the database changed since it was shown
     pub fn confirm_sql(&mut self) -> Option<String> {
+        if self.popup == Popup::Confirm {
+            self.popup = Popup::None;
+        }
+        let pending = self.pending_confirm.take()?;
+        if pending.db_url != self.db_info.url {
+            self.log(
+                LogLevel::Warn,
+                "not running: the sql was written for a different connection".to_string(),
+            );
+            self.sql = None;
+            self.sql_status = None;
+            return None;
+        }
+        Some(pending.sql)
+    }
+
+    // drop sql nobody answered for, before a new prompt or connection replaces it
+    fn discard_pending(&mut self) {
+        if self.pending_confirm.take().is_none() {
+            return;
+        }
+        if self.popup == Popup::Confirm {
+            self.popup = Popup::None;
+        }
+        self.sql = None;
+        self.sql_status = None;
+        self.log(LogLevel::Info, "discarded unconfirmed sql".to_string());
+    }
+
+    // the model gave alternatives, let the user pick one
+    pub fn show_candidates(&mut self, candidates: Vec<String>) {
+        self.log(
+            LogLevel::Info,
+            format!("model offered {} queries, pick one", candidates.len()),
+        );
+        self.candidates = candidates;
+        self.candidate_index = 0;
+        self.loading = false;
+        self.popup = Popup::Candidates;
+    }
+
+    pub fn candidate_up(&mut self) {
+        self.candidate_index = self.candidate_index.saturating_sub(1);
+    }
+
+    pub fn candidate_down(&mut self) {
+        if self.candidate_index + 1 < self.candidates.len() {
+            self.candidate_index += 1;
+        }
+    }
+
+    pub fn pick_candidate(&mut self) -> Option<String> {
         self.popup = Popup::None;
-        self.sql.clone()
+        let sql = self.candidates.get(self.candidate_index).cloned();
+        self.candidates.clear();
+        sql
+    }
+
+    pub fn cancel_candidates(&mut self) {
+        self.popup = Popup::None;
+        self.candidates.clear();
+        self.log(LogLevel::Warn, "no query picked".to_string());
+    }
+
+    /// both providers answered, the sql panel shows them until one is picked
+    pub fn show_comparison(&mut self, left: CompareSide, right: CompareSide) {
+        self.log(
+            LogLevel::Info,
+            format!("comparing {} and {}, pick one", left.label, right.label),
+        );
+        // start on a side that has sql
+        let picked = usize::from(left.sql.is_err() && right.sql.is_ok());
+        self.comparison = Some(Comparison {
+            sides: [left, right],
+            picked,
+        });
+        self.loading = false;
+        self.popup = Popup::Compare;
+    }
+
+    pub fn compare_pick_side(&mut self, side: usize) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.picked = side.min(1);
+        }
+    }
+
+    /// the picked side's sql; a side that failed can't be picked
+    pub fn pick_comparison(&mut self) -> Option<String> {
+        let comparison = self.comparison.as_ref()?;
+        let side = &comparison.sides[comparison.picked];
+        match &side.sql {
+            Ok(sql) => {
+                let sql = sql.clone();
+                self.log(LogLevel::Info, format!("using {}", side.label));
+                self.comparison = None;
+                self.popup = Popup::None;
+                Some(sql)
+            }
+            Err(_) => {
+                self.log(LogLevel::Warn, format!("{} has no sql", side.label));
+                None
+            }
+        }
+    }
+
+    pub fn cancel_comparison(&mut self) {
+        self.popup = Popup::None;
+        self.comparison = None;
+        self.log(LogLevel::Warn, "no query picked".to_string());
+    }
+
+    /// `:compare`, only once a second provider is set up
+    pub fn toggle_compare(&mut self) {
+        let Some(label) = &self.compare_label else {
+            self.log(
+                LogLevel::Warn,
+                "compare: start with --compare <provider>".to_string(),
+            );
+            return;
+        };
+        self.compare_enabled = !self.compare_enabled;
+        let message = if self.compare_enabled {
+            format!("compare with {label} on")
+        } else {
+            "compare off".to_string()
+        };
+        self.log(LogLevel::Info, message);
+    }
+
+    /// the table picker, checked where the current scope has them
+    pub fn open_table_picker(&mut self) {
+        let scope = self.schema_scope.as_deref().unwrap_or_default();
+        self.table_picks = schema_tables(&self.schema)
+            .into_iter()
+            .map(|t| (t.to_string(), scope.iter().any(|s| s == t)))
+            .collect();
+        if self.table_picks.is_empty() {
+            self.log(LogLevel::Warn, "no tables to pick from".to_string());
+            return;
+        }
+        self.table_pick_index = 0;
+        self.popup = Popup::Tables;
+    }
+
+    pub fn table_pick_up(&mut self) {
+        self.table_pick_index = self.table_pick_index.saturating_sub(1);
+    }
+
+    pub fn table_pick_down(&mut self) {
+        if self.table_pick_index + 1 < self.table_picks.len() {
+            self.table_pick_index += 1;
+        }
+    }
+
+    pub fn toggle_table_pick(&mut self) {
+        if let Some((_, checked)) = self.table_picks.get_mut(self.table_pick_index) {
+            *checked = !*checked;
+        }
+    }
+
+    /// check everything, or clear it all when everything is already checked
+    pub fn toggle_all_table_picks(&mut self) {
+        let all = self.table_picks.iter().all(|(_, checked)| *checked);
+        for (_, checked) in &mut self.table_picks {
+            *checked = !all;
+        }
+    }
+
+    /// scope the schema to the checked tables; none checked means all of them
+    pub fn apply_table_picks(&mut self) {
+        self.popup = Popup::None;
+        let total = self.table_picks.len();
+        let picked: Vec<String> = std::mem::take(&mut self.table_picks)
+            .into_iter()
+            .filter_map(|(table, checked)| checked.then_some(table))
+            .collect();
+        if picked.is_empty() || picked.len() == total {
+            self.schema_scope = None;
+            self.log(LogLevel::Info, format!("using all {total} tables"));
+        } else {
+            self.log(
+                LogLevel::Info,
+                format!("schema scoped to {} of {total} tables", picked.len()),
+            );
+            self.schema_scope = Some(picked);
+        }
+    }
+
+    pub fn cancel_table_picks(&mut self) {
+        self.popup = Popup::None;
+        self.table_picks.clear();
+    }
+
+    /// `schema` cut down to the picked tables, as sent to the model
+    pub fn scoped_schema(&self, schema: &str) -> String {
+        match &self.schema_scope {
+            Some(tables) => scope_schema(schema, tables),
+            None => schema.to_string(),
+        }
     }
 
     pub fn cancel_sql(&mut self) {
         self.popup = Popup::None;
+        self.pending_confirm = None;
         self.sql = None;
         self.sql_status = None;
     }
@@ -793,9 +1304,108 @@ impl App {
         }
         let url = self.connection_input.clone();
         self.popup = Popup::None;
+        self.discard_pending();
         Some(url)
     }
 
+    // command line editing
+    pub fn open_command_popup(&mut self) {
+        self.popup = Popup::Command;
+        self.command_input.clear();
+        self.command_cursor = 0;
+    }
+
+    pub fn command_insert_char(&mut self, c: char) {
+        self.command_input.insert(self.command_cursor, c);
+        self.command_cursor += 1;
+    }
+
+    pub fn command_delete_char(&mut self) {
+        if self.command_cursor > 0 {
+            self.command_cursor -= 1;
+            self.command_input.remove(self.command_cursor);
+        }
+    }
+
+    pub fn command_move_left(&mut self) {
+        self.command_cursor = self.command_cursor.saturating_sub(1);
+    }
+
+    pub fn command_move_right(&mut self) {
+        if self.command_cursor < self.command_input.len() {
+            self.command_cursor += 1;
+        }
+    }
+
+    pub fn command_clear(&mut self) {
+        self.command_input.clear();
+        self.command_cursor = 0;
+    }
+
+    // run the typed command, e.g. `describe users` or `d users`
+    // `save` needs the database, so it's handed back to the event loop
+    pub fn run_command(&mut self) -> Option<SaveRequest> {
+        let input = self.command_input.trim().to_string();
+        let mut parts = input.split_whitespace();
+
+        match (parts.next(), parts.next()) {
+            (Some("save"), Some(table)) => {
+                self.close_popup();
+                return Some(SaveRequest {
+                    table: table.to_string(),
+                    out: parts.next().map(str::to_string),
+                });
+            }
+            (Some("save"), None) => {
+                self.log(LogLevel::Warn, "usage: save <table> [file.db]".to_string());
+                self.close_popup();
+            }
+            (Some("describe" | "desc" | "d"), Some(table)) => {
+                let block = describe_table(&self.schema, table);
+                self.describe = Some((table.to_string(), block));
+                self.popup = Popup::Describe;
+            }
+            (Some("sort"), Some(column)) => {
+                self.close_popup();
+                let descending = matches!(parts.next(), Some("desc" | "d"));
+                let Some(result) = self.result.as_mut() else {
+                    self.log(LogLevel::Warn, "sort: no results yet".to_string());
+                    return None;
+                };
+                match result.sort_by(column, descending) {
+                    Ok(()) => {
+                        self.result_scroll = 0;
+                        let order = if descending { "desc" } else { "asc" };
+                        self.log(LogLevel::Info, format!("sorted by {column} {order}"));
+                    }
+                    Err(e) => self.log(LogLevel::Warn, e.to_string()),
+                }
+            }
+            (Some("tables"), _) => {
+                self.close_popup();
+                self.open_table_picker();
+            }
+            (Some("compare"), _) => {
+                self.close_popup();
+                self.toggle_compare();
+            }
+            (Some("sort"), None) => {
+                self.log(LogLevel::Warn, "usage: sort <column> [desc]".to_string());
+                self.close_popup();
+            }
+            (Some("describe" | "desc" | "d"), None) => {
+                self.log(LogLevel::Warn, "usage: describe <table>".to_string());
+                self.close_popup();
+            }
+            (Some(other), _) => {
+                self.log(LogLevel::Warn, format!("unknown command: {other}"));
+                self.close_popup();
+            }
+            (None, _) => self.close_popup(),
+        }
+        None
+    }
+
     pub fn update_db_info(&mut self, info: DbInfo, schema: String) {
         self.log(LogLevel::Ok, format!("connected {}", info.dialect));
         self.log(
@@ -806,7 +1416,10 @@ impl App {
             ),
         );
         self.db_info = info;
+        self.schema = schema;
         self.reconnecting = false;
+        self.schema_scope = None;
+        self.discard_pending();
         self.result = None;
         self.sql = None;
         self.sql_status = None;
@@ -814,7 +1427,8 @@ impl App {
         self.confidence = None;
         self.risk = None;
         self.show_explain = false;
-        self.explain_result = None;
+        self.explain = ExplainState::Empty;
+        self.clarification = None;
     }
 
     pub fn cycle_panel(&mut self) {
@@ -925,28 +1539,111 @@ impl App {
             return None;
         }
         let query = self.prompt.clone();
-        self.history.push(query.clone());
+        self.last_prompt = Some(query.clone());
+        self.discard_pending();
+        // a repeated prompt is only recalled once
+        if self.history.last() != Some(&query) {
+            self.history.push(query.clone());
+            let over = self.history.len().saturating_sub(self.history_limit);
+            self.history.drain(..over);
+            self.save_history(&query);
+        }
         self.history_index = None;
         self.clear_prompt();
         self.error = None;
+        self.clarification = None;
         self.query_start = Some(Instant::now());
         Some(query)
     }
 
+    // model asked a question instead of writing sql; hand the prompt back to refine
+    pub fn set_clarification(&mut self, question: String, query: String) {
+        self.query_start = None;
+        self.loading = false;
+        self.sql = None;
+        self.sql_status = None;
+        self.confidence = None;
+        self.risk = None;
+        self.log(LogLevel::Warn, format!("model asks: {question}"));
+        self.clarification = Some(question);
+        self.prompt_cursor = query.len();
+        self.prompt = query;
+        self.panel = Panel::Prompt;
+    }
+
+    pub fn set_ai_unavailable(&mut self, reason: String) {
+        self.ai_ready = false;
+        self.log(LogLevel::Error, format!("ai not ready: {reason}"));
+        self.ai_error = Some(reason);
+    }
+
+    // submitted without a working ai client: keep the prompt and say why
+    pub fn block_submit(&mut self, query: String) {
+        self.query_start = None;
+        self.prompt_cursor = query.len();
+        self.prompt = query;
+        let reason = self
+            .ai_error
+            .as_deref()
+            .unwrap_or("ai client not initialized");
+        self.set_error(format!(
+            "ai not configured ({reason}) - set the provider's api key env var or pass --api-key"
+        ));
+    }
+
+    /// the next streamed piece of the model's answer
+    pub fn append_partial_sql(&mut self, text: &str) {
+        self.partial_sql.push_str(text);
+    }
+
     pub fn set_sql(&mut self, sql: String) {
+        self.clarification = None;
         self.risk = Some(RiskLevel::from_sql(&sql));
-        self.confidence = Some(92); // TODO: get from AI response
+        // only a fresh generation knows it, see `submit_prompt`
+        self.confidence = None;
         self.sql = Some(sql);
         self.sql_status = Some("pending".to_string());
-        self.explain_result = None; // clear old explain
+        self.explain = ExplainState::Empty;
         self.show_explain = false;
         self.log(LogLevel::Ok, "generated sql".to_string());
     }
 
+    pub fn prompt_chars(&self) -> usize {
+        self.prompt.chars().count()
+    }
+
+    /// rough token count, about four chars a token for english
+    pub fn prompt_tokens(&self) -> usize {
+        self.prompt_chars().div_ceil(4)
+    }
+
+    pub fn prompt_size(&self) -> PromptSize {
+        let chars = self.prompt_chars();
+        if chars > self.max_prompt_chars {
+            PromptSize::Over
+        } else if chars * 5 > self.max_prompt_chars * 4 {
+            PromptSize::Near
+        } else {
+            PromptSize::Fine
+        }
+    }
+
+    /// note what a prompt cost, from the generator's totals before and after it
+    pub fn record_usage(&mut self, before: Option<Usage>, after: Option<Usage>) {
+        self.last_usage = after.map(|after| after - before.unwrap_or_default());
+        self.session_usage = after;
+    }
+
     pub fn toggle_explain(&mut self) {
         self.show_explain = !self.show_explain;
     }
 
+    /// show the plan and force it to be run again
+    pub fn refresh_explain(&mut self) {
+        self.show_explain = true;
+        self.explain = ExplainState::Empty;
+    }
+
     pub fn copy_sql(&self) -> Option<String> {
         self.sql.clone()
     }
@@ -954,8 +1651,8 @@ impl App {
     pub fn copy_output(&self) -> Option<String> {
         let result = self.result.as_ref()?;
 
-        if result.rows.is_empty() {
-            return Some("no rows".to_string());
+        if let Some(status) = result.status(self.sql.as_deref().unwrap_or_default()) {
+            return Some(status);
         }
 
         let mut output = String::new();
@@ -1001,6 +1698,10 @@ impl App {
             output.push('\n');
         }
 
+        if result.truncated {
+            output.push_str(&format!("(truncated to {} rows)\n", result.row_count));
+        }
+
         Some(output)
     }
 
@@ -1017,29 +1718,7 @@ impl App {
 
     pub fn export_csv(&self) -> Option<String> {
         let result = self.result.as_ref()?;
-        let mut csv = result.columns.join(",");
-        csv.push('\n');
-
-        for row in &result.rows {
-            let values: Vec<String> = row
-                .iter()
-                .map(|v| match v {
-                    serde_json::Value::String(s) => {
-                        if s.contains(',') || s.contains('"') || s.contains('\n') {
-                            format!("\"{}\"", s.replace('"', "\"\""))
-                        } else {
-                            s.clone()
-                        }
-                    }
-                    serde_json::Value::Null => String::new(),
-                    _ => v.to_string(),
-                })
-                .collect();
-            csv.push_str(&values.join(","));
-            csv.push('\n');
-        }
-
-        Some(csv)
+        Some(result.to_csv(&self.csv_options))
     }
 
     pub fn set_result(&mut self, result: QueryResult) {
@@ -1047,11 +1726,51 @@ impl App {
             self.latency_ms = Some(start.elapsed().as_millis() as u64);
         }
         self.sql_status = Some(format!("executed ({}ms)", self.latency_ms.unwrap_or(0)));
+        if result.truncated {
+            self.log(
+                LogLevel::Warn,
+                format!("result truncated to {} rows", result.row_count),
+            );
+        }
+        if let Some(sql) = &self.sql {
+            let mut kept = result.clone();
+            if kept.rows.len() > SESSION_ROWS {
+                kept.rows.truncate(SESSION_ROWS);
+                kept.truncated = true;
+            }
+            self.session.push(SessionEntry {
+                prompt: self.last_prompt.clone(),
+                sql: sql.clone(),
+                outcome: Ok(kept),
+            });
+        }
         self.result = Some(result);
         self.error = None;
         self.loading = false;
         self.result_scroll = 0;
-        self.log(LogLevel::Ok, "executed query".to_string());
+        // the data may have moved under the plan
+        if let ExplainState::Ready(plan) = &self.explain {
+            self.explain = ExplainState::Stale(plan.clone());
+        }
+        let message = self
+            .result
+            .as_ref()
+            .zip(self.sql.as_deref())
+            .and_then(|(result, sql)| result.status(sql))
+            .unwrap_or_else(|| "executed query".to_string());
+        self.log(LogLevel::Ok, message);
+    }
+
+    /// the sql itself failed, unlike the ai or connection errors `set_error` also covers
+    pub fn set_sql_error(&mut self, err: String) {
+        if let Some(sql) = &self.sql {
+            self.session.push(SessionEntry {
+                prompt: self.last_prompt.clone(),
+                sql: sql.clone(),
+                outcome: Err(err.clone()),
+            });
+        }
+        self.set_error(err);
     }
 
     pub fn set_error(&mut self, err: String) {
@@ -1080,6 +1799,85 @@ impl App {
             _ => {}
         }
     }
+
+    pub fn scroll_top(&mut self) {
+        match self.panel {
+            Panel::Results => self.result_scroll = 0,
+            Panel::Logs => self.log_scroll = 0,
+            _ => {}
+        }
+    }
+
+    // like the log's auto-scroll, the last lines stay in view
+    pub fn scroll_bottom(&mut self) {
+        match self.panel {
+            Panel::Results => {
+                // header and separator come before the rows
+                let lines = self.result.as_ref().map_or(0, |r| r.rows.len() + 2);
+                self.result_scroll = lines.saturating_sub(10);
+            }
+            Panel::Logs => {
+                let shown = self
+                    .logs
+                    .iter()
+                    .filter(|entry| self.log_filter.allows(entry.level))
+                    .count();
+                self.log_scroll = shown.saturating_sub(10);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// check a connection url's shape before we try to connect with it
+pub fn validate_url(url: &str) -> Result<(), String> {
+    if let Some(path) = sqlite_path(url) {
+        if path.trim().is_empty() {
+            return Err("sqlite url needs a file path".to_string());
+        }
+        return Ok(());
+    }
+
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Err("missing scheme, expected postgres://, mysql:// or sqlite:".to_string());
+    };
+
+    if !matches!(scheme, "postgres" | "postgresql" | "mysql" | "mariadb") {
+        return Err(format!("unknown scheme '{scheme}'"));
+    }
+
+    // [user[:pass]@]host[:port][/name]
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, hp)| hp);
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (host_port, None),
+    };
+
+    if host.trim().is_empty() {
+        return Err("host required".to_string());
+    }
+    if let Some(port) = port
+        && (port.is_empty() || port.parse::<u16>().is_err())
+    {
+        return Err(format!("port '{port}' is not a valid number"));
+    }
+
+    Ok(())
+}
+
+// pull one `TABLE name ( ... )` block out of the schema text
+fn describe_table(schema: &str, table: &str) -> Option<String> {
+    schema
+        .split("\n\n")
+        .find(|block| {
+            block
+                .lines()
+                .next()
+                .and_then(|l| l.split_whitespace().nth(1))
+                .is_some_and(|name| name.eq_ignore_ascii_case(table))
+        })
+        .map(|block| block.to_string())
 }
 
 fn format_value(val: &serde_json::Value) -> String {
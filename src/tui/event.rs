@@ -3,8 +3,9 @@
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use std::time::Duration;
 
-use crate::tui::app::{App, Mode, Popup};
 use crate::Provider;
+use crate::tui::app::{App, Mode, Panel, Popup, SaveRequest};
+use crate::tui::keys::KeyAction;
 
 pub enum Action {
     None,
@@ -12,11 +13,17 @@ pub enum Action {
     Submit(String),
     ConfirmSql,
     CancelSql,
+    RerunSql,
+    ToggleCache,
     Reconnect(String),
     ToggleExplain,
+    RefreshExplain,
     CopySql,
     CopyOutput,
     ExportCsv,
+    ExportSession,
+    PickCandidate(String),
+    SaveTable(SaveRequest),
     // setup actions
     SetupConnectDb(String),
     SetupComplete {
@@ -54,10 +61,17 @@ fn handle_key(app: &mut App, key: KeyEvent) -> Action {
         Popup::Themes => return handle_theme_popup(app, key),
         Popup::Confirm => return handle_confirm_popup(app, key),
         Popup::Connection => return handle_connection_popup(app, key),
+        Popup::SetupProfile => return handle_setup_profile_popup(app, key),
         Popup::SetupDbType => return handle_setup_db_type_popup(app, key),
         Popup::SetupDbDetails => return handle_setup_db_details_popup(app, key),
         Popup::SetupProvider => return handle_setup_provider_popup(app, key),
         Popup::SetupApiKey => return handle_setup_api_key_popup(app, key),
+        Popup::Command => return handle_command_popup(app, key),
+        Popup::Describe => return handle_describe_popup(app, key),
+        Popup::Candidates => return handle_candidates_popup(app, key),
+        Popup::Compare => return handle_compare_popup(app, key),
+        Popup::Help => return handle_help_popup(app, key),
+        Popup::Tables => return handle_tables_popup(app, key),
         Popup::None => {}
     }
 
@@ -163,6 +177,103 @@ fn handle_connection_popup(app: &mut App, key: KeyEvent) -> Action {
     }
 }
 
+fn handle_command_popup(app: &mut App, key: KeyEvent) -> Action {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        if key.code == KeyCode::Char('u') {
+            app.command_clear();
+        }
+        return Action::None;
+    }
+
+    match key.code {
+        KeyCode::Esc => app.close_popup(),
+        KeyCode::Enter => {
+            if let Some(request) = app.run_command() {
+                return Action::SaveTable(request);
+            }
+        }
+        KeyCode::Char(c) => app.command_insert_char(c),
+        KeyCode::Backspace => app.command_delete_char(),
+        KeyCode::Left => app.command_move_left(),
+        KeyCode::Right => app.command_move_right(),
+        _ => {}
+    }
+    Action::None
+}
+
+fn handle_describe_popup(app: &mut App, key: KeyEvent) -> Action {
+    if matches!(key.code, KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter) {
+        app.close_popup();
+    }
+    Action::None
+}
+
+fn handle_candidates_popup(app: &mut App, key: KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.cancel_candidates(),
+        KeyCode::Char('j') | KeyCode::Down => app.candidate_down(),
+        KeyCode::Char('k') | KeyCode::Up => app.candidate_up(),
+        KeyCode::Enter => {
+            if let Some(sql) = app.pick_candidate() {
+                return Action::PickCandidate(sql);
+            }
+        }
+        _ => {}
+    }
+    Action::None
+}
+
+fn handle_compare_popup(app: &mut App, key: KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.cancel_comparison(),
+        KeyCode::Char('h') | KeyCode::Left => app.compare_pick_side(0),
+        KeyCode::Char('l') | KeyCode::Right => app.compare_pick_side(1),
+        KeyCode::Tab => {
+            let side = app.comparison.as_ref().map_or(0, |c| 1 - c.picked);
+            app.compare_pick_side(side);
+        }
+        KeyCode::Enter => {
+            if let Some(sql) = app.pick_comparison() {
+                return Action::PickCandidate(sql);
+            }
+        }
+        _ => {}
+    }
+    Action::None
+}
+
+fn handle_tables_popup(app: &mut App, key: KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.cancel_table_picks(),
+        KeyCode::Char('j') | KeyCode::Down => app.table_pick_down(),
+        KeyCode::Char('k') | KeyCode::Up => app.table_pick_up(),
+        KeyCode::Char(' ') => app.toggle_table_pick(),
+        KeyCode::Char('a') => app.toggle_all_table_picks(),
+        KeyCode::Enter => app.apply_table_picks(),
+        _ => {}
+    }
+    Action::None
+}
+
+fn handle_setup_profile_popup(app: &mut App, key: KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Esc => Action::Quit,
+        KeyCode::Char('j') | KeyCode::Down => {
+            app.profile_down();
+            Action::None
+        }
+        KeyCode::Char('k') | KeyCode::Up => {
+            app.profile_up();
+            Action::None
+        }
+        KeyCode::Enter => match app.profile_select() {
+            Some(url) => Action::SetupConnectDb(url),
+            None => Action::None,
+        },
+        _ => Action::None,
+    }
+}
+
 fn handle_setup_db_type_popup(app: &mut App, key: KeyEvent) -> Action {
     match key.code {
         KeyCode::Esc => Action::Quit,
@@ -280,6 +391,12 @@ fn handle_setup_provider_popup(app: &mut App, key: KeyEvent) -> Action {
                         || std::env::var("CLAUDE_API_KEY").is_ok()
                 }
                 Provider::OpenAI => std::env::var("OPENAI_API_KEY").is_ok(),
+                Provider::Gemini => {
+                    std::env::var("GEMINI_API_KEY").is_ok()
+                        || std::env::var("GOOGLE_API_KEY").is_ok()
+                }
+                // nothing to ask for
+                Provider::Ollama => true,
             };
 
             if has_env_key {
@@ -360,101 +477,153 @@ fn handle_setup_api_key_popup(app: &mut App, key: KeyEvent) -> Action {
     }
 }
 
-fn handle_normal_key(app: &mut App, key: KeyEvent) -> Action {
+fn handle_help_popup(app: &mut App, key: KeyEvent) -> Action {
     match key.code {
-        // quit
-        KeyCode::Char('q') => Action::Quit,
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => app.close_popup(),
+        KeyCode::Char('j') | KeyCode::Down => app.help_scroll_down(),
+        KeyCode::Char('k') | KeyCode::Up => app.help_scroll_up(),
+        _ => {}
+    }
+    Action::None
+}
+
+fn handle_normal_key(app: &mut App, key: KeyEvent) -> Action {
+    let Some(action) = app.keymap.lookup(&key) else {
+        return Action::None;
+    };
+
+    match action {
+        KeyAction::Quit => Action::Quit,
 
         // enter insert mode
-        KeyCode::Char('i') => {
+        KeyAction::Insert => {
             app.enter_insert();
             Action::None
         }
-        KeyCode::Char('a') => {
+        KeyAction::Append | KeyAction::AppendEnd => {
             app.move_cursor_end();
             app.enter_insert();
             Action::None
         }
-        KeyCode::Char('I') => {
+        KeyAction::InsertStart => {
             app.move_cursor_start();
             app.enter_insert();
             Action::None
         }
-        KeyCode::Char('A') => {
-            app.move_cursor_end();
-            app.enter_insert();
-            Action::None
-        }
 
         // panel navigation
-        KeyCode::Tab => {
+        KeyAction::NextPanel => {
             app.cycle_panel();
             Action::None
         }
+        KeyAction::FocusPrompt => {
+            app.panel = Panel::Prompt;
+            Action::None
+        }
+        KeyAction::FocusSql => {
+            app.panel = Panel::Sql;
+            Action::None
+        }
+        KeyAction::FocusResults => {
+            app.panel = Panel::Results;
+            Action::None
+        }
+        KeyAction::FocusLogs => {
+            app.panel = Panel::Logs;
+            Action::None
+        }
+
+        KeyAction::Help => {
+            app.open_help_popup();
+            Action::None
+        }
 
         // theme popup
-        KeyCode::Char('t') => {
+        KeyAction::Themes => {
             app.open_theme_popup();
             Action::None
         }
 
         // fullscreen toggle
-        KeyCode::Char('f') => {
+        KeyAction::Fullscreen => {
             app.toggle_fullscreen();
             Action::None
         }
 
         // connection popup
-        KeyCode::Char('c') => {
+        KeyAction::Connection => {
             app.open_connection_popup();
             Action::None
         }
 
+        // re-run the current sql
+        KeyAction::Rerun => Action::RerunSql,
+
+        // result cache toggle
+        KeyAction::ToggleCache => Action::ToggleCache,
+
+        // command line (describe <table>)
+        KeyAction::Command => {
+            app.open_command_popup();
+            Action::None
+        }
+
         // explain toggle
-        KeyCode::Char('e') => {
+        KeyAction::Explain => {
             app.toggle_explain();
             Action::ToggleExplain
         }
+        KeyAction::RefreshExplain => {
+            app.refresh_explain();
+            Action::RefreshExplain
+        }
 
-        // copy sql
-        KeyCode::Char('y') => Action::CopySql,
-
-        // copy output
-        KeyCode::Char('Y') => Action::CopyOutput,
+        KeyAction::CopySql => Action::CopySql,
+        KeyAction::CopyOutput => Action::CopyOutput,
+        KeyAction::Export => Action::ExportCsv,
+        KeyAction::SessionReport => Action::ExportSession,
 
-        // export csv
-        KeyCode::Char('x') => Action::ExportCsv,
+        // logs panel level filter
+        KeyAction::LogFilter => {
+            app.cycle_log_filter();
+            Action::None
+        }
 
         // scrolling
-        KeyCode::Char('j') | KeyCode::Down => {
+        KeyAction::ScrollDown => {
             app.scroll_down();
             Action::None
         }
-        KeyCode::Char('k') | KeyCode::Up => {
+        KeyAction::ScrollUp => {
             app.scroll_up();
             Action::None
         }
+        KeyAction::ScrollTop => {
+            app.scroll_top();
+            Action::None
+        }
+        KeyAction::ScrollBottom => {
+            app.scroll_bottom();
+            Action::None
+        }
 
         // history
-        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+        KeyAction::HistoryUp => {
             app.history_up();
             Action::None
         }
-        KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+        KeyAction::HistoryDown => {
             app.history_down();
             Action::None
         }
 
-        // submit
-        KeyCode::Enter => {
+        KeyAction::Submit => {
             if let Some(query) = app.submit() {
                 Action::Submit(query)
             } else {
                 Action::None
             }
         }
-
-        _ => Action::None,
     }
 }
 
@@ -0,0 +1,98 @@
+// prompt history kept across sessions, one prompt per line
+
+use super::app::{App, LogLevel};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// prompts kept in the history file unless --history-size says otherwise
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// where the history lives, ~/.local/share/nlql/history (respects XDG_DATA_HOME)
+pub fn history_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))?;
+    Some(base.join("nlql").join("history"))
+}
+
+impl App {
+    /// load earlier prompts from `path` and append new ones to it, keeping the last `limit`
+    ///
+    /// a missing or unreadable file just starts an empty history
+    pub fn load_history(&mut self, path: PathBuf, limit: usize) {
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        let mut history: Vec<String> = Vec::new();
+        for prompt in text.lines().filter(|l| !l.is_empty()).map(unescape) {
+            if history.last() != Some(&prompt) {
+                history.push(prompt);
+            }
+        }
+
+        let stored = text.lines().count();
+        let start = history.len().saturating_sub(limit);
+        history.drain(..start);
+        // cut the file back down, only when it grew past the cap or had repeats
+        if stored > history.len() && write_all(&path, &history).is_err() {
+            self.log(LogLevel::Warn, format!("can't trim {}", path.display()));
+        }
+
+        self.history = history;
+        self.history_index = None;
+        self.history_file = Some(path);
+        self.history_limit = limit;
+    }
+
+    // called from `submit`, the prompt is already in `history`
+    pub(super) fn save_history(&mut self, prompt: &str) {
+        let Some(path) = &self.history_file else {
+            return;
+        };
+        if let Err(e) = append(path, prompt) {
+            let message = format!("history not saved to {}: {e}", path.display());
+            // once is enough, the next prompt would only fail the same way
+            self.history_file = None;
+            self.log(LogLevel::Warn, message);
+        }
+    }
+}
+
+fn append(path: &Path, prompt: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", escape(prompt))
+}
+
+fn write_all(path: &Path, history: &[String]) -> std::io::Result<()> {
+    let text: String = history.iter().map(|p| format!("{}\n", escape(p))).collect();
+    std::fs::write(path, text)
+}
+
+// a pasted multi-line prompt still takes one line
+fn escape(prompt: &str) -> String {
+    prompt.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,478 @@
+// normal mode keybindings, remappable from the `[keys]` config section
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+
+/// things a normal mode key can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Quit,
+    Insert,
+    Append,
+    InsertStart,
+    AppendEnd,
+    NextPanel,
+    FocusPrompt,
+    FocusSql,
+    FocusResults,
+    FocusLogs,
+    Themes,
+    Fullscreen,
+    Connection,
+    Rerun,
+    ToggleCache,
+    Command,
+    Explain,
+    RefreshExplain,
+    CopySql,
+    CopyOutput,
+    Export,
+    SessionReport,
+    LogFilter,
+    ScrollDown,
+    ScrollUp,
+    ScrollTop,
+    ScrollBottom,
+    HistoryUp,
+    HistoryDown,
+    Submit,
+    Help,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 31] = [
+        KeyAction::Quit,
+        KeyAction::Insert,
+        KeyAction::Append,
+        KeyAction::InsertStart,
+        KeyAction::AppendEnd,
+        KeyAction::NextPanel,
+        KeyAction::FocusPrompt,
+        KeyAction::FocusSql,
+        KeyAction::FocusResults,
+        KeyAction::FocusLogs,
+        KeyAction::Themes,
+        KeyAction::Fullscreen,
+        KeyAction::Connection,
+        KeyAction::Rerun,
+        KeyAction::ToggleCache,
+        KeyAction::Command,
+        KeyAction::Explain,
+        KeyAction::RefreshExplain,
+        KeyAction::CopySql,
+        KeyAction::CopyOutput,
+        KeyAction::Export,
+        KeyAction::SessionReport,
+        KeyAction::LogFilter,
+        KeyAction::ScrollDown,
+        KeyAction::ScrollUp,
+        KeyAction::ScrollTop,
+        KeyAction::ScrollBottom,
+        KeyAction::HistoryUp,
+        KeyAction::HistoryDown,
+        KeyAction::Submit,
+        KeyAction::Help,
+    ];
+
+    /// name used in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyAction::Quit => "quit",
+            KeyAction::Insert => "insert",
+            KeyAction::Append => "append",
+            KeyAction::InsertStart => "insert_start",
+            KeyAction::AppendEnd => "append_end",
+            KeyAction::NextPanel => "next_panel",
+            KeyAction::FocusPrompt => "focus_prompt",
+            KeyAction::FocusSql => "focus_sql",
+            KeyAction::FocusResults => "focus_results",
+            KeyAction::FocusLogs => "focus_logs",
+            KeyAction::Themes => "themes",
+            KeyAction::Fullscreen => "fullscreen",
+            KeyAction::Connection => "connection",
+            KeyAction::Rerun => "rerun",
+            KeyAction::ToggleCache => "toggle_cache",
+            KeyAction::Command => "command",
+            KeyAction::Explain => "explain",
+            KeyAction::RefreshExplain => "refresh_explain",
+            KeyAction::CopySql => "copy_sql",
+            KeyAction::CopyOutput => "copy_output",
+            KeyAction::Export => "export",
+            KeyAction::SessionReport => "session_report",
+            KeyAction::LogFilter => "log_filter",
+            KeyAction::ScrollDown => "scroll_down",
+            KeyAction::ScrollUp => "scroll_up",
+            KeyAction::ScrollTop => "scroll_top",
+            KeyAction::ScrollBottom => "scroll_bottom",
+            KeyAction::HistoryUp => "history_up",
+            KeyAction::HistoryDown => "history_down",
+            KeyAction::Submit => "submit",
+            KeyAction::Help => "help",
+        }
+    }
+
+    /// what the key does, shown in the help popup
+    pub fn description(&self) -> &'static str {
+        match self {
+            KeyAction::Quit => "quit",
+            KeyAction::Insert => "edit the prompt",
+            KeyAction::Append => "edit the prompt at the end",
+            KeyAction::InsertStart => "edit the prompt at the start",
+            KeyAction::AppendEnd => "edit the prompt at the end",
+            KeyAction::NextPanel => "next panel",
+            KeyAction::FocusPrompt => "go to the prompt",
+            KeyAction::FocusSql => "go to the sql",
+            KeyAction::FocusResults => "go to the results",
+            KeyAction::FocusLogs => "go to the logs",
+            KeyAction::Themes => "pick a theme",
+            KeyAction::Fullscreen => "toggle fullscreen",
+            KeyAction::Connection => "change the connection",
+            KeyAction::Rerun => "run the sql again",
+            KeyAction::ToggleCache => "toggle the result cache",
+            KeyAction::Command => "command line (describe, save)",
+            KeyAction::Explain => "toggle the query plan",
+            KeyAction::RefreshExplain => "refresh the query plan",
+            KeyAction::CopySql => "copy the sql",
+            KeyAction::CopyOutput => "copy the output",
+            KeyAction::Export => "export the results as csv",
+            KeyAction::SessionReport => "write the session to a markdown report",
+            KeyAction::LogFilter => "cycle the log level filter",
+            KeyAction::ScrollDown => "scroll down",
+            KeyAction::ScrollUp => "scroll up",
+            KeyAction::ScrollTop => "scroll to the top",
+            KeyAction::ScrollBottom => "scroll to the bottom",
+            KeyAction::HistoryUp => "previous prompt",
+            KeyAction::HistoryDown => "next prompt",
+            KeyAction::Submit => "run the prompt",
+            KeyAction::Help => "this help",
+        }
+    }
+
+    // "CopySql", "copy_sql" and "copy-sql" all work
+    fn from_name(name: &str) -> Option<Self> {
+        let wanted: String = name
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name().replace('_', "") == wanted)
+    }
+
+    fn defaults(&self) -> &'static [&'static str] {
+        match self {
+            KeyAction::Quit => &["q"],
+            KeyAction::Insert => &["i"],
+            KeyAction::Append => &["a"],
+            KeyAction::InsertStart => &["I"],
+            KeyAction::AppendEnd => &["A"],
+            KeyAction::NextPanel => &["tab"],
+            KeyAction::FocusPrompt => &["1"],
+            KeyAction::FocusSql => &["2"],
+            KeyAction::FocusResults => &["3"],
+            KeyAction::FocusLogs => &["4"],
+            KeyAction::Themes => &["t"],
+            KeyAction::Fullscreen => &["f"],
+            KeyAction::Connection => &["c"],
+            KeyAction::Rerun => &["r"],
+            KeyAction::ToggleCache => &["C"],
+            KeyAction::Command => &[":"],
+            KeyAction::Explain => &["e"],
+            KeyAction::RefreshExplain => &["E"],
+            KeyAction::CopySql => &["y"],
+            KeyAction::CopyOutput => &["Y"],
+            KeyAction::Export => &["x"],
+            KeyAction::SessionReport => &["S"],
+            KeyAction::LogFilter => &["L"],
+            KeyAction::ScrollDown => &["j", "down"],
+            KeyAction::ScrollUp => &["k", "up"],
+            KeyAction::ScrollTop => &["g"],
+            KeyAction::ScrollBottom => &["G"],
+            KeyAction::HistoryUp => &["ctrl+p"],
+            KeyAction::HistoryDown => &["ctrl+n"],
+            KeyAction::Submit => &["enter"],
+            KeyAction::Help => &["?"],
+        }
+    }
+}
+
+/// a key plus the modifiers that matter (ctrl, alt)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// parse things like `q`, `G`, `ctrl+p`, `alt+enter`, `f5`, `space`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        // a lone "+" is a key, not a separator
+        let (mods, key) = match s.rsplit_once('+') {
+            Some((mods, "")) if mods.is_empty() || mods.ends_with('+') => {
+                (mods.trim_end_matches('+'), "+")
+            }
+            Some((mods, key)) => (mods, key),
+            None => ("", s),
+        };
+        if key.is_empty() {
+            return Err("empty key".to_string());
+        }
+        let parts = mods.split('+').filter(|m| !m.is_empty());
+
+        for m in parts {
+            modifiers |= match m.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier '{other}'")),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+                KeyCode::F(f[1..].parse().unwrap_or(1))
+            }
+            _ if key.chars().count() == 1 => {
+                let c = key.chars().next().unwrap_or_default();
+                // shift+a is just A
+                if modifiers.contains(KeyModifiers::SHIFT) {
+                    modifiers.remove(KeyModifiers::SHIFT);
+                    KeyCode::Char(c.to_ascii_uppercase())
+                } else {
+                    KeyCode::Char(c)
+                }
+            }
+            _ => return Err(format!("unknown key '{key}'")),
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // terminals report shift on capitals, the char already says it
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+}
+
+// written the way `parse` reads it
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            code => write!(f, "{}", format!("{code:?}").to_lowercase()),
+        }
+    }
+}
+
+impl From<&KeyEvent> for KeyBinding {
+    fn from(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers & !KeyModifiers::SUPER)
+    }
+}
+
+/// lookup from key to action for normal mode
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: HashMap<KeyBinding, KeyAction>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        for action in KeyAction::ALL {
+            for key in action.defaults() {
+                if let Ok(binding) = KeyBinding::parse(key) {
+                    bindings.insert(binding, action);
+                }
+            }
+        }
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    /// defaults with the `[keys]` overrides applied, plus warnings for entries we skipped
+    ///
+    /// a value can list several keys: `scroll_down = "j, down, ctrl+e"`
+    pub fn from_config(keys: &HashMap<String, String>) -> (Self, Vec<String>) {
+        let mut map = Self::default();
+        let mut warnings = Vec::new();
+
+        // sorted so warnings and conflicts come out the same every run
+        let mut entries: Vec<_> = keys.iter().collect();
+        entries.sort();
+
+        for (name, spec) in entries {
+            let Some(action) = KeyAction::from_name(name) else {
+                warnings.push(format!("keys: unknown action '{name}', ignored"));
+                continue;
+            };
+
+            let parsed: Result<Vec<KeyBinding>, String> = spec
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(KeyBinding::parse)
+                .collect();
+
+            match parsed {
+                Ok(bindings) if !bindings.is_empty() => {
+                    map.bindings.retain(|_, a| *a != action);
+                    for binding in bindings {
+                        map.bindings.insert(binding, action);
+                    }
+                }
+                Ok(_) => warnings.push(format!("keys: no key given for '{name}', using default")),
+                Err(e) => warnings.push(format!("keys: {name} = \"{spec}\": {e}, using default")),
+            }
+        }
+
+        (map, warnings)
+    }
+
+    pub fn lookup(&self, key: &KeyEvent) -> Option<KeyAction> {
+        self.bindings.get(&KeyBinding::from(key)).copied()
+    }
+
+    /// every key bound to `action`, plain keys first
+    pub fn keys_for(&self, action: KeyAction) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(binding, _)| binding.to_string())
+            .collect();
+        keys.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        keys
+    }
+
+    /// the help popup: normal mode from this map, the fixed keys from the tables below
+    pub fn help(&self) -> Vec<HelpSection> {
+        let normal = KeyAction::ALL
+            .into_iter()
+            .filter_map(|action| {
+                let keys = self.keys_for(action);
+                (!keys.is_empty()).then(|| (keys.join(", "), action.description()))
+            })
+            .collect();
+
+        let mut sections = vec![HelpSection {
+            title: "normal mode",
+            entries: normal,
+        }];
+        for (title, entries) in FIXED_KEYS {
+            sections.push(HelpSection {
+                title,
+                entries: entries
+                    .iter()
+                    .map(|(keys, what)| (keys.to_string(), *what))
+                    .collect(),
+            });
+        }
+        sections
+    }
+}
+
+/// a titled group of keys in the help popup
+#[derive(Debug, Clone)]
+pub struct HelpSection {
+    pub title: &'static str,
+    /// (keys, what they do)
+    pub entries: Vec<(String, &'static str)>,
+}
+
+// keys that can't be remapped, keep in step with the handlers in event.rs
+const FIXED_KEYS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "insert mode",
+        &[
+            ("esc", "back to normal mode"),
+            ("enter", "run the prompt"),
+            ("shift+enter, ctrl+enter", "new line"),
+            ("left, right, home, end", "move the cursor"),
+            ("ctrl+a, ctrl+e", "start / end of the prompt"),
+            ("ctrl+u", "clear the prompt"),
+            ("up, down, ctrl+p, ctrl+n", "prompt history"),
+        ],
+    ),
+    ("confirm", &[("y", "run the sql"), ("n, esc", "cancel")]),
+    (
+        "tables",
+        &[
+            ("j, k, down, up", "move"),
+            ("space", "check / uncheck"),
+            ("a", "check all / none"),
+            ("enter", "use the checked tables"),
+            ("esc, q", "cancel"),
+        ],
+    ),
+    (
+        "candidates",
+        &[
+            ("j, k, down, up", "move"),
+            ("enter", "run the picked sql"),
+            ("esc, q", "cancel"),
+        ],
+    ),
+    (
+        "compare",
+        &[
+            ("h, l, left, right, tab", "pick a side"),
+            ("enter", "run the picked sql"),
+            ("esc, q", "cancel"),
+        ],
+    ),
+    (
+        "themes",
+        &[
+            ("j, k, down, up", "preview"),
+            ("enter", "keep the theme"),
+            ("esc, q", "close"),
+        ],
+    ),
+    (
+        "connection and command line",
+        &[
+            ("enter", "connect / run the command"),
+            ("ctrl+u", "clear the input"),
+            ("esc", "close"),
+        ],
+    ),
+    (
+        "help",
+        &[("j, k, down, up", "scroll"), ("esc, q, ?", "close")],
+    ),
+];
@@ -3,11 +3,21 @@
 mod app;
 mod ascii;
 mod event;
+mod history;
+mod keys;
+mod report;
 mod theme;
 mod ui;
 
-pub use app::{App, DbInfo};
+pub use app::{
+    App, CompareSide, Comparison, DEFAULT_MAX_PROMPT_CHARS, DbInfo, ExplainState, Panel,
+    PendingSql, Popup, ProfileEntry, PromptSize, RiskLevel, SESSION_ROWS, SessionEntry,
+    validate_url,
+};
+pub use history::{DEFAULT_HISTORY_SIZE, history_path};
+pub use keys::{HelpSection, KeyAction, KeyBinding, KeyMap};
 pub use theme::ThemeKind;
+pub use ui::render;
 
 use crossterm::{
     cursor::SetCursorStyle,
@@ -15,12 +25,15 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
+use std::collections::HashMap;
 use std::io::{self, stdout};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
-use crate::{Ai, Db, Error, Provider};
+use crate::core::{CsvOptions, ResultCache, quote_reserved as quote_reserved_words};
+use crate::{Ai, AiOptions, Db, DbConfig, Error, Provider, Safety, SqlGenerator};
 use app::{LogLevel, Mode};
 use event::{Action, handle_event, poll_event};
 
@@ -31,9 +44,10 @@ fn copy_to_clipboard(text: &str) -> bool {
     // try pbcopy (macOS)
     if let Ok(mut child) = Command::new("pbcopy").stdin(Stdio::piped()).spawn()
         && let Some(stdin) = child.stdin.as_mut()
-            && stdin.write_all(text.as_bytes()).is_ok() {
-                return child.wait().map(|s| s.success()).unwrap_or(false);
-            }
+        && stdin.write_all(text.as_bytes()).is_ok()
+    {
+        return child.wait().map(|s| s.success()).unwrap_or(false);
+    }
 
     // try xclip (Linux)
     if let Ok(mut child) = Command::new("xclip")
@@ -41,9 +55,10 @@ fn copy_to_clipboard(text: &str) -> bool {
         .stdin(Stdio::piped())
         .spawn()
         && let Some(stdin) = child.stdin.as_mut()
-            && stdin.write_all(text.as_bytes()).is_ok() {
-                return child.wait().map(|s| s.success()).unwrap_or(false);
-            }
+        && stdin.write_all(text.as_bytes()).is_ok()
+    {
+        return child.wait().map(|s| s.success()).unwrap_or(false);
+    }
 
     // try xsel (Linux fallback)
     if let Ok(mut child) = Command::new("xsel")
@@ -51,20 +66,282 @@ fn copy_to_clipboard(text: &str) -> bool {
         .stdin(Stdio::piped())
         .spawn()
         && let Some(stdin) = child.stdin.as_mut()
-            && stdin.write_all(text.as_bytes()).is_ok() {
-                return child.wait().map(|s| s.success()).unwrap_or(false);
-            }
+        && stdin.write_all(text.as_bytes()).is_ok()
+    {
+        return child.wait().map(|s| s.success()).unwrap_or(false);
+    }
 
     false
 }
 
+// event poll timeouts: short while waiting on background work, long when idle
+const BUSY_POLL: Duration = Duration::from_millis(100);
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// startup settings from the command line
+pub struct Settings {
+    pub confirm: bool,
+    pub confirm_all: bool,
+    pub cache: bool,
+    /// --db-schema, kept for reconnects
+    pub db_schema: Option<String>,
+    /// --db-timeout and --max-connections, kept for reconnects
+    pub db_config: DbConfig,
+    pub max_prompt_chars: usize,
+    /// --history-size, 0 keeps prompts for this session only
+    pub history_size: usize,
+    /// --interactive-schema, open the table picker once connected
+    pub interactive_schema: bool,
+    /// --db-ping-interval, `None` leaves idle connections alone
+    pub db_ping_interval: Option<Duration>,
+    pub quote_reserved: bool,
+    /// --read-only
+    pub read_only: bool,
+    /// --multi-statement
+    pub multi_statement: bool,
+    /// --limit, `None` with --no-limit
+    pub limit: Option<u64>,
+    pub csv: CsvOptions,
+    pub keys: HashMap<String, String>,
+    /// saved connections offered first in setup
+    pub profiles: Vec<ProfileEntry>,
+    pub provider: Provider,
+    pub api_key: Option<String>,
+    pub ai_options: AiOptions,
+    /// --compare: a second provider asked alongside
+    pub compare: Option<Provider>,
+    /// the config's key for --compare, its env var otherwise
+    pub compare_api_key: Option<String>,
+    /// --compare-model, the compare provider's default otherwise
+    pub compare_model: Option<String>,
+}
+
+/// run sql, answering repeat reads from the cache when it's on; a write
+/// empties the cache so no read after it gets old rows.
+/// true when it failed on a missing table, the schema may be stale
+pub async fn run_sql(app: &mut App, db: &Db, cache: &mut ResultCache, sql: &str) -> bool {
+    if app.read_only
+        && let Some(reason) = Safety::read_only_refusal(sql)
+    {
+        app.set_sql_error(reason);
+        return false;
+    }
+
+    let cacheable = app.cache_enabled && Safety::is_single_read(sql);
+
+    if cacheable && let Some(result) = cache.get(sql) {
+        app.set_result(result);
+        app.log(LogLevel::Info, "served from cache".to_string());
+        return false;
+    }
+
+    match db.execute_statements(sql, app.multi_statement).await {
+        Ok(result) => {
+            if cacheable {
+                cache.put(sql, result.clone());
+            } else if !Safety::is_read_only(sql) {
+                cache.clear();
+            }
+            app.set_result(result);
+            false
+        }
+        Err(e) => {
+            app.set_sql_error(e.to_string());
+            e.is_missing_table()
+        }
+    }
+}
+
+// a fresh connection with --db-schema applied
+async fn connect(url: &str, db_schema: Option<&str>, config: &DbConfig) -> Result<Db, Error> {
+    let db = Db::connect_with(url, config).await?;
+    match db_schema {
+        Some(name) => db.with_db_schema(name),
+        None => Ok(db),
+    }
+}
+
+/// load the schema again into `app.schema`, true when it changed
+pub async fn refresh_schema(app: &mut App, db: &Db) -> bool {
+    match db.schema().await {
+        Ok(schema) if schema != app.schema => {
+            let tables = schema.matches("TABLE ").count();
+            app.log(
+                LogLevel::Info,
+                format!("schema was stale, refreshed ({tables} tables)"),
+            );
+            app.schema = schema;
+            true
+        }
+        Ok(_) => false,
+        Err(e) => {
+            app.log(LogLevel::Warn, format!("schema refresh failed: {e}"));
+            false
+        }
+    }
+}
+
+/// turn a submitted prompt into sql and update the app for it
+///
+/// returns the sql when it should run right away; candidates, confirmation,
+/// clarification and errors end up in `app` instead
+pub async fn submit_prompt(
+    app: &mut App,
+    generator: &dyn SqlGenerator,
+    query: &str,
+    schema: &str,
+    quote_reserved: bool,
+) -> Option<String> {
+    submit_prompt_streaming(app, generator, query, schema, quote_reserved, &mut |_| {}).await
+}
+
+/// `submit_prompt`, calling `redraw` each time more of the answer streams in
+pub async fn submit_prompt_streaming(
+    app: &mut App,
+    generator: &dyn SqlGenerator,
+    query: &str,
+    schema: &str,
+    quote_reserved: bool,
+    redraw: &mut dyn FnMut(&mut App),
+) -> Option<String> {
+    let before = generator.session_usage();
+    let (chunks, mut streamed) = tokio::sync::mpsc::unbounded_channel();
+    app.partial_sql.clear();
+    let generation = generator.generate_candidates_streaming(query, schema, chunks);
+    tokio::pin!(generation);
+    let generated = loop {
+        tokio::select! {
+            // text that's already in goes on screen before the answer is checked
+            biased;
+            Some(text) = streamed.recv() => {
+                app.append_partial_sql(&text);
+                redraw(app);
+            }
+            generated = &mut generation => break generated,
+        }
+    };
+    app.partial_sql.clear();
+    app.record_usage(before, generator.session_usage());
+
+    let generated = generated.map(|mut candidates| {
+        if quote_reserved {
+            for sql in &mut candidates {
+                *sql = quote_reserved_words(sql, schema, &app.db_info.dialect);
+            }
+        }
+        if let Some(limit) = app.row_limit {
+            let mut added = false;
+            for sql in &mut candidates {
+                if let Some(limited) = Safety::with_limit(sql, limit) {
+                    *sql = limited;
+                    added = true;
+                }
+            }
+            if added {
+                app.log(
+                    LogLevel::Info,
+                    format!("no LIMIT in the sql, added LIMIT {limit} (--no-limit to keep it out)"),
+                );
+            }
+        }
+        candidates
+    });
+
+    match generated {
+        Ok(candidates) if candidates.is_empty() => {
+            app.set_error("the model returned no sql".to_string());
+            None
+        }
+        Ok(candidates) if candidates.len() > 1 => {
+            app.show_candidates(candidates);
+            None
+        }
+        Ok(mut candidates) => {
+            let sql = candidates.remove(0);
+            app.set_sql(sql.clone());
+            app.confidence = generator.last_confidence();
+
+            if app.needs_confirm(&sql) {
+                // show confirmation popup
+                app.loading = false;
+                app.show_confirm(sql);
+                None
+            } else {
+                Some(sql)
+            }
+        }
+        Err(Error::NeedsClarification(question)) => {
+            app.set_clarification(question, query.to_string());
+            None
+        }
+        Err(e) => {
+            app.set_error(e.to_string());
+            None
+        }
+    }
+}
+
+/// compare mode: ask both generators for the same prompt and show their sql side by side
+///
+/// `labels` name the generators in the panel; only the first one's tokens are counted
+pub async fn compare_prompt(
+    app: &mut App,
+    generators: [&dyn SqlGenerator; 2],
+    labels: [String; 2],
+    query: &str,
+    schema: &str,
+    quote_reserved: bool,
+) {
+    let before = generators[0].session_usage();
+    let (left, right) = tokio::join!(
+        generators[0].generate_sql(query, schema),
+        generators[1].generate_sql(query, schema),
+    );
+    app.record_usage(before, generators[0].session_usage());
+
+    let [left, right] = [left, right].map(|generated| match generated {
+        Ok(sql) if quote_reserved => Ok(quote_reserved_words(&sql, schema, &app.db_info.dialect)),
+        Ok(sql) => Ok(sql),
+        Err(Error::NeedsClarification(question)) => Err(format!("model asks: {question}")),
+        Err(e) => Err(e.to_string()),
+    });
+    let [left_label, right_label] = labels;
+    app.show_comparison(
+        CompareSide {
+            label: left_label,
+            sql: left,
+        },
+        CompareSide {
+            label: right_label,
+            sql: right,
+        },
+    );
+}
+
+// EXPLAIN the current sql into app.explain
+async fn run_explain(app: &mut App, db: &Db) {
+    let Some(sql) = &app.sql else {
+        return;
+    };
+    // EXPLAIN runs what follows it on some databases, the same rule as run_sql
+    if app.read_only
+        && let Some(reason) = Safety::read_only_refusal(sql)
+    {
+        app.explain = ExplainState::Failed(reason);
+        return;
+    }
+
+    app.explain = match db.explain_lines(sql).await {
+        Ok(lines) => ExplainState::Ready(lines.join("\n")),
+        Err(e) => ExplainState::Failed(e.to_string()),
+    };
+}
+
 pub async fn run(
     db: Option<Db>,
     schema: Option<String>,
     db_info: Option<DbInfo>,
-    confirm: bool,
-    provider: Provider,
-    api_key: Option<String>,
+    settings: Settings,
 ) -> Result<(), Error> {
     // setup terminal
     enable_raw_mode().map_err(|e| Error::Server(e.to_string()))?;
@@ -74,7 +351,7 @@ pub async fn run(
     let mut terminal = Terminal::new(backend).map_err(|e| Error::Server(e.to_string()))?;
 
     // run app
-    let result = run_app(&mut terminal, db, schema, db_info, confirm, provider, api_key).await;
+    let result = run_app(&mut terminal, db, schema, db_info, settings).await;
 
     // restore terminal
     disable_raw_mode().ok();
@@ -94,16 +371,39 @@ async fn run_app(
     db: Option<Db>,
     schema: Option<String>,
     db_info: Option<DbInfo>,
-    confirm: bool,
-    provider: Provider,
-    api_key: Option<String>,
+    settings: Settings,
 ) -> Result<(), Error> {
+    let Settings {
+        confirm,
+        confirm_all,
+        cache,
+        db_schema,
+        db_config,
+        max_prompt_chars,
+        history_size,
+        interactive_schema,
+        db_ping_interval,
+        quote_reserved,
+        read_only,
+        multi_statement,
+        limit,
+        csv,
+        keys,
+        profiles,
+        provider,
+        api_key,
+        ai_options,
+        compare,
+        compare_api_key,
+        compare_model,
+    } = settings;
+
     // determine if we're in setup mode
     let setup_mode = db.is_none();
 
     // create app state
     let mut app = if setup_mode {
-        App::new_setup()
+        App::new_setup_with_profiles(profiles)
     } else {
         App::new(
             schema.clone().unwrap_or_default(),
@@ -113,18 +413,103 @@ async fn run_app(
     };
 
     // these will be initialized after setup or immediately if db provided
-    let mut ai: Option<Ai> = if !setup_mode {
-        Some(Ai::new(provider, api_key.clone())?)
-    } else {
-        None
-    };
+    // a missing key shouldn't stop the tui, it just leaves the ai not ready
+    let mut ai: Option<Ai> = None;
+    if !setup_mode {
+        match Ai::new(provider, api_key.clone()) {
+            Ok(ai_client) => {
+                let ai_client = ai_client.with_options(ai_options.clone());
+                app.agent_info.model = ai_client.model().to_string();
+                ai = Some(ai_client);
+                app.ai_ready = true;
+            }
+            Err(e) => app.set_ai_unavailable(e.to_string()),
+        }
+    }
+
+    // --compare's client only needs its key from the env, setup or not
+    let mut compare_ai: Option<Ai> = None;
+    if let Some(compare) = compare {
+        let options = AiOptions {
+            model: compare_model,
+            candidates: false,
+            ..ai_options.clone()
+        };
+        match Ai::new(compare, compare_api_key) {
+            Ok(client) => {
+                let client = client.with_options(options);
+                app.compare_label = Some(format!("{compare} ({})", client.model()));
+                app.compare_enabled = true;
+                compare_ai = Some(client);
+            }
+            Err(e) => app.log(LogLevel::Warn, format!("compare unavailable: {e}")),
+        }
+    }
+
+    app.confirm_all = confirm_all;
+    app.read_only = read_only;
+    app.multi_statement = multi_statement;
+    app.row_limit = limit;
+    app.cache_enabled = cache;
+    app.max_prompt_chars = max_prompt_chars;
+    if history_size > 0
+        && let Some(path) = history_path()
+    {
+        app.load_history(path, history_size);
+    }
+    app.csv_options = csv;
+
+    let (keymap, warnings) = KeyMap::from_config(&keys);
+    app.keymap = keymap;
+    for warning in warnings {
+        app.log(LogLevel::Warn, warning);
+    }
+    let mut result_cache = ResultCache::default();
+    if interactive_schema && !setup_mode {
+        app.open_table_picker();
+    }
 
     let db_arc: Arc<Mutex<Option<Db>>> = Arc::new(Mutex::new(db));
     let mut current_schema = schema.unwrap_or_default();
 
     let mut last_mode = app.mode;
+    // only draw when something changed; nothing animates, so idle means no redraws
+    let mut dirty = true;
+
+    // keepalive pings, one at a time and never awaited while still running
+    let mut last_ping = Instant::now();
+    let mut ping: Option<JoinHandle<Result<(), Error>>> = None;
+    let mut ping_failed = false;
 
     loop {
+        if let Some(interval) = db_ping_interval {
+            if ping.as_ref().is_some_and(|p| p.is_finished())
+                && let Some(done) = ping.take()
+            {
+                dirty = true;
+                match done.await {
+                    Ok(Ok(())) if ping_failed => {
+                        ping_failed = false;
+                        app.log(LogLevel::Ok, "database reachable again".to_string());
+                    }
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        ping_failed = true;
+                        app.log(LogLevel::Error, format!("database ping failed: {e}"));
+                    }
+                    Err(e) => app.log(LogLevel::Error, format!("database ping failed: {e}")),
+                }
+            }
+            if ping.is_none()
+                && last_ping.elapsed() >= interval
+                && let Ok(guard) = db_arc.try_lock()
+                && let Some(db) = &*guard
+            {
+                ping = Some(tokio::spawn(db.ping()));
+                last_ping = Instant::now();
+            }
+        }
+
         // update cursor style before render
         if app.mode != last_mode {
             let cursor_style = match app.mode {
@@ -136,14 +521,19 @@ async fn run_app(
         }
 
         // render (cursor position is set in ui::render when in insert mode)
-        terminal
-            .draw(|frame| ui::render(frame, &mut app))
-            .map_err(|e| Error::Server(e.to_string()))?;
-
-        // poll events
-        if let Some(event) =
-            poll_event(Duration::from_millis(100)).map_err(|e| Error::Server(e.to_string()))?
-        {
+        if dirty {
+            terminal
+                .draw(|frame| ui::render(frame, &mut app))
+                .map_err(|e| Error::Server(e.to_string()))?;
+            dirty = false;
+        }
+
+        // poll returns as soon as a key arrives, the timeout only bounds how
+        // long background work (a ping in flight) waits to be noticed
+        let timeout = if ping.is_some() { BUSY_POLL } else { IDLE_POLL };
+        if let Some(event) = poll_event(timeout).map_err(|e| Error::Server(e.to_string()))? {
+            // keys, resizes and focus changes can all change what's on screen
+            dirty = true;
             match handle_event(&mut app, event) {
                 Action::Quit => break,
                 Action::Submit(query) => {
@@ -160,32 +550,118 @@ async fn run_app(
                             .draw(|frame| ui::render(frame, &mut app))
                             .map_err(|e| Error::Server(e.to_string()))?;
 
-                        // generate sql
-                        match ai_client.generate_sql(&query, &current_schema).await {
-                            Ok(sql) => {
-                                app.set_sql(sql.clone());
-
-                                if app.confirm_before_run {
-                                    // show confirmation popup
-                                    app.loading = false;
-                                    app.show_confirm(sql);
-                                } else {
-                                    // execute directly
-                                    terminal
-                                        .draw(|frame| ui::render(frame, &mut app))
-                                        .map_err(|e| Error::Server(e.to_string()))?;
-
-                                    let db_guard = db_arc.lock().await;
-                                    if let Some(ref db_conn) = *db_guard {
-                                        match db_conn.execute(&sql).await {
-                                            Ok(result) => app.set_result(result),
-                                            Err(e) => app.set_error(e.to_string()),
-                                        }
-                                    }
+                        let schema = app.scoped_schema(&current_schema);
+                        if app.compare_enabled
+                            && let Some(compare_client) = &compare_ai
+                        {
+                            // nothing runs until a side is picked
+                            let labels = [
+                                format!("{} ({})", ai_client.provider(), ai_client.model()),
+                                app.compare_label.clone().unwrap_or_default(),
+                            ];
+                            compare_prompt(
+                                &mut app,
+                                [ai_client, compare_client],
+                                labels,
+                                &query,
+                                &schema,
+                                quote_reserved,
+                            )
+                            .await;
+                            continue;
+                        }
+                        // the sql panel fills in as the model writes
+                        let run_now = submit_prompt_streaming(
+                            &mut app,
+                            ai_client,
+                            &query,
+                            &schema,
+                            quote_reserved,
+                            &mut |app| {
+                                let _ = terminal.draw(|frame| ui::render(frame, app));
+                            },
+                        )
+                        .await;
+
+                        if let Some(sql) = run_now {
+                            // execute directly
+                            terminal
+                                .draw(|frame| ui::render(frame, &mut app))
+                                .map_err(|e| Error::Server(e.to_string()))?;
+
+                            let db_guard = db_arc.lock().await;
+                            if let Some(ref db_conn) = *db_guard
+                                && run_sql(&mut app, db_conn, &mut result_cache, &sql).await
+                                && refresh_schema(&mut app, db_conn).await
+                            {
+                                // one more go against the tables as they are now
+                                current_schema = app.schema.clone();
+                                app.log(LogLevel::Info, "generating again".to_string());
+                                let schema = app.scoped_schema(&current_schema);
+                                let retry = submit_prompt(
+                                    &mut app,
+                                    ai_client,
+                                    &query,
+                                    &schema,
+                                    quote_reserved,
+                                )
+                                .await;
+                                if let Some(sql) = retry {
+                                    run_sql(&mut app, db_conn, &mut result_cache, &sql).await;
                                 }
                             }
-                            Err(e) => app.set_error(e.to_string()),
                         }
+                    } else {
+                        app.block_submit(query);
+                    }
+                }
+                Action::PickCandidate(sql) => {
+                    app.set_sql(sql.clone());
+
+                    if app.needs_confirm(&sql) {
+                        app.show_confirm(sql);
+                    } else {
+                        app.loading = true;
+                        terminal
+                            .draw(|frame| ui::render(frame, &mut app))
+                            .map_err(|e| Error::Server(e.to_string()))?;
+
+                        let db_guard = db_arc.lock().await;
+                        if let Some(ref db_conn) = *db_guard
+                            && run_sql(&mut app, db_conn, &mut result_cache, &sql).await
+                            && refresh_schema(&mut app, db_conn).await
+                        {
+                            current_schema = app.schema.clone();
+                        }
+                    }
+                }
+                Action::SaveTable(request) => {
+                    let Some(result) = app.result.clone() else {
+                        app.log(LogLevel::Warn, "no results to save".to_string());
+                        continue;
+                    };
+
+                    let saved = match &request.out {
+                        Some(path) => match Db::open_sqlite(path).await {
+                            Ok(out) => out.save_table(&request.table, &result).await,
+                            Err(e) => Err(e),
+                        },
+                        // a new table in the database is still a write
+                        None if app.read_only => Err(Error::Blocked(
+                            "read-only mode: save to a separate file.db instead".to_string(),
+                        )),
+                        None => match &*db_arc.lock().await {
+                            Some(db_conn) => db_conn.save_table(&request.table, &result).await,
+                            None => Err(Error::Config("not connected".to_string())),
+                        },
+                    };
+
+                    match saved {
+                        Ok(()) => app.log(
+                            LogLevel::Ok,
+                            format!("saved {} rows to table {}", result.row_count, request.table),
+                        ),
+                        Err(e) => app.log(LogLevel::Error, format!("save failed: {e}")),
                     }
                 }
                 Action::ConfirmSql => {
@@ -199,49 +675,55 @@ async fn run_app(
 
                         // execute
                         let db_guard = db_arc.lock().await;
-                        if let Some(ref db_conn) = *db_guard {
-                            match db_conn.execute(&sql).await {
-                                Ok(result) => app.set_result(result),
-                                Err(e) => app.set_error(e.to_string()),
-                            }
+                        if let Some(ref db_conn) = *db_guard
+                            && run_sql(&mut app, db_conn, &mut result_cache, &sql).await
+                            && refresh_schema(&mut app, db_conn).await
+                        {
+                            current_schema = app.schema.clone();
+                        }
+                    }
+                }
+                Action::RerunSql => {
+                    let Some(sql) = app.sql.clone() else {
+                        app.log(LogLevel::Warn, "no sql to re-run".to_string());
+                        continue;
+                    };
+                    // a yes counts once, running it again asks again
+                    if app.needs_confirm(&sql) {
+                        app.show_confirm(sql);
+                    } else {
+                        app.loading = true;
+                        app.query_start = Some(std::time::Instant::now());
+
+                        terminal
+                            .draw(|frame| ui::render(frame, &mut app))
+                            .map_err(|e| Error::Server(e.to_string()))?;
+
+                        let db_guard = db_arc.lock().await;
+                        if let Some(ref db_conn) = *db_guard
+                            && run_sql(&mut app, db_conn, &mut result_cache, &sql).await
+                            && refresh_schema(&mut app, db_conn).await
+                        {
+                            current_schema = app.schema.clone();
                         }
                     }
                 }
+                Action::ToggleCache => {
+                    app.cache_enabled = !app.cache_enabled;
+                    result_cache.clear();
+                    let state = if app.cache_enabled { "on" } else { "off" };
+                    app.log(LogLevel::Info, format!("result cache {state}"));
+                }
                 Action::CancelSql => {
                     app.log(LogLevel::Info, "query cancelled".to_string());
                 }
-                Action::ToggleExplain => {
-                    // run EXPLAIN if we have SQL and toggled to show explain
-                    if app.show_explain && app.explain_result.is_none() {
-                        if let Some(sql) = &app.sql {
-                            let explain_sql = format!("EXPLAIN {}", sql);
-                            let db_guard = db_arc.lock().await;
-                            if let Some(ref db_conn) = *db_guard {
-                                match db_conn.execute(&explain_sql).await {
-                                    Ok(result) => {
-                                        // format explain result as text
-                                        let explain_text = result
-                                            .rows
-                                            .iter()
-                                            .map(|row| {
-                                                row.iter()
-                                                    .map(|v| match v {
-                                                        serde_json::Value::String(s) => s.clone(),
-                                                        _ => v.to_string(),
-                                                    })
-                                                    .collect::<Vec<_>>()
-                                                    .join(" | ")
-                                            })
-                                            .collect::<Vec<_>>()
-                                            .join("\n");
-                                        app.explain_result = Some(explain_text);
-                                    }
-                                    Err(e) => {
-                                        app.explain_result = Some(format!("EXPLAIN failed: {}", e));
-                                    }
-                                }
-                            }
-                        }
+                Action::ToggleExplain | Action::RefreshExplain => {
+                    // only a fresh plan is reused, stale or failed ones run again
+                    if app.show_explain
+                        && app.explain.needs_run()
+                        && let Some(db_conn) = &*db_arc.lock().await
+                    {
+                        run_explain(&mut app, db_conn).await;
                     }
                 }
                 Action::CopySql => {
@@ -274,6 +756,10 @@ async fn run_app(
                             chrono::Local::now().format("%Y%m%d_%H%M%S")
                         );
                         match std::fs::write(&filename, &csv) {
+                            Ok(_) if app.result.as_ref().is_some_and(|r| r.truncated) => app.log(
+                                LogLevel::Warn,
+                                format!("exported to {} (truncated result)", filename),
+                            ),
                             Ok(_) => app.log(LogLevel::Ok, format!("exported to {}", filename)),
                             Err(e) => app.log(LogLevel::Error, format!("export failed: {}", e)),
                         }
@@ -281,6 +767,26 @@ async fn run_app(
                         app.log(LogLevel::Warn, "no results to export".to_string());
                     }
                 }
+                Action::ExportSession => {
+                    if let Some(report) = app.session_report() {
+                        let filename = format!(
+                            "nlql_session_{}.md",
+                            chrono::Local::now().format("%Y%m%d_%H%M%S")
+                        );
+                        match std::fs::write(&filename, &report) {
+                            Ok(_) => app.log(
+                                LogLevel::Ok,
+                                format!(
+                                    "session ({} queries) written to {filename}",
+                                    app.session.len()
+                                ),
+                            ),
+                            Err(e) => app.log(LogLevel::Error, format!("export failed: {e}")),
+                        }
+                    } else {
+                        app.log(LogLevel::Warn, "nothing run yet this session".to_string());
+                    }
+                }
                 Action::Reconnect(url) => {
                     app.reconnecting = true;
                     app.log(LogLevel::Info, "reconnecting...".to_string());
@@ -291,7 +797,7 @@ async fn run_app(
                         .map_err(|e| Error::Server(e.to_string()))?;
 
                     // try to connect
-                    match Db::connect(&url).await {
+                    match connect(&url, db_schema.as_deref(), &db_config).await {
                         Ok(new_db) => match new_db.schema().await {
                             Ok(new_schema) => {
                                 let tables = new_schema.matches("TABLE ").count();
@@ -303,8 +809,12 @@ async fn run_app(
                                     url: url.clone(),
                                 };
                                 current_schema = new_schema.clone();
+                                result_cache.clear();
                                 app.update_db_info(new_info, new_schema);
                                 *db_arc.lock().await = Some(new_db);
+                                if interactive_schema {
+                                    app.open_table_picker();
+                                }
                             }
                             Err(e) => app.set_error(format!("schema error: {e}")),
                         },
@@ -321,7 +831,7 @@ async fn run_app(
                         .map_err(|e| Error::Server(e.to_string()))?;
 
                     // try to connect
-                    match Db::connect(&url).await {
+                    match connect(&url, db_schema.as_deref(), &db_config).await {
                         Ok(new_db) => match new_db.schema().await {
                             Ok(new_schema) => {
                                 let tables = new_schema.matches("TABLE ").count();
@@ -359,13 +869,21 @@ async fn run_app(
                     api_key: setup_api_key,
                 } => {
                     // initialize AI client
-                    let api_key_from_env = setup_api_key.is_none();
+                    let api_key_from_env =
+                        setup_api_key.is_none() && setup_provider != Provider::Ollama;
                     match Ai::new(setup_provider, setup_api_key) {
                         Ok(ai_client) => {
+                            let ai_client = ai_client.with_options(ai_options.clone());
+                            app.agent_info.model = ai_client.model().to_string();
                             ai = Some(ai_client);
+                            app.ai_ready = true;
+                            app.ai_error = None;
                             // finish setup and enter normal mode
                             app.finish_setup(app.db_info.clone(), &current_schema);
                             app.confirm_before_run = confirm;
+                            if interactive_schema {
+                                app.open_table_picker();
+                            }
                             if api_key_from_env {
                                 app.log(
                                     LogLevel::Info,
@@ -0,0 +1,83 @@
+// markdown report of everything run in a session
+
+use super::app::App;
+use crate::core::QueryResult;
+
+impl App {
+    /// every prompt, its sql and the result (or error) so far, `None` before anything ran
+    pub fn session_report(&self) -> Option<String> {
+        if self.session.is_empty() {
+            return None;
+        }
+
+        let mut out = format!(
+            "# nlql session\n\n{} · {} · {}\n",
+            self.db_info.dialect,
+            self.db_info.database,
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        );
+
+        for (i, entry) in self.session.iter().enumerate() {
+            let title = match &entry.prompt {
+                Some(prompt) => one_line(prompt),
+                None => "sql".to_string(),
+            };
+            out.push_str(&format!("\n## {}. {title}\n\n", i + 1));
+            out.push_str(&format!("```sql\n{}\n```\n\n", entry.sql.trim()));
+
+            match &entry.outcome {
+                Ok(result) if result.rows.is_empty() => {
+                    let status = result.status(&entry.sql).unwrap_or_default();
+                    out.push_str(&format!("_{status}_\n"));
+                }
+                Ok(result) => out.push_str(&table(result)),
+                Err(e) => out.push_str(&format!("**failed:** {}\n", one_line(e))),
+            }
+        }
+        Some(out)
+    }
+}
+
+// a markdown table plus a note when rows were left out
+fn table(result: &QueryResult) -> String {
+    let mut out = String::new();
+    let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+
+    out.push_str(&row(result.columns.iter().map(|c| cell(c)).collect()));
+    out.push_str(&row(result
+        .columns
+        .iter()
+        .map(|_| "---".to_string())
+        .collect()));
+    for values in &result.rows {
+        out.push_str(&row(values
+            .iter()
+            .map(|v| match v {
+                serde_json::Value::Null => String::new(),
+                serde_json::Value::String(s) => cell(s),
+                other => cell(&other.to_string()),
+            })
+            .collect()));
+    }
+
+    let rows = if result.row_count == 1 { "row" } else { "rows" };
+    if result.rows.len() < result.row_count {
+        out.push_str(&format!(
+            "\n_first {} of {} {rows}_\n",
+            result.rows.len(),
+            result.row_count
+        ));
+    } else {
+        out.push_str(&format!("\n_{} {rows}_\n", result.row_count));
+    }
+    out
+}
+
+// pipes would end the cell and newlines the row
+fn cell(s: &str) -> String {
+    one_line(s).replace('|', "\\|")
+}
+
+fn one_line(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -7,7 +7,7 @@ use ratatui::style::{Color, Modifier, Style};
 pub fn detect_theme() -> ThemeKind {
     match terminal_light::luma() {
         Ok(luma) if luma > 0.5 => ThemeKind::RosePineDawn, // light terminal
-        _ => ThemeKind::CatppuccinMocha,                    // dark terminal (default)
+        _ => ThemeKind::CatppuccinMocha,                   // dark terminal (default)
     }
 }
 
@@ -7,15 +7,20 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
 };
 
-use crate::tui::app::{App, LogLevel, Mode, Panel, Popup, RiskLevel};
+use crate::extract_sql;
+use crate::tui::app::{
+    App, ExplainState, LogFilter, LogLevel, Mode, Panel, Popup, PromptSize, RiskLevel,
+    SETUP_PROVIDERS,
+};
 use crate::tui::ascii::NLQL_LOGO;
 use crate::tui::theme::ThemeKind;
+use crate::util::{display_width, pad, truncate};
 
 pub fn render(frame: &mut Frame, app: &mut App) {
     let theme = &app.theme;
 
-    // clear with bg color
-    frame.render_widget(Clear, frame.area());
+    // ratatui starts every frame from an empty buffer and only sends the cells
+    // that changed, so no full Clear here (it flickered); popups clear their own area
     frame.render_widget(Block::default().style(theme.base()), frame.area());
 
     // main layout: header + content + footer
@@ -37,10 +42,18 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         Popup::Themes => render_theme_popup(frame, app),
         Popup::Confirm => render_confirm_popup(frame, app),
         Popup::Connection => render_connection_popup(frame, app),
+        Popup::SetupProfile => render_setup_profile_popup(frame, app),
         Popup::SetupDbType => render_setup_db_type_popup(frame, app),
         Popup::SetupDbDetails => render_setup_db_details_popup(frame, app),
         Popup::SetupProvider => render_setup_provider_popup(frame, app),
         Popup::SetupApiKey => render_setup_api_key_popup(frame, app),
+        Popup::Command => render_command_popup(frame, app),
+        Popup::Describe => render_describe_popup(frame, app),
+        Popup::Candidates => render_candidates_popup(frame, app),
+        Popup::Help => render_help_popup(frame, app),
+        Popup::Tables => render_tables_popup(frame, app),
+        // the comparison replaces the sql panel instead
+        Popup::Compare => {}
         Popup::None => {}
     }
 }
@@ -84,6 +97,26 @@ fn render_header(frame: &mut Frame, app: &mut App, area: Rect) {
         Mode::Insert => "insert",
     };
 
+    // schema and ai readiness are separate: a db can be up with no api key
+    let schema_status = if app.db_info.dialect.is_empty() {
+        Span::styled("not loaded", theme.muted())
+    } else {
+        Span::styled(format!("{} tables", app.db_info.tables), theme.success())
+    };
+    let ai_status = if app.ai_ready {
+        Span::styled("ready", theme.success())
+    } else {
+        Span::styled("not configured", theme.error())
+    };
+
+    let tokens = match (app.last_usage, app.session_usage) {
+        (Some(last), Some(session)) if last != session => {
+            Span::styled(format!("{last} (session {session})"), theme.base())
+        }
+        (_, Some(session)) => Span::styled(session.to_string(), theme.base()),
+        _ => Span::styled("-", theme.muted()),
+    };
+
     let info_lines = vec![
         Line::from(vec![
             Span::styled("| ", theme.muted()),
@@ -103,6 +136,12 @@ fn render_header(frame: &mut Frame, app: &mut App, area: Rect) {
         Line::from(vec![
             Span::styled("| Mode: ", theme.muted()),
             Span::styled(mode_str, theme.accent()),
+            Span::styled("  | Schema: ", theme.muted()),
+            schema_status,
+            Span::styled("  | AI: ", theme.muted()),
+            ai_status,
+            Span::styled("  | Tokens: ", theme.muted()),
+            tokens,
         ]),
         Line::from(vec![
             Span::styled("| ", theme.muted()),
@@ -188,6 +227,9 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
         Span::styled("t ", theme.accent()),
         Span::styled("Theme ", theme.muted()),
         Span::styled("| ", theme.border()),
+        Span::styled("? ", theme.accent()),
+        Span::styled("Help ", theme.muted()),
+        Span::styled("| ", theme.border()),
         Span::styled("q ", theme.accent()),
         Span::styled("Quit ", theme.muted()),
     ]);
@@ -212,8 +254,24 @@ fn render_prompt(frame: &mut Frame, app: &mut App, area: Rect) {
 
     let title = " Prompt (Natural Language) ";
 
+    // size on the right of the border, coloured as it nears --max-prompt-chars
+    let size_style = match app.prompt_size() {
+        PromptSize::Fine => theme.muted(),
+        PromptSize::Near => theme.warning(),
+        PromptSize::Over => theme.error(),
+    };
+    let lines = app.prompt.lines().count().max(1);
+    let size = format!(
+        " {}/{} chars, {lines} {}, ~{} tokens ",
+        app.prompt_chars(),
+        app.max_prompt_chars,
+        if lines == 1 { "line" } else { "lines" },
+        app.prompt_tokens(),
+    );
+
     let block = Block::default()
         .title(Span::styled(title, theme.title()))
+        .title(Line::styled(size, size_style).right_aligned())
         .borders(Borders::ALL)
         .border_style(border_style)
         .style(theme.base());
@@ -274,6 +332,10 @@ fn render_prompt(frame: &mut Frame, app: &mut App, area: Rect) {
 }
 
 fn render_sql(frame: &mut Frame, app: &mut App, area: Rect) {
+    if app.comparison.is_some() {
+        render_comparison(frame, app, area);
+        return;
+    }
     let theme = &app.theme;
     let active = app.panel == Panel::Sql;
 
@@ -301,7 +363,7 @@ fn render_sql(frame: &mut Frame, app: &mut App, area: Rect) {
     ];
 
     // confidence and risk
-    if let Some(confidence) = app.confidence {
+    if app.sql.is_some() {
         let risk_style = match app.risk {
             Some(RiskLevel::Safe) => theme.success(),
             Some(RiskLevel::Moderate) => theme.warning(),
@@ -318,12 +380,25 @@ fn render_sql(frame: &mut Frame, app: &mut App, area: Rect) {
 
         lines.push(Line::from(vec![
             Span::styled("Confidence: ", theme.muted()),
-            Span::styled(format!("{}%", confidence), theme.accent()),
+            match app.confidence {
+                Some(confidence) => Span::styled(format!("{}%", confidence), theme.accent()),
+                None => Span::styled("-", theme.muted()),
+            },
             Span::styled("  | Risk: ", theme.muted()),
             Span::styled(format!("{} ({})", risk_label, sql_type), risk_style),
         ]));
     }
 
+    // what the last generation cost, the header keeps the session total
+    if let Some(usage) = app.last_usage
+        && app.sql.is_some()
+    {
+        lines.push(Line::from(vec![
+            Span::styled("Tokens: ", theme.muted()),
+            Span::styled(usage.to_string(), theme.base()),
+        ]));
+    }
+
     lines.push(Line::styled(
         "-----------------------------------------------",
         theme.border(),
@@ -331,6 +406,21 @@ fn render_sql(frame: &mut Frame, app: &mut App, area: Rect) {
 
     if app.loading {
         lines.push(Line::styled("generating sql...", theme.muted()));
+        // fences and tags come off as soon as they're complete
+        for sql_line in extract_sql(&app.partial_sql).lines() {
+            lines.push(Line::styled(sql_line.to_string(), theme.muted()));
+        }
+    } else if let Some(question) = &app.clarification {
+        lines.push(Line::styled(
+            "the model needs more detail:",
+            theme.warning(),
+        ));
+        lines.push(Line::styled(question.clone(), theme.base()));
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "refine your prompt and press enter again",
+            theme.muted(),
+        ));
     } else if let Some(sql) = &app.sql {
         for sql_line in sql.lines() {
             lines.push(Line::styled(sql_line.to_string(), theme.accent()));
@@ -375,13 +465,19 @@ fn render_results(frame: &mut Frame, app: &mut App, area: Rect) {
         theme.border()
     };
 
-    let title = match &app.result {
-        Some(r) => format!(" Results ({} rows) ", r.row_count),
-        None => " Results ".to_string(),
+    let (title, title_style) = match &app.result {
+        // a statement without a result set has no row count worth showing
+        Some(r) if r.columns.is_empty() => (" Results ".to_string(), theme.title()),
+        Some(r) if r.truncated => (
+            format!(" Results ({} rows, truncated) ", r.row_count),
+            theme.warning(),
+        ),
+        Some(r) => (format!(" Results ({} rows) ", r.row_count), theme.title()),
+        None => (" Results ".to_string(), theme.title()),
     };
 
     let block = Block::default()
-        .title(Span::styled(title, theme.title()))
+        .title(Span::styled(title, title_style))
         .borders(Borders::ALL)
         .border_style(border_style)
         .style(theme.base());
@@ -394,7 +490,7 @@ fn render_results(frame: &mut Frame, app: &mut App, area: Rect) {
     } else if let Some(err) = &app.error {
         vec![Line::styled(format!("error: {err}"), theme.error())]
     } else if let Some(result) = &app.result {
-        format_result(result, theme, available_width)
+        format_result(result, app.sql.as_deref(), theme, available_width)
     } else {
         vec![Line::styled("run a query to see results", theme.muted())]
     };
@@ -418,9 +514,11 @@ fn render_logs(frame: &mut Frame, app: &mut App, area: Rect) {
     };
 
     let title = if app.show_explain {
-        " Explain "
+        " Explain ".to_string()
+    } else if app.log_filter == LogFilter::All {
+        " Logs ".to_string()
     } else {
-        " Logs "
+        format!(" Logs ({}) ", app.log_filter.label())
     };
 
     let block = Block::default()
@@ -430,21 +528,39 @@ fn render_logs(frame: &mut Frame, app: &mut App, area: Rect) {
         .style(theme.base());
 
     let lines: Vec<Line> = if app.show_explain {
-        if let Some(explain) = &app.explain_result {
-            explain
+        match &app.explain {
+            ExplainState::Ready(plan) => plan
                 .lines()
                 .map(|l| Line::styled(l.to_string(), theme.base()))
-                .collect()
-        } else {
-            vec![
+                .collect(),
+            ExplainState::Stale(plan) => {
+                let mut lines = vec![
+                    Line::styled(
+                        "query ran again since, plan may be out of date - (E) to refresh",
+                        theme.warning(),
+                    ),
+                    Line::from(""),
+                ];
+                lines.extend(
+                    plan.lines()
+                        .map(|l| Line::styled(l.to_string(), theme.muted())),
+                );
+                lines
+            }
+            ExplainState::Failed(e) => vec![
+                Line::styled(format!("EXPLAIN failed: {e}"), theme.error()),
+                Line::styled("press (E) to try again", theme.muted()),
+            ],
+            ExplainState::Empty => vec![
                 Line::styled("press (e) to toggle EXPLAIN", theme.muted()),
                 Line::styled("requires executing a query first", theme.muted()),
-            ]
+            ],
         }
     } else {
         let mut log_lines: Vec<Line> = app
             .logs
             .iter()
+            .filter(|entry| app.log_filter.allows(entry.level))
             .map(|entry| {
                 let (prefix, style) = match entry.level {
                     LogLevel::Ok => ("[OK]", theme.success()),
@@ -463,7 +579,9 @@ fn render_logs(frame: &mut Frame, app: &mut App, area: Rect) {
         log_lines.push(Line::from(vec![
             Span::styled("Press ", theme.muted()),
             Span::styled("(e)", theme.accent()),
-            Span::styled(" to toggle EXPLAIN", theme.muted()),
+            Span::styled(" to toggle EXPLAIN, ", theme.muted()),
+            Span::styled("(L)", theme.accent()),
+            Span::styled(" to filter", theme.muted()),
         ]));
 
         log_lines
@@ -542,8 +660,12 @@ fn render_confirm_popup(frame: &mut Frame, app: &mut App) {
     ];
 
     if let Some(sql) = &app.sql {
+        // one giant line shouldn't wrap the y/n prompt out of the popup
+        let inner_width = area.width.saturating_sub(2) as usize;
+        let max_rows = area.height.saturating_sub(7).max(1) as usize;
         for sql_line in sql.lines() {
-            lines.push(Line::styled(sql_line.to_string(), theme.accent()));
+            let line = truncate(sql_line, inner_width * max_rows, "…");
+            lines.push(Line::styled(line, theme.accent()));
         }
     }
 
@@ -612,6 +734,392 @@ fn render_connection_popup(frame: &mut Frame, app: &mut App) {
     }
 }
 
+fn render_command_popup(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let area = centered_rect(60, 20, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(Span::styled(" command ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+
+    let lines = vec![
+        Line::from(vec![
+            Span::styled(":", theme.accent()),
+            Span::raw(&app.command_input),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("describe <table> ", theme.accent()),
+            Span::styled("show columns  ", theme.muted()),
+            Span::styled("save <table> [file.db] ", theme.accent()),
+            Span::styled("save result  ", theme.muted()),
+            Span::styled("sort <column> [desc] ", theme.accent()),
+            Span::styled("sort rows  ", theme.muted()),
+            Span::styled("tables ", theme.accent()),
+            Span::styled("pick tables  ", theme.muted()),
+            Span::styled("compare ", theme.accent()),
+            Span::styled("ask both providers  ", theme.muted()),
+            Span::styled("esc ", theme.accent()),
+            Span::styled("cancel", theme.muted()),
+        ]),
+    ];
+
+    let paragraph = Paragraph::new(lines).block(block).style(theme.base());
+    frame.render_widget(paragraph, area);
+
+    let inner = area.inner(ratatui::layout::Margin {
+        horizontal: 1,
+        vertical: 1,
+    });
+    let cursor_x = inner.x + 1 + app.command_cursor as u16; // ":" = 1 char
+    if cursor_x < inner.right() {
+        frame.set_cursor_position((cursor_x, inner.y));
+    }
+}
+
+fn render_describe_popup(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let area = centered_rect(50, 60, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let (table, columns) = app.describe.clone().unwrap_or_default();
+
+    let block = Block::default()
+        .title(Span::styled(format!(" describe {table} "), theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+
+    let mut lines: Vec<Line> = match columns {
+        Some(text) => text
+            .lines()
+            .map(|l| Line::styled(l.to_string(), theme.base()))
+            .collect(),
+        None => vec![Line::styled(
+            format!("no table named {table} in the schema"),
+            theme.error(),
+        )],
+    };
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("esc ", theme.accent()),
+        Span::styled("close", theme.muted()),
+    ]));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .style(theme.base())
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+fn render_help_popup(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let area = centered_rect(60, 80, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(Span::styled(" keys ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+
+    let sections = app.keymap.help();
+    let key_width = sections
+        .iter()
+        .flat_map(|s| &s.entries)
+        .map(|(keys, _)| display_width(keys))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for section in sections {
+        lines.push(Line::styled(format!(" {}", section.title), theme.title()));
+        for (keys, what) in section.entries {
+            lines.push(Line::from(vec![
+                Span::styled(format!("   {} ", pad(&keys, key_width)), theme.accent()),
+                Span::styled(what, theme.base()),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .style(theme.base())
+        .scroll((app.help_scroll as u16, 0));
+    frame.render_widget(paragraph, area);
+}
+
+// compare mode: the sql panel split between the two providers
+fn render_comparison(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.theme;
+    let Some(comparison) = &app.comparison else {
+        return;
+    };
+
+    let block = Block::default()
+        .title(Span::styled(" SQL + Execution (compare) ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let [sides_area, hint_area] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(inner);
+    let columns = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(sides_area);
+
+    for (i, side) in comparison.sides.iter().enumerate() {
+        let picked = i == comparison.picked;
+        let (marker, border) = if picked {
+            ("> ", theme.accent())
+        } else {
+            ("  ", theme.border())
+        };
+        let side_block = Block::default()
+            .title(Span::styled(
+                format!(" {marker}{} ", side.label),
+                theme.title(),
+            ))
+            .borders(Borders::ALL)
+            .border_style(border)
+            .style(theme.base());
+
+        let lines: Vec<Line> = match &side.sql {
+            Ok(sql) => {
+                let risk = RiskLevel::from_sql(sql);
+                let risk_style = match risk {
+                    RiskLevel::Safe => theme.success(),
+                    RiskLevel::Moderate => theme.warning(),
+                    RiskLevel::Danger => theme.error(),
+                };
+                let mut lines = vec![Line::from(vec![
+                    Span::styled("Risk: ", theme.muted()),
+                    Span::styled(
+                        format!("{} ({})", risk.label(), risk.sql_type(sql)),
+                        risk_style,
+                    ),
+                ])];
+                lines.extend(
+                    sql.lines()
+                        .map(|line| Line::styled(line.to_string(), theme.accent())),
+                );
+                lines
+            }
+            Err(e) => vec![Line::styled(format!("failed: {e}"), theme.error())],
+        };
+
+        let paragraph = Paragraph::new(lines)
+            .block(side_block)
+            .style(theme.base())
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, columns[i]);
+    }
+
+    let hint = Line::from(vec![
+        Span::styled(" h/l ", theme.accent()),
+        Span::styled("pick side  ", theme.muted()),
+        Span::styled("enter ", theme.accent()),
+        Span::styled("run it  ", theme.muted()),
+        Span::styled("esc ", theme.accent()),
+        Span::styled("cancel", theme.muted()),
+    ]);
+    frame.render_widget(Paragraph::new(hint).style(theme.base()), hint_area);
+}
+
+fn render_candidates_popup(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let area = centered_rect(70, 60, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(Span::styled(" pick a query ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, sql) in app.candidates.iter().enumerate() {
+        let selected = i == app.candidate_index;
+        let (marker, style) = if selected {
+            (" > ", theme.selected().fg(theme.accent))
+        } else {
+            ("   ", theme.base())
+        };
+
+        for (n, line) in sql.lines().enumerate() {
+            let prefix = if n == 0 { marker } else { "   " };
+            lines.push(Line::from(vec![
+                Span::styled(prefix, theme.accent()),
+                Span::styled(line.to_string(), style),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(vec![
+        Span::styled(" j/k ", theme.accent()),
+        Span::styled("navigate  ", theme.muted()),
+        Span::styled("enter ", theme.accent()),
+        Span::styled("use  ", theme.muted()),
+        Span::styled("esc ", theme.accent()),
+        Span::styled("cancel", theme.muted()),
+    ]));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .style(theme.base())
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+fn render_tables_popup(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let area = centered_rect(50, 70, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let checked = app.table_picks.iter().filter(|(_, c)| *c).count();
+    let block = Block::default()
+        .title(Span::styled(
+            format!(
+                " tables for the model ({checked}/{}) ",
+                app.table_picks.len()
+            ),
+            theme.title(),
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, (table, picked)) in app.table_picks.iter().enumerate() {
+        let selected = i == app.table_pick_index;
+        let marker = if selected { " > " } else { "   " };
+        let style = if selected {
+            theme.selected().fg(theme.accent)
+        } else {
+            theme.base()
+        };
+        let check = if *picked { "[x] " } else { "[ ] " };
+        lines.push(Line::from(vec![
+            Span::styled(marker, theme.accent()),
+            Span::styled(check, theme.accent()),
+            Span::styled(table.clone(), style),
+        ]));
+    }
+
+    // keep the selection in view on databases with hundreds of tables
+    let height = area.height.saturating_sub(4) as usize;
+    let scroll = app
+        .table_pick_index
+        .saturating_sub(height.saturating_sub(1));
+
+    let inner = area.inner(ratatui::layout::Margin {
+        horizontal: 1,
+        vertical: 1,
+    });
+    let [list, hint] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(2)])
+        .areas(inner);
+
+    frame.render_widget(block, area);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .style(theme.base())
+            .scroll((scroll as u16, 0)),
+        list,
+    );
+    let keys = Line::from(vec![
+        Span::styled(" space ", theme.accent()),
+        Span::styled("toggle  ", theme.muted()),
+        Span::styled("a ", theme.accent()),
+        Span::styled("all  ", theme.muted()),
+        Span::styled("enter ", theme.accent()),
+        Span::styled("use (none = all)  ", theme.muted()),
+        Span::styled("esc ", theme.accent()),
+        Span::styled("cancel", theme.muted()),
+    ]);
+    frame.render_widget(
+        Paragraph::new(vec![Line::from(""), keys]).style(theme.base()),
+        hint,
+    );
+}
+
+fn render_setup_profile_popup(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let area = centered_rect(60, 50, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(Span::styled(" nlql setup - profile ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.accent())
+        .style(theme.base());
+
+    let mut lines = vec![
+        Line::styled("pick a saved connection:", theme.muted()),
+        Line::from(""),
+    ];
+
+    let width = app.profiles.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    for (i, profile) in app.profiles.iter().enumerate() {
+        let name = format!("{:width$}", profile.name);
+        if i == app.profile_index {
+            lines.push(Line::from(vec![
+                Span::styled(" > ", theme.accent()),
+                Span::styled(name, theme.selected().fg(theme.accent)),
+                Span::styled(format!("  {}", profile.url), theme.muted()),
+            ]));
+        } else {
+            lines.push(Line::from(vec![
+                Span::styled(format!("   {name}"), theme.base()),
+                Span::styled(format!("  {}", profile.url), theme.muted()),
+            ]));
+        }
+    }
+
+    let new_label = "+ new connection (not saved as a profile)";
+    if app.profile_index == app.profiles.len() {
+        lines.push(Line::from(vec![
+            Span::styled(" > ", theme.accent()),
+            Span::styled(new_label, theme.selected().fg(theme.accent)),
+        ]));
+    } else {
+        lines.push(Line::styled(format!("   {new_label}"), theme.base()));
+    }
+
+    if let Some(err) = &app.setup_error {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(err.clone(), theme.error()));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("j/k ", theme.accent()),
+        Span::styled("navigate  ", theme.muted()),
+        Span::styled("enter ", theme.accent()),
+        Span::styled("connect  ", theme.muted()),
+        Span::styled("esc ", theme.accent()),
+        Span::styled("quit", theme.muted()),
+    ]));
+
+    let paragraph = Paragraph::new(lines).block(block).style(theme.base());
+    frame.render_widget(paragraph, area);
+}
+
 fn render_setup_db_type_popup(frame: &mut Frame, app: &mut App) {
     let theme = &app.theme;
     let area = centered_rect(50, 40, frame.area());
@@ -846,14 +1354,12 @@ fn render_setup_provider_popup(frame: &mut Frame, app: &mut App) {
         .border_style(theme.accent())
         .style(theme.base());
 
-    let providers = ["Claude (Anthropic)", "OpenAI (GPT-4)"];
-
     let mut lines = vec![
         Line::styled("select your ai provider:", theme.muted()),
         Line::from(""),
     ];
 
-    for (i, provider) in providers.iter().enumerate() {
+    for (i, (_, provider)) in SETUP_PROVIDERS.iter().enumerate() {
         let is_selected = i == app.setup_provider_index;
         if is_selected {
             lines.push(Line::from(vec![
@@ -891,6 +1397,8 @@ fn render_setup_api_key_popup(frame: &mut Frame, app: &mut App) {
     let provider_name = match app.setup_provider {
         crate::Provider::Claude => "claude",
         crate::Provider::OpenAI => "openai",
+        crate::Provider::Gemini => "gemini",
+        crate::Provider::Ollama => "ollama",
     };
 
     let block = Block::default()
@@ -906,7 +1414,10 @@ fn render_setup_api_key_popup(frame: &mut Frame, app: &mut App) {
     let masked: String = "*".repeat(app.setup_api_key_input.len());
 
     let mut lines = vec![
-        Line::styled(format!("enter your {} api key:", provider_name), theme.muted()),
+        Line::styled(
+            format!("enter your {} api key:", provider_name),
+            theme.muted(),
+        ),
         Line::from(""),
         Line::raw(&masked),
         Line::from(""),
@@ -921,6 +1432,8 @@ fn render_setup_api_key_popup(frame: &mut Frame, app: &mut App) {
     let env_var = match app.setup_provider {
         crate::Provider::Claude => "ANTHROPIC_API_KEY",
         crate::Provider::OpenAI => "OPENAI_API_KEY",
+        crate::Provider::Gemini => "GEMINI_API_KEY",
+        crate::Provider::Ollama => "OLLAMA_HOST",
     };
 
     lines.push(Line::styled(
@@ -979,13 +1492,15 @@ fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
 
 fn format_result(
     result: &crate::core::QueryResult,
+    sql: Option<&str>,
     theme: &crate::tui::theme::Theme,
     available_width: usize,
 ) -> Vec<Line<'static>> {
     let mut lines = Vec::new();
 
     if result.rows.is_empty() {
-        lines.push(Line::styled("no rows".to_string(), theme.muted()));
+        let status = result.status(sql.unwrap_or_default());
+        lines.push(Line::styled(status.unwrap_or_default(), theme.muted()));
         return lines;
     }
 
@@ -995,11 +1510,11 @@ fn format_result(
     }
 
     // calculate ideal column widths based on content
-    let mut widths: Vec<usize> = result.columns.iter().map(|c| c.len()).collect();
+    let mut widths: Vec<usize> = result.columns.iter().map(|c| display_width(c)).collect();
     for row in &result.rows {
         for (i, val) in row.iter().enumerate() {
             if i < widths.len() {
-                let len = format_value(val).len();
+                let len = display_width(&format_value(val));
                 if len > widths[i] {
                     widths[i] = len;
                 }
@@ -1016,16 +1531,16 @@ fn format_result(
         let content_width = available_width - spacing;
         let total_content: usize = widths.iter().sum();
 
-        if total_content > 0 {
-            // shrink proportionally, with minimum width of 4
-            for w in &mut widths {
-                *w = (*w * content_width / total_content).max(4);
+        // shrink proportionally, with minimum width of 4
+        for w in &mut widths {
+            if let Some(shrunk) = (*w * content_width).checked_div(total_content) {
+                *w = shrunk.max(4);
             }
         }
     }
 
     // cap individual columns at reasonable max
-    let max_col_width = (available_width / num_cols).max(8).min(30);
+    let max_col_width = (available_width / num_cols).clamp(8, 30);
     for w in &mut widths {
         if *w > max_col_width {
             *w = max_col_width;
@@ -1039,9 +1554,8 @@ fn format_result(
         .enumerate()
         .flat_map(|(i, c)| {
             let w = widths.get(i).copied().unwrap_or(10);
-            let s = truncate_str(c, w);
             let mut spans = vec![Span::styled(
-                format!("{:width$}", s, width = w),
+                pad(&truncate(c, w, "..."), w),
                 ratatui::style::Style::default().fg(theme.accent),
             )];
             if i < num_cols - 1 {
@@ -1077,8 +1591,7 @@ fn format_result(
             .flat_map(|(i, v)| {
                 let w = widths.get(i).copied().unwrap_or(10);
                 let s = format_value(v);
-                let s = truncate_str(&s, w);
-                let mut spans = vec![Span::raw(format!("{:width$}", s, width = w))];
+                let mut spans = vec![Span::raw(pad(&truncate(&s, w, "..."), w))];
                 if i < num_cols - 1 {
                     spans.push(Span::raw(" "));
                 }
@@ -1091,16 +1604,6 @@ fn format_result(
     lines
 }
 
-fn truncate_str(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else if max_len > 3 {
-        format!("{}...", &s[..max_len - 3])
-    } else {
-        s[..max_len].to_string()
-    }
-}
-
 fn format_value(val: &serde_json::Value) -> String {
     match val {
         serde_json::Value::Null => "null".to_string(),
@@ -0,0 +1,49 @@
+// small string helpers shared by the cli and the tui
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// columns `s` takes up in a terminal, so CJK and emoji count double
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// cut `s` to at most `max` columns, ending in `ellipsis` if anything was dropped
+///
+/// never splits a char; if the ellipsis itself doesn't fit it's left off
+pub fn truncate(s: &str, max: usize, ellipsis: &str) -> String {
+    if display_width(s) <= max {
+        return s.to_string();
+    }
+
+    let ellipsis = if display_width(ellipsis) < max {
+        ellipsis
+    } else {
+        ""
+    };
+    let budget = max - display_width(ellipsis);
+
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > budget {
+            break;
+        }
+        used += w;
+        out.push(c);
+    }
+    out.push_str(ellipsis);
+    out
+}
+
+/// left-align `s` in `width` columns; `format!("{:w$}")` counts chars, not columns
+pub fn pad(s: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(s));
+    format!("{s}{}", " ".repeat(fill))
+}
+
+/// right-align `s` in `width` columns, for numbers
+pub fn pad_left(s: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(s));
+    format!("{}{s}", " ".repeat(fill))
+}
@@ -0,0 +1,404 @@
+// tests for what we'd send to the ai providers (no network)
+
+use nlql::{Ai, AiOptions, DEFAULT_ANTHROPIC_VERSION, Provider};
+
+#[test]
+fn test_show_request_redacts_key() {
+    let ai = Ai::new(Provider::Claude, Some("sk-secret".to_string())).unwrap();
+    let request = ai.show_request("count users", "TABLE users (\n  id INT\n)");
+    let text = request.to_string();
+
+    assert!(!text.contains("sk-secret"));
+    assert_eq!(request["headers"]["x-api-key"], "<redacted>");
+    assert_eq!(request["body"]["messages"][0]["content"], "count users");
+    assert!(
+        request["body"]["system"]
+            .as_str()
+            .unwrap()
+            .contains("TABLE users")
+    );
+}
+
+#[test]
+fn test_show_request_openai_model_override() {
+    let options = AiOptions {
+        model: Some("gpt-4o-mini".to_string()),
+        prompt_prefix: Some("be brief".to_string()),
+        ..Default::default()
+    };
+    let ai = Ai::new(Provider::OpenAI, Some("sk-secret".to_string()))
+        .unwrap()
+        .with_options(options);
+    let request = ai.show_request("count users", "");
+
+    assert_eq!(request["headers"]["Authorization"], "<redacted>");
+    assert_eq!(request["body"]["model"], "gpt-4o-mini");
+    assert_eq!(
+        request["body"]["messages"][1]["content"],
+        "be brief\n\ncount users"
+    );
+}
+
+#[test]
+fn test_show_request_extra_headers() {
+    let options = AiOptions {
+        headers: vec![
+            ("X-Org-Id".to_string(), "acme".to_string()),
+            (
+                "Content-Type".to_string(),
+                "application/json; charset=utf-8".to_string(),
+            ),
+            ("X-Proxy-Token".to_string(), "hunter2".to_string()),
+        ],
+        ..Default::default()
+    };
+    for provider in [Provider::Claude, Provider::OpenAI, Provider::Gemini] {
+        let ai = Ai::new(provider, Some("sk-secret".to_string()))
+            .unwrap()
+            .with_options(options.clone());
+        let request = ai.show_request("count users", "");
+        let headers = request["headers"].as_object().unwrap();
+
+        assert_eq!(headers["X-Org-Id"], "acme");
+        assert_eq!(headers["X-Proxy-Token"], "<redacted>");
+        // replaced, not sent twice
+        let content_types = headers
+            .keys()
+            .filter(|k| k.eq_ignore_ascii_case("content-type"))
+            .count();
+        assert_eq!(content_types, 1);
+        assert_eq!(headers["Content-Type"], "application/json; charset=utf-8");
+    }
+}
+
+#[test]
+fn test_show_request_gemini() {
+    let ai = Ai::new(Provider::Gemini, Some("AIza-secret".to_string())).unwrap();
+    let request = ai.show_request("count users", "TABLE users (\n  id INT\n)");
+
+    assert!(!request.to_string().contains("AIza-secret"));
+    assert_eq!(request["headers"]["x-goog-api-key"], "<redacted>");
+    assert_eq!(
+        request["url"],
+        "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-pro:generateContent"
+    );
+    // no system role, the instructions are the first part
+    let parts = &request["body"]["contents"][0]["parts"];
+    assert!(parts[0]["text"].as_str().unwrap().contains("TABLE users"));
+    assert_eq!(parts[1]["text"], "count users");
+    assert_eq!("google".parse::<Provider>(), Ok(Provider::Gemini));
+}
+
+#[test]
+fn test_ollama_needs_no_key() {
+    let options = AiOptions {
+        model: Some("sqlcoder".to_string()),
+        ..Default::default()
+    };
+    let ai = Ai::new(Provider::Ollama, None)
+        .unwrap()
+        .with_options(options);
+
+    // SAFETY: only this test touches this variable
+    unsafe { std::env::set_var("OLLAMA_HOST", "gpu-box:11434/") };
+    let request = ai.show_request("count users", "TABLE users (\n  id INT\n)");
+    unsafe { std::env::remove_var("OLLAMA_HOST") };
+
+    assert_eq!(request["url"], "http://gpu-box:11434/api/generate");
+    assert!(request["headers"].get("Authorization").is_none());
+    assert_eq!(request["body"]["model"], "sqlcoder");
+    assert_eq!(request["body"]["stream"], false);
+    assert_eq!(request["body"]["prompt"], "count users");
+    assert!(
+        request["body"]["system"]
+            .as_str()
+            .unwrap()
+            .contains("TABLE users")
+    );
+}
+
+#[test]
+fn test_select_only_prompt_rule() {
+    let ai = Ai::new(Provider::Claude, Some("sk-secret".to_string())).unwrap();
+    let system = ai.show_request("delete old users", "")["body"]["system"].to_string();
+    assert!(!system.contains("Only generate SELECT"));
+
+    let options = AiOptions {
+        select_only: true,
+        ..Default::default()
+    };
+    let ai = ai.with_options(options);
+    let system = ai.show_request("delete old users", "")["body"]["system"].to_string();
+    assert!(system.contains("Only generate SELECT statements"));
+    assert!(system.contains("READ_ONLY:"));
+}
+
+#[test]
+fn test_parse_response() {
+    let claude = r#"{"content":[{"type":"text","text":"```sql\nSELECT 1\n```"}],
+        "usage":{"input_tokens":10,"output_tokens":3}}"#;
+    let generated = Provider::Claude.parse_response(claude).unwrap();
+    assert_eq!(generated.sql, "SELECT 1");
+    assert_eq!(generated.usage.map(|u| u.output_tokens), Some(3));
+
+    let openai = r#"{"choices":[{"message":{"content":"SELECT 2"}}]}"#;
+    assert_eq!(
+        Provider::OpenAI.parse_response(openai).unwrap().sql,
+        "SELECT 2"
+    );
+
+    let gemini = r#"{"candidates":[{"content":{"parts":[{"text":"```sql\nSELECT 3\n```"}]}}],
+        "usageMetadata":{"promptTokenCount":12,"candidatesTokenCount":4}}"#;
+    let generated = Provider::Gemini.parse_response(gemini).unwrap();
+    assert_eq!(generated.sql, "SELECT 3");
+    assert_eq!(generated.usage.map(|u| u.input_tokens), Some(12));
+
+    let ollama = r#"{"model":"llama3","response":"SELECT 4","done":true,
+        "prompt_eval_count":30,"eval_count":5}"#;
+    let generated = Provider::Ollama.parse_response(ollama).unwrap();
+    assert_eq!(generated.sql, "SELECT 4");
+    assert_eq!(generated.usage.map(|u| u.output_tokens), Some(5));
+}
+
+#[test]
+fn test_parse_response_empty() {
+    let empty = [
+        (Provider::Claude, r#"{"content":[]}"#),
+        (
+            Provider::Claude,
+            r#"{"content":[{"type":"text","text":"  \n"}]}"#,
+        ),
+        (
+            Provider::Claude,
+            r#"{"content":[{"type":"text","text":"```sql\n```"}]}"#,
+        ),
+        (Provider::OpenAI, r#"{"choices":[]}"#),
+        (
+            Provider::OpenAI,
+            r#"{"choices":[{"message":{"content":""}}]}"#,
+        ),
+        (
+            Provider::OpenAI,
+            r#"{"choices":[{"message":{"content":null}}]}"#,
+        ),
+        (Provider::Gemini, r#"{"candidates":[]}"#),
+        (
+            Provider::Gemini,
+            r#"{"candidates":[{"finishReason":"SAFETY"}]}"#,
+        ),
+    ];
+    for (provider, body) in empty {
+        let err = provider.parse_response(body).unwrap_err().to_string();
+        assert!(err.contains("model returned no SQL"), "{body}: {err}");
+    }
+
+    assert!(Provider::Claude.parse_response("not json").is_err());
+}
+
+#[test]
+fn test_configured_models_and_version() {
+    let options = AiOptions {
+        claude_model: Some("claude-next".to_string()),
+        openai_model: Some("gpt-next".to_string()),
+        anthropic_version: Some("2099-01-01".to_string()),
+        ..Default::default()
+    };
+    let claude = Ai::new(Provider::Claude, Some("k".to_string()))
+        .unwrap()
+        .with_options(options.clone());
+    let request = claude.show_request("count users", "");
+    assert_eq!(request["body"]["model"], "claude-next");
+    assert_eq!(request["headers"]["anthropic-version"], "2099-01-01");
+
+    let openai = Ai::new(Provider::OpenAI, Some("k".to_string()))
+        .unwrap()
+        .with_options(options.clone());
+    assert_eq!(openai.model(), "gpt-next");
+
+    // --model wins over the per-provider setting
+    let options = AiOptions {
+        model: Some("gpt-4o-mini".to_string()),
+        ..options
+    };
+    let openai = Ai::new(Provider::OpenAI, Some("k".to_string()))
+        .unwrap()
+        .with_options(options);
+    assert_eq!(openai.model(), "gpt-4o-mini");
+}
+
+#[test]
+fn test_default_model_and_version() {
+    let ai = Ai::new(Provider::Claude, Some("k".to_string())).unwrap();
+    let request = ai.show_request("count users", "");
+
+    assert_eq!(ai.model(), Provider::Claude.default_model());
+    assert_eq!(
+        request["headers"]["anthropic-version"],
+        DEFAULT_ANTHROPIC_VERSION
+    );
+}
+
+#[test]
+fn test_order_hint_rule() {
+    let schema = "TABLE orders (\n  id INTEGER\n  created_at TEXT\n)";
+    let ai = Ai::new(Provider::Claude, Some("sk-secret".to_string())).unwrap();
+    let system = ai.show_request("latest orders", schema)["body"]["system"].to_string();
+    assert!(!system.contains("ORDER BY the table's timestamp"));
+
+    let ai = ai.with_options(AiOptions {
+        order_hint: true,
+        ..Default::default()
+    });
+    let system = ai.show_request("latest orders", schema)["body"]["system"].to_string();
+    assert!(system.contains("timestamp columns: orders.created_at"));
+    // nothing to point at, no rule
+    let system = ai.show_request("latest tags", "TABLE tags (\n  name TEXT\n)")["body"]["system"]
+        .to_string();
+    assert!(!system.contains("timestamp columns"));
+}
+
+#[test]
+fn test_parse_response_skips_thinking_blocks() {
+    let body = r#"{"content":[
+        {"type":"thinking","thinking":"SELECT * is too broad here","signature":"abc"},
+        {"type":"redacted_thinking","data":"xyz"},
+        {"type":"text","text":"SELECT id FROM users"}
+    ]}"#;
+    assert_eq!(
+        Provider::Claude.parse_response(body).unwrap().sql,
+        "SELECT id FROM users"
+    );
+
+    // prose in one text block, the query in another
+    let body = r#"{"content":[
+        {"type":"text","text":"Here is the query you asked for:"},
+        {"type":"text","text":"```sql\nSELECT 2\n```"},
+        {"type":"text","text":"It counts the rows."}
+    ]}"#;
+    assert_eq!(
+        Provider::Claude.parse_response(body).unwrap().sql,
+        "SELECT 2"
+    );
+
+    // nothing sql-like, so the answer is still whatever the model said last
+    let body = r#"{"content":[{"type":"text","text":"CLARIFY: which users?"}]}"#;
+    assert_eq!(
+        Provider::Claude.parse_response(body).unwrap().sql,
+        "CLARIFY: which users?"
+    );
+}
+
+#[test]
+fn test_parse_response_confidence() {
+    let body = r#"{"choices":[{"message":{"content":"```sql\nSELECT id FROM users\n```\n-- confidence: 85"}}]}"#;
+    let generated = Provider::OpenAI.parse_response(body).unwrap();
+    assert_eq!(generated.sql, "SELECT id FROM users");
+    assert_eq!(generated.confidence, Some(85));
+
+    // a trailing comment on the last line of the query
+    let body = r#"{"choices":[{"message":{"content":"SELECT 1; -- Confidence: 70%"}}]}"#;
+    let generated = Provider::OpenAI.parse_response(body).unwrap();
+    assert_eq!(generated.sql, "SELECT 1;");
+    assert_eq!(generated.confidence, Some(70));
+
+    // no marker, no made up number; other comments stay
+    let body = r#"{"choices":[{"message":{"content":"-- active users\nSELECT 1"}}]}"#;
+    let generated = Provider::OpenAI.parse_response(body).unwrap();
+    assert_eq!(generated.sql, "-- active users\nSELECT 1");
+    assert_eq!(generated.confidence, None);
+}
+
+// the schema part of the system prompt `ai` would send
+fn sent_schema(ai: &Ai, prompt: &str, schema: &str) -> String {
+    let request = ai.show_request(prompt, schema);
+    request["body"]["system"].as_str().unwrap().to_string()
+}
+
+fn table(name: &str) -> String {
+    format!(
+        "TABLE {name} (\n  id INT,\n  created_at TIMESTAMP,\n  updated_at TIMESTAMP,\n  note TEXT\n)"
+    )
+}
+
+#[test]
+fn test_schema_cut_keeps_mentioned_tables() {
+    let schema = ["accounts", "orders", "users"].map(table).join("\n\n");
+    // room for two tables and the note
+    let options = AiOptions {
+        max_schema_bytes: 64 + 2 * (table("orders").len() + 2),
+        ..Default::default()
+    };
+    let ai = Ai::new(Provider::Claude, Some("sk-secret".to_string()))
+        .unwrap()
+        .with_options(options);
+    let system = sent_schema(&ai, "orders per user", &schema);
+
+    // "user" finds users, accounts comes first but isn't asked about
+    assert!(system.contains("TABLE orders"), "{system}");
+    assert!(system.contains("TABLE users"), "{system}");
+    assert!(!system.contains("TABLE accounts"), "{system}");
+    assert!(system.contains("[schema truncated: 2 of 3 tables shown]"));
+}
+
+#[test]
+fn test_schema_under_budget_is_whole() {
+    let schema = ["accounts", "orders"].map(table).join("\n\n");
+    let ai = Ai::new(Provider::Claude, Some("sk-secret".to_string())).unwrap();
+    let system = sent_schema(&ai, "orders per user", &schema);
+
+    assert!(system.contains(&schema));
+    assert!(!system.contains("schema truncated"));
+}
+
+#[test]
+fn test_ai_header_values_keep_commas() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_nlql"))
+        .args(["--provider", "ollama", "--ai-header", "X-Tags=a,b"])
+        .args(["query", "users", "--no-schema", "--show-request"])
+        .env("NLQL_AI_HEADERS", "X-Org-Id=acme\nX-Team=data,ops")
+        .env("XDG_CONFIG_HOME", "/tmp/nlql-ai-header-no-config")
+        .output()
+        .unwrap();
+    let shown = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{shown}");
+    let request: serde_json::Value = serde_json::from_str(&shown).unwrap();
+
+    // the flag wins over the env var, which holds one header per line
+    assert_eq!(request["headers"]["X-Tags"], "a,b");
+    assert!(request["headers"].get("X-Org-Id").is_none());
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_nlql"))
+        .args(["--provider", "ollama", "query", "users", "--no-schema"])
+        .arg("--show-request")
+        .env("NLQL_AI_HEADERS", "X-Org-Id=acme\nX-Team=data,ops")
+        .env("XDG_CONFIG_HOME", "/tmp/nlql-ai-header-no-config")
+        .output()
+        .unwrap();
+    let request: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(request["headers"]["X-Org-Id"], "acme");
+    assert_eq!(request["headers"]["X-Team"], "data,ops");
+}
+
+#[test]
+fn test_read_only_asks_for_selects() {
+    let system = |extra: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_nlql"))
+            .args(["--provider", "ollama"])
+            .args(extra)
+            .args(["query", "users", "--no-schema", "--show-request"])
+            .env_remove("NLQL_READ_ONLY")
+            .env("XDG_CONFIG_HOME", "/tmp/nlql-read-only-no-config")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let request: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        request["body"]["system"].as_str().unwrap().to_string()
+    };
+
+    assert!(!system(&[]).contains("Only generate SELECT statements"));
+    assert!(system(&["--read-only"]).contains("Only generate SELECT statements"));
+}
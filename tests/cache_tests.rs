@@ -0,0 +1,100 @@
+// tests for the query result cache
+
+mod common;
+
+use nlql::tui::{App, run_sql};
+use nlql::{Db, QueryResult, ResultCache};
+use std::time::Duration;
+
+fn result(rows: usize) -> QueryResult {
+    common::result(
+        &["id"],
+        (0..rows).map(|i| vec![serde_json::json!(i)]).collect(),
+    )
+}
+
+#[test]
+fn test_hit_and_miss() {
+    let mut cache = ResultCache::default();
+    cache.put("SELECT 1", result(1));
+
+    assert_eq!(cache.get("SELECT 1").unwrap().row_count, 1);
+    assert!(cache.get("SELECT 2").is_none());
+}
+
+#[test]
+fn test_evicts_least_recently_used() {
+    let mut cache = ResultCache::new(2, Duration::from_secs(60));
+    cache.put("a", result(1));
+    cache.put("b", result(2));
+    cache.get("a");
+    cache.put("c", result(3));
+
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("a").is_some());
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn test_expired_entry() {
+    let mut cache = ResultCache::new(4, Duration::ZERO);
+    cache.put("a", result(1));
+    std::thread::sleep(Duration::from_millis(5));
+
+    assert!(cache.get("a").is_none());
+    assert!(cache.is_empty());
+}
+
+fn app() -> App {
+    let mut app = common::app("", false);
+    app.cache_enabled = true;
+    app.multi_statement = true;
+    app
+}
+
+fn first_name(app: &App) -> serde_json::Value {
+    app.result.as_ref().unwrap().rows[0][0].clone()
+}
+
+#[tokio::test]
+async fn test_write_clears_cache() {
+    let path = common::temp_db("cache_write");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (name TEXT)").await.unwrap();
+    db.execute("INSERT INTO users VALUES ('ann')")
+        .await
+        .unwrap();
+    let mut app = app();
+    let mut cache = ResultCache::default();
+
+    run_sql(&mut app, &db, &mut cache, "SELECT name FROM users").await;
+    assert_eq!(cache.len(), 1);
+
+    run_sql(&mut app, &db, &mut cache, "UPDATE users SET name = 'bob'").await;
+    assert!(cache.is_empty());
+
+    run_sql(&mut app, &db, &mut cache, "SELECT name FROM users").await;
+    assert_eq!(first_name(&app), "bob");
+
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn test_script_with_write_not_cached() {
+    let path = common::temp_db("cache_script");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (name TEXT)").await.unwrap();
+    let mut app = app();
+    let mut cache = ResultCache::default();
+
+    let script = "SELECT count(*) FROM users; INSERT INTO users VALUES ('ann')";
+    run_sql(&mut app, &db, &mut cache, script).await;
+    run_sql(&mut app, &db, &mut cache, script).await;
+
+    // both runs inserted, the second wasn't answered from the cache
+    assert!(cache.is_empty());
+    let rows = db.execute("SELECT count(*) FROM users").await.unwrap();
+    assert_eq!(rows.rows[0][0], 2);
+
+    std::fs::remove_file(path).ok();
+}
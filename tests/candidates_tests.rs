@@ -0,0 +1,43 @@
+// tests for splitting model output into candidate queries
+
+use nlql::split_candidates;
+
+#[test]
+fn test_single_statement() {
+    let out = "SELECT id, name\nFROM users\nWHERE active = true;";
+
+    assert_eq!(
+        split_candidates(out),
+        vec!["SELECT id, name\nFROM users\nWHERE active = true"]
+    );
+}
+
+#[test]
+fn test_two_candidates_with_commentary() {
+    let out = "Here are two options:\n\nSELECT count(*) FROM orders;\n\nOr, counting only paid ones:\n\nSELECT count(*) FROM orders WHERE paid;";
+
+    assert_eq!(
+        split_candidates(out),
+        vec![
+            "SELECT count(*) FROM orders",
+            "SELECT count(*) FROM orders WHERE paid",
+        ]
+    );
+}
+
+#[test]
+fn test_blank_line_inside_statement() {
+    let out = "SELECT name\n\nFROM users\n\nORDER BY name";
+
+    assert_eq!(
+        split_candidates(out),
+        vec!["SELECT name\nFROM users\nORDER BY name"]
+    );
+}
+
+#[test]
+fn test_fenced_and_duplicate() {
+    let out = "```sql\nSELECT 1;\n```\n\n```sql\nSELECT 1;\n```";
+
+    assert_eq!(split_candidates(out), vec!["SELECT 1"]);
+}
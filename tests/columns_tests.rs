@@ -0,0 +1,80 @@
+// tests that results keep the select list's column order
+
+mod common;
+
+use nlql::{CsvOptions, Db};
+use serde_json::json;
+
+async fn seeded(name: &str) -> (Db, String) {
+    let path = common::temp_db(name);
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE t (a INTEGER, b TEXT, c INTEGER)")
+        .await
+        .unwrap();
+    db.execute("INSERT INTO t VALUES (1, 'x', 3)")
+        .await
+        .unwrap();
+    (db, path)
+}
+
+#[tokio::test]
+async fn test_projection_order() {
+    let (db, path) = seeded("columns_projection").await;
+
+    let result = db.execute("SELECT b, a FROM t").await.unwrap();
+    assert_eq!(result.columns, ["b", "a"]);
+    assert_eq!(result.rows, vec![vec![json!("x"), json!(1)]]);
+
+    let result = db.execute("SELECT c, b AS second, a FROM t").await.unwrap();
+    assert_eq!(result.columns, ["c", "second", "a"]);
+    let csv = result.to_csv(&CsvOptions::default());
+    assert!(csv.starts_with("c,second,a"));
+
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn test_star_follows_table_order() {
+    let (db, path) = seeded("columns_star").await;
+
+    let result = db.execute("SELECT * FROM t").await.unwrap();
+    assert_eq!(result.columns, ["a", "b", "c"]);
+    assert_eq!(result.rows, vec![vec![json!(1), json!("x"), json!(3)]]);
+
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn test_ddl_status() {
+    let (db, path) = seeded("columns_ddl_status").await;
+
+    let result = db.execute("CREATE TABLE u (id INTEGER)").await.unwrap();
+    assert!(result.columns.is_empty());
+    assert_eq!(
+        result.status("CREATE TABLE u (id INTEGER)").as_deref(),
+        Some("CREATE executed successfully")
+    );
+
+    let sql = "SELECT a FROM t WHERE a > 100";
+    let result = db.execute(sql).await.unwrap();
+    assert_eq!(result.status(sql).as_deref(), Some("no rows"));
+
+    let sql = "SELECT a FROM t";
+    assert!(db.execute(sql).await.unwrap().status(sql).is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_rows_affected() {
+    let (db, path) = seeded("columns_rows_affected").await;
+    db.execute("INSERT INTO t VALUES (2, 'y', 4), (5, 'z', 6)")
+        .await
+        .unwrap();
+
+    let changed = db.rows_affected("UPDATE t SET b = 'w' WHERE a > 1").await;
+    assert_eq!(changed.unwrap(), 2);
+    assert_eq!(db.rows_affected("DELETE FROM t").await.unwrap(), 3);
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,50 @@
+// helpers shared by the integration tests, each test binary uses some of them
+#![allow(dead_code)]
+
+use nlql::tui::{App, DbInfo};
+use nlql::{QueryResult, ServeOptions, Server, SqlGenerator};
+use std::sync::Arc;
+
+/// a result with these columns and rows, not truncated
+pub fn result(columns: &[&str], rows: Vec<Vec<serde_json::Value>>) -> QueryResult {
+    QueryResult {
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+        row_count: rows.len(),
+        rows,
+        truncated: false,
+    }
+}
+
+/// a sqlite file path in the temp dir, removed if a previous run left it behind
+pub fn temp_db(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("nlql_{name}_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path.to_string_lossy().to_string()
+}
+
+/// a tui app on a made-up local sqlite database with this schema
+pub fn app(schema: &str, confirm: bool) -> App {
+    let info = DbInfo {
+        dialect: "sqlite".to_string(),
+        host: "local".to_string(),
+        database: "test.db".to_string(),
+        tables: schema.matches("TABLE ").count(),
+        url: "sqlite:test.db".to_string(),
+    };
+    App::new(schema.to_string(), info, confirm)
+}
+
+/// serve `path` as the default database on a free port and return the base url;
+/// the provider `options` pick answers unless a `generator` is given
+pub async fn serve_with(
+    generator: Option<Arc<dyn SqlGenerator>>,
+    path: &str,
+    options: &ServeOptions,
+) -> String {
+    let dbs = vec![("default".to_string(), format!("sqlite:{path}"))];
+    let app = Server::router(&dbs, generator, options).await.unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}")
+}
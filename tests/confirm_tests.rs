@@ -0,0 +1,89 @@
+// tests for which statements the tui asks about before running
+
+mod common;
+
+use common::app;
+
+#[test]
+fn test_confirm_skips_selects() {
+    let app = app("", true);
+
+    assert!(!app.needs_confirm("SELECT * FROM users"));
+    assert!(app.needs_confirm("DELETE FROM users WHERE id = 1"));
+    assert!(app.needs_confirm("CREATE TABLE t (id INT)"));
+}
+
+#[test]
+fn test_confirm_checks_every_statement() {
+    let app = app("", true);
+
+    assert!(app.needs_confirm("SELECT 1; DROP TABLE users"));
+    assert!(app.needs_confirm("SELECT * FROM users; DELETE FROM users"));
+    assert!(!app.needs_confirm("SELECT 1; SELECT 2"));
+}
+
+#[test]
+fn test_confirm_all() {
+    let mut app = app("", true);
+    app.confirm_all = true;
+
+    assert!(app.needs_confirm("SELECT * FROM users"));
+}
+
+#[test]
+fn test_no_confirm() {
+    let app = app("", false);
+
+    assert!(!app.needs_confirm("DROP TABLE users"));
+}
+
+#[test]
+fn test_confirm_runs_the_shown_sql() {
+    let mut app = app("", true);
+    app.show_confirm("DELETE FROM users WHERE id = 1".to_string());
+    assert!(app.pending_confirm.is_some());
+
+    assert_eq!(
+        app.confirm_sql().as_deref(),
+        Some("DELETE FROM users WHERE id = 1")
+    );
+    assert!(app.pending_confirm.is_none());
+    // answering twice doesn't run it twice
+    assert_eq!(app.confirm_sql(), None);
+}
+
+#[test]
+fn test_new_prompt_discards_pending() {
+    let mut app = app("", true);
+    app.show_confirm("DELETE FROM users WHERE id = 1".to_string());
+
+    app.prompt = "count users".to_string();
+    assert_eq!(app.submit().as_deref(), Some("count users"));
+    assert!(app.pending_confirm.is_none());
+    assert!(app.sql.is_none());
+    assert_eq!(app.confirm_sql(), None);
+}
+
+#[test]
+fn test_reconnect_discards_pending() {
+    let mut app = app("", true);
+    app.show_confirm("DELETE FROM users WHERE id = 1".to_string());
+
+    let mut other = app.db_info.clone();
+    other.url = "sqlite:other.db".to_string();
+    app.update_db_info(other, String::new());
+
+    assert!(app.pending_confirm.is_none());
+    assert_eq!(app.confirm_sql(), None);
+}
+
+#[test]
+fn test_confirm_refuses_after_db_swap() {
+    let mut app = app("", true);
+    app.show_confirm("DELETE FROM users WHERE id = 1".to_string());
+
+    // swapped underneath without going through a reconnect
+    app.db_info.url = "sqlite:other.db".to_string();
+    assert_eq!(app.confirm_sql(), None);
+    assert!(app.sql.is_none());
+}
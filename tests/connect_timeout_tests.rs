@@ -0,0 +1,32 @@
+// tests for giving up on a database that never answers
+
+use nlql::{Db, DbConfig, Error};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_connect_times_out() {
+    // accepts the connection but never says a word back
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            held.push(socket);
+        }
+    });
+
+    let config = DbConfig {
+        connect_timeout: Duration::from_secs(1),
+        ..DbConfig::default()
+    };
+    let started = Instant::now();
+    let err = Db::connect_with(&format!("postgres://me@{addr}/app"), &config)
+        .await
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, Error::ConnectTimeout(1)), "{err}");
+    assert_eq!(err.to_string(), "connection timed out after 1s");
+    assert_eq!(err.exit_code(), 2);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
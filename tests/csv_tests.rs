@@ -0,0 +1,57 @@
+// tests for csv rendering of query results
+
+mod common;
+
+use common::result;
+use nlql::CsvOptions;
+use serde_json::json;
+
+#[test]
+fn test_csv_values() {
+    let r = result(
+        &["id", "ok", "score", "note"],
+        vec![
+            vec![json!(1), json!(true), json!(2.5), json!("a, \"b\"")],
+            vec![json!(2), json!(null), json!(1.0), json!(null)],
+        ],
+    );
+    let csv = r.to_csv(&CsvOptions::default());
+
+    assert_eq!(
+        csv,
+        "id,ok,score,note\n1,true,2.5,\"a, \"\"b\"\"\"\n2,,1,\n"
+    );
+}
+
+#[test]
+fn test_csv_crlf_and_type_header() {
+    let r = result(&["id", "name"], vec![vec![json!(1), json!("x")]]);
+    let opts = CsvOptions {
+        crlf: true,
+        type_header: true,
+        ..Default::default()
+    };
+
+    assert_eq!(r.to_csv(&opts), "id:integer,name:text\r\n1,x\r\n");
+}
+
+#[test]
+fn test_column_type_mixed_numbers() {
+    let r = result(
+        &["n"],
+        vec![vec![json!(1)], vec![json!(1.5)], vec![json!(null)]],
+    );
+
+    assert_eq!(r.column_type(0), "real");
+}
+
+#[test]
+fn test_csv_tab_delimiter() {
+    let r = result(&["a", "b"], vec![vec![json!("x\ty"), json!("z")]]);
+    let opts = CsvOptions {
+        delimiter: '\t',
+        ..Default::default()
+    };
+
+    assert_eq!(r.to_csv(&opts), "a\tb\n\"x\ty\"\tz\n");
+}
@@ -0,0 +1,71 @@
+// tests for --db-schema
+
+mod common;
+
+use nlql::Db;
+
+#[tokio::test]
+async fn test_sqlite_rejects_db_schema() {
+    let path = common::temp_db("db_schema");
+    let db = Db::open_sqlite(&path).await.unwrap();
+
+    let err = db.with_db_schema("analytics").err().unwrap();
+    assert!(err.to_string().contains("postgres and mysql"));
+
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_keys() {
+    let path = common::temp_db("schema_keys");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+        .await
+        .unwrap();
+    db.execute(
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users, \
+         total REAL)",
+    )
+    .await
+    .unwrap();
+    db.execute(
+        "CREATE TABLE lines (order_id INTEGER, n INTEGER, sku TEXT, PRIMARY KEY (order_id, n), \
+         FOREIGN KEY (order_id) REFERENCES orders(id))",
+    )
+    .await
+    .unwrap();
+
+    let schema = db.schema().await.unwrap();
+    assert!(schema.contains(
+        "  total REAL\n  PRIMARY KEY (id)\n  FOREIGN KEY (user_id) REFERENCES users(id)\n)"
+    ));
+    assert!(schema.contains(
+        "  PRIMARY KEY (order_id, n)\n  FOREIGN KEY (order_id) REFERENCES orders(id)\n)"
+    ));
+    assert!(schema.contains("  name TEXT\n  PRIMARY KEY (id)\n)"));
+
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_views() {
+    let path = common::temp_db("schema_views");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE orders (id INTEGER, total REAL)")
+        .await
+        .unwrap();
+    db.execute("CREATE VIEW big_orders AS SELECT id, total FROM orders WHERE total > 100")
+        .await
+        .unwrap();
+
+    let schema = db.schema().await.unwrap();
+    assert!(schema.contains("TABLE orders (\n"));
+    assert!(schema.contains("VIEW big_orders (\n  id INTEGER\n  total REAL\n)"));
+    assert_eq!(nlql::schema_tables(&schema), ["orders", "big_orders"]);
+
+    let tables = db.table_schema().await.unwrap();
+    assert!(tables.contains("TABLE orders ("));
+    assert!(!tables.contains("big_orders"));
+
+    std::fs::remove_file(path).ok();
+}
@@ -85,3 +85,19 @@ async fn test_empty_result() {
     assert_eq!(result.row_count, 0);
     assert!(result.rows.is_empty());
 }
+
+#[tokio::test]
+async fn test_db_schema_qualifies_tables() {
+    let url = get_db_url();
+    if !url.starts_with("postgres") {
+        return;
+    }
+    let db = Db::connect(&url)
+        .await
+        .unwrap()
+        .with_db_schema("public")
+        .unwrap();
+    let schema = db.schema().await.unwrap();
+
+    assert!(schema.contains("TABLE public.users ("));
+}
@@ -0,0 +1,21 @@
+// tests for the exit codes the nlql binary returns
+
+#[tokio::test]
+async fn test_query_without_db_is_config_error() {
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_nlql"))
+        .args(["--provider", "ollama", "query", "everything"])
+        .env_remove("DATABASE_URL")
+        .env_remove("NLQL_PROFILE")
+        .env_remove("NLQL_CONFIG")
+        .env("XDG_CONFIG_HOME", "/tmp/nlql-exit-code-no-config")
+        .output()
+        .await
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("database url required"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,171 @@
+// tests for EXPLAIN plans and when a cached one is reused, marked stale or dropped
+
+mod common;
+
+use common::result;
+use nlql::tui::ExplainState;
+use nlql::{Db, PlanNode, plan_rows, plan_tree};
+
+#[test]
+fn test_rerun_marks_plan_stale() {
+    let mut app = common::app("", false);
+    app.set_sql("SELECT 1".to_string());
+    app.explain = ExplainState::Ready("SCAN t".to_string());
+    assert!(!app.explain.needs_run());
+
+    app.set_result(result(&[], vec![]));
+    assert_eq!(app.explain, ExplainState::Stale("SCAN t".to_string()));
+    assert!(app.explain.needs_run());
+}
+
+#[test]
+fn test_new_sql_or_connection_clears_plan() {
+    let mut app = common::app("", false);
+    app.set_sql("SELECT 1".to_string());
+    app.explain = ExplainState::Ready("SCAN t".to_string());
+    app.set_sql("SELECT 2".to_string());
+    assert_eq!(app.explain, ExplainState::Empty);
+
+    app.explain = ExplainState::Ready("SCAN t".to_string());
+    app.update_db_info(app.db_info.clone(), String::new());
+    assert_eq!(app.explain, ExplainState::Empty);
+}
+
+#[test]
+fn test_refresh_forces_run() {
+    let mut app = common::app("", false);
+    app.set_sql("SELECT 1".to_string());
+    app.explain = ExplainState::Ready("SCAN t".to_string());
+
+    app.refresh_explain();
+    assert!(app.show_explain);
+    assert!(app.explain.needs_run());
+}
+
+#[test]
+fn test_postgres_plan_tree() {
+    let lines: Vec<String> = [
+        "Hash Join  (cost=1.09..2.21 rows=5 width=40)",
+        "  Hash Cond: (o.user_id = u.id)",
+        "  ->  Seq Scan on orders o  (cost=0.00..1.05 rows=5 width=8)",
+        "        Filter: (total > 10)",
+        "  ->  Hash  (cost=1.04..1.04 rows=4 width=36)",
+        "        ->  Seq Scan on users u  (cost=0.00..1.04 rows=4 width=36)",
+    ]
+    .iter()
+    .map(|l| l.to_string())
+    .collect();
+
+    let leaf = |node: &str, details: &[&str]| PlanNode {
+        node: node.to_string(),
+        details: details.iter().map(|d| d.to_string()).collect(),
+        plans: vec![],
+    };
+    let mut hash = leaf("Hash  (cost=1.04..1.04 rows=4 width=36)", &[]);
+    hash.plans.push(leaf(
+        "Seq Scan on users u  (cost=0.00..1.04 rows=4 width=36)",
+        &[],
+    ));
+    let mut root = leaf(
+        "Hash Join  (cost=1.09..2.21 rows=5 width=40)",
+        &["Hash Cond: (o.user_id = u.id)"],
+    );
+    root.plans.push(leaf(
+        "Seq Scan on orders o  (cost=0.00..1.05 rows=5 width=8)",
+        &["Filter: (total > 10)"],
+    ));
+    root.plans.push(hash);
+
+    assert_eq!(plan_tree(&lines), Some(root));
+    assert_eq!(plan_tree(&[]), None);
+}
+
+#[test]
+fn test_plan_rows_takes_the_largest_estimate() {
+    let lines: Vec<String> = [
+        "Limit  (cost=0.00..0.04 rows=10 width=36)",
+        "  ->  Seq Scan on events  (cost=0.00..18334.00 rows=1000000 width=36)",
+    ]
+    .iter()
+    .map(|l| l.to_string())
+    .collect();
+    assert_eq!(plan_rows(&lines), Some(1_000_000));
+    assert_eq!(plan_rows(&["SCAN events".to_string()]), None);
+}
+
+#[tokio::test]
+async fn test_sqlite_has_no_estimate() {
+    let path = common::temp_db("estimate");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE t (a INTEGER)").await.unwrap();
+
+    assert_eq!(db.estimated_rows("SELECT * FROM t").await.unwrap(), None);
+
+    std::fs::remove_file(path).ok();
+}
+
+// a local stand-in for ollama that always answers with `sql`
+async fn fake_ollama(sql: &'static str) -> std::net::SocketAddr {
+    let answer =
+        move || async move { serde_json::json!({ "response": sql, "done": true }).to_string() };
+    let app = axum::Router::new().route("/api/generate", axum::routing::post(answer));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+async fn nlql_query(addr: std::net::SocketAddr, extra: &[&str]) -> std::process::Output {
+    tokio::process::Command::new(env!("CARGO_BIN_EXE_nlql"))
+        .args([
+            "--provider",
+            "ollama",
+            "--db",
+            "sqlite::memory:",
+            "query",
+            "everything",
+        ])
+        .args(extra)
+        .env("OLLAMA_HOST", addr.to_string())
+        .env("XDG_CONFIG_HOME", "/tmp/nlql-explain-no-config")
+        .output()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_query_explain_prints_plan() {
+    let addr = fake_ollama("SELECT 1").await;
+    let output = nlql_query(addr, &["--explain"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("EXPLAIN SELECT 1"), "{stdout}");
+}
+
+#[tokio::test]
+async fn test_query_analyze_needs_server_database() {
+    let addr = fake_ollama("SELECT 1").await;
+    let output = nlql_query(addr, &["--explain", "--analyze"]).await;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--analyze needs postgres or mysql"));
+}
+
+#[tokio::test]
+async fn test_query_explain_takes_one_statement() {
+    // without --analyze too, sqlite would run whatever follows the EXPLAIN
+    let addr = fake_ollama("SELECT 1; SELECT 2").await;
+    let output = nlql_query(addr, &["--explain"]).await;
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("EXPLAIN takes one"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,120 @@
+// tests for streaming exports straight from the database
+
+mod common;
+
+use nlql::{CsvOptions, Db, ExportFormat, export_rows};
+use std::path::Path;
+
+async fn seeded(name: &str) -> (Db, String) {
+    let path = common::temp_db(name);
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE t (id INTEGER, name TEXT, score REAL)")
+        .await
+        .unwrap();
+    db.execute("INSERT INTO t VALUES (1, 'ada', 9.5), (2, 'bob, jr', NULL)")
+        .await
+        .unwrap();
+    (db, path)
+}
+
+#[tokio::test]
+async fn test_stream_csv() {
+    let (db, path) = seeded("export_csv").await;
+
+    let mut rows = db.execute_stream("SELECT name, id FROM t ORDER BY id");
+    let mut out = Vec::new();
+    let count = export_rows(
+        &mut rows,
+        ExportFormat::Csv,
+        &CsvOptions::default(),
+        &mut out,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(count, 2);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "name,id\nada,1\n\"bob, jr\",2\n"
+    );
+    assert_eq!(rows.columns().unwrap(), ["name", "id"]);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_stream_ndjson() {
+    let (db, path) = seeded("export_ndjson").await;
+
+    let mut rows = db.execute_stream("SELECT id, score FROM t ORDER BY id");
+    let mut out = Vec::new();
+    export_rows(
+        &mut rows,
+        ExportFormat::Ndjson,
+        &CsvOptions::default(),
+        &mut out,
+    )
+    .await
+    .unwrap();
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["id"], 1);
+    assert_eq!(lines[0]["score"], 9.5);
+    assert!(lines[1]["score"].is_null());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_stream_empty_and_errors() {
+    let (db, path) = seeded("export_empty").await;
+
+    let mut rows = db.execute_stream("SELECT id FROM t WHERE id > 10");
+    let mut out = Vec::new();
+    let count = export_rows(
+        &mut rows,
+        ExportFormat::Csv,
+        &CsvOptions::default(),
+        &mut out,
+    )
+    .await
+    .unwrap();
+    assert_eq!(count, 0);
+    assert!(out.is_empty());
+
+    let mut rows = db.execute_stream("SELECT nope FROM t");
+    assert!(
+        export_rows(
+            &mut rows,
+            ExportFormat::Csv,
+            &CsvOptions::default(),
+            &mut out
+        )
+        .await
+        .is_err()
+    );
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(
+        ExportFormat::from_path(Path::new("out.ndjson")),
+        ExportFormat::Ndjson
+    );
+    assert_eq!(
+        ExportFormat::from_path(Path::new("out.JSONL")),
+        ExportFormat::Ndjson
+    );
+    assert_eq!(
+        ExportFormat::from_path(Path::new("out.csv")),
+        ExportFormat::Csv
+    );
+    assert_eq!(ExportFormat::from_path(Path::new("-")), ExportFormat::Csv);
+}
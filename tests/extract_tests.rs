@@ -0,0 +1,62 @@
+// tests for pulling the sql out of a model reply
+
+use nlql::extract_sql;
+
+#[test]
+fn test_plain_sql_untouched() {
+    assert_eq!(extract_sql("SELECT * FROM users"), "SELECT * FROM users");
+    assert_eq!(
+        extract_sql("  SELECT * FROM t WHERE a < 3 AND b > 1\n"),
+        "SELECT * FROM t WHERE a < 3 AND b > 1"
+    );
+}
+
+#[test]
+fn test_fences() {
+    assert_eq!(extract_sql("```sql\nSELECT 1\n```"), "SELECT 1");
+    assert_eq!(extract_sql("```\nSELECT 1\n```"), "SELECT 1");
+    assert_eq!(
+        extract_sql("Here you go:\n```sql\nSELECT 1\n```\nhope it helps"),
+        "SELECT 1"
+    );
+}
+
+#[test]
+fn test_tags() {
+    assert_eq!(extract_sql("<sql>SELECT 1</sql>"), "SELECT 1");
+    assert_eq!(
+        extract_sql("Sure!\n<SQL>\nSELECT name FROM users\n</SQL>"),
+        "SELECT name FROM users"
+    );
+    assert_eq!(
+        extract_sql("<answer>\n```sql\nSELECT 1\n```\n</answer>"),
+        "SELECT 1"
+    );
+    assert_eq!(
+        extract_sql("<answer><sql>SELECT 2</sql></answer>"),
+        "SELECT 2"
+    );
+}
+
+#[test]
+fn test_json() {
+    assert_eq!(extract_sql(r#"{"sql": "SELECT 1"}"#), "SELECT 1");
+    assert_eq!(
+        extract_sql("```json\n{\"query\": \"SELECT 3\"}\n```"),
+        "SELECT 3"
+    );
+}
+
+#[test]
+fn test_reasoning_tags_dropped() {
+    assert_eq!(
+        extract_sql("<thinking>maybe DELETE the dupes first?</thinking>\nSELECT 1"),
+        "SELECT 1"
+    );
+    assert_eq!(
+        extract_sql("<think>join on id</think><sql>SELECT a FROM t</sql>"),
+        "SELECT a FROM t"
+    );
+    // an unclosed tag isn't reasoning we can cut out safely
+    assert_eq!(extract_sql("<thinking>SELECT 1"), "<thinking>SELECT 1");
+}
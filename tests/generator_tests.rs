@@ -0,0 +1,656 @@
+// tests for the query flow with a canned sql generator instead of a live provider
+
+mod common;
+
+use common::{app, serve_with, temp_db};
+use nlql::cli;
+use nlql::tui::{Popup, compare_prompt, submit_prompt, submit_prompt_streaming};
+use nlql::{Db, Error, ServeOptions, SqlFuture, SqlGenerator, Usage};
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+// answers every prompt with the same output
+struct Canned(&'static str);
+
+impl SqlGenerator for Canned {
+    fn generate_sql<'a>(&'a self, _prompt: &'a str, _schema: &'a str) -> SqlFuture<'a, String> {
+        let sql = self.0.to_string();
+        Box::pin(async move { Ok(sql) })
+    }
+}
+
+// always asks for more detail
+struct Vague;
+
+impl SqlGenerator for Vague {
+    fn generate_sql<'a>(&'a self, _prompt: &'a str, _schema: &'a str) -> SqlFuture<'a, String> {
+        Box::pin(async { Err(Error::NeedsClarification("which users?".to_string())) })
+    }
+}
+
+// bills 100 in / 20 out per call
+#[derive(Default)]
+struct Billed(Mutex<Usage>);
+
+impl SqlGenerator for Billed {
+    fn generate_sql<'a>(&'a self, _prompt: &'a str, _schema: &'a str) -> SqlFuture<'a, String> {
+        *self.0.lock().unwrap() += Usage {
+            input_tokens: 100,
+            output_tokens: 20,
+        };
+        Box::pin(async { Ok("SELECT 1".to_string()) })
+    }
+
+    fn session_usage(&self) -> Option<Usage> {
+        Some(*self.0.lock().unwrap())
+    }
+}
+
+// writes its answer a piece at a time
+struct Streamed(&'static [&'static str]);
+
+impl SqlGenerator for Streamed {
+    fn generate_sql<'a>(&'a self, _prompt: &'a str, _schema: &'a str) -> SqlFuture<'a, String> {
+        let sql = self.0.concat();
+        Box::pin(async move { Ok(sql) })
+    }
+
+    fn generate_candidates_streaming<'a>(
+        &'a self,
+        _prompt: &'a str,
+        _schema: &'a str,
+        chunks: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> SqlFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            for piece in self.0 {
+                chunks.send(piece.to_string()).unwrap();
+                tokio::task::yield_now().await;
+            }
+            Ok(vec![self.0.concat()])
+        })
+    }
+}
+
+const SCHEMA: &str = "TABLE users (\n  id INTEGER\n)";
+
+#[tokio::test]
+async fn test_tui_submit_runs_select() {
+    let mut app = app(SCHEMA, false);
+    let sql = submit_prompt(
+        &mut app,
+        &Canned("SELECT id FROM users"),
+        "all users",
+        "",
+        false,
+    )
+    .await;
+
+    assert_eq!(sql.as_deref(), Some("SELECT id FROM users"));
+    assert_eq!(app.sql.as_deref(), Some("SELECT id FROM users"));
+}
+
+#[tokio::test]
+async fn test_tui_submit_holds_writes_for_confirm() {
+    let mut app = app(SCHEMA, true);
+    let sql = submit_prompt(
+        &mut app,
+        &Canned("DELETE FROM users"),
+        "clear users",
+        "",
+        false,
+    )
+    .await;
+
+    assert_eq!(sql, None);
+    assert_eq!(app.sql.as_deref(), Some("DELETE FROM users"));
+}
+
+#[tokio::test]
+async fn test_tui_submit_clarification() {
+    let mut app = app(SCHEMA, false);
+    let sql = submit_prompt(&mut app, &Vague, "users", "", false).await;
+
+    assert_eq!(sql, None);
+    assert_eq!(app.clarification.as_deref(), Some("which users?"));
+    assert_eq!(app.sql, None);
+}
+
+#[tokio::test]
+async fn test_tui_submit_tracks_tokens() {
+    let mut app = app(SCHEMA, false);
+    let billed = Billed::default();
+    submit_prompt(&mut app, &billed, "one", "", false).await;
+    submit_prompt(&mut app, &billed, "two", "", false).await;
+
+    let per_call = Usage {
+        input_tokens: 100,
+        output_tokens: 20,
+    };
+    assert_eq!(app.last_usage, Some(per_call));
+    assert_eq!(
+        app.session_usage,
+        Some(Usage {
+            input_tokens: 200,
+            output_tokens: 40,
+        })
+    );
+
+    // generators that aren't billed leave it empty
+    submit_prompt(&mut app, &Canned("SELECT 1"), "three", "", false).await;
+    assert_eq!(app.last_usage, None);
+}
+
+#[tokio::test]
+async fn test_tui_submit_streams_partial_sql() {
+    let mut app = app(SCHEMA, false);
+    let mut seen = Vec::new();
+    let sql = submit_prompt_streaming(
+        &mut app,
+        &Streamed(&["SELECT id", " FROM users"]),
+        "all users",
+        "",
+        false,
+        &mut |app| seen.push(app.partial_sql.clone()),
+    )
+    .await;
+
+    assert_eq!(seen, ["SELECT id", "SELECT id FROM users"]);
+    assert_eq!(sql.as_deref(), Some("SELECT id FROM users"));
+    assert!(app.partial_sql.is_empty());
+}
+
+#[test]
+fn test_usage_display() {
+    let usage = Usage {
+        input_tokens: 1_234,
+        output_tokens: 56,
+    };
+    assert_eq!(usage.to_string(), "1.2k in / 56 out");
+}
+
+// serve the router on a free port and return its base url
+async fn serve(generator: Arc<dyn SqlGenerator>, path: &str) -> String {
+    let options = ServeOptions {
+        enable_metrics: true,
+        ..Default::default()
+    };
+    serve_with(Some(generator), path, &options).await
+}
+
+#[tokio::test]
+async fn test_server_query_runs_generated_sql() {
+    let path = temp_db("generator_server");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER)").await.unwrap();
+    db.execute("INSERT INTO users VALUES (1), (2)")
+        .await
+        .unwrap();
+
+    let base = serve(Arc::new(Canned("SELECT id FROM users ORDER BY id")), &path).await;
+    let response: serde_json::Value = reqwest::Client::new()
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "all users" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response["sql"], "SELECT id FROM users ORDER BY id");
+    assert_eq!(response["result"]["rows"], json!([[1], [2]]));
+
+    let metrics = reqwest::get(format!("{base}/metrics"))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains("nlql_queries_total 1\n"));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_server_query_blocks_dangerous_sql() {
+    let path = temp_db("generator_blocked");
+    Db::open_sqlite(&path)
+        .await
+        .unwrap()
+        .execute("CREATE TABLE users (id INTEGER)")
+        .await
+        .unwrap();
+
+    let base = serve(Arc::new(Canned("DROP TABLE users")), &path).await;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "drop everything" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().starts_with("blocked"));
+    assert_eq!(body["error_kind"], "safety");
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_server_read_only() {
+    let path = temp_db("generator_read_only");
+    Db::open_sqlite(&path)
+        .await
+        .unwrap()
+        .execute("CREATE TABLE users (id INTEGER)")
+        .await
+        .unwrap();
+
+    let options = ServeOptions {
+        read_only: true,
+        ..Default::default()
+    };
+    let generator = Arc::new(Canned("INSERT INTO users VALUES (1)"));
+    let base = serve_with(Some(generator), &path, &options).await;
+
+    // run_dangerous doesn't get around it
+    let response = reqwest::Client::new()
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "add a user", "run_dangerous": true }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("read-only mode")
+    );
+    assert_eq!(body["error_kind"], "read_only");
+
+    // nor does EXPLAIN, sqlite would run the DELETE after it
+    let response = reqwest::Client::new()
+        .post(format!("{base}/explain"))
+        .json(&json!({ "sql": "SELECT 1; DELETE FROM users" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error_kind"], "read_only");
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_server_error_kinds() {
+    let path = temp_db("generator_error_kinds");
+    Db::open_sqlite(&path)
+        .await
+        .unwrap()
+        .execute("CREATE TABLE users (id INTEGER)")
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+    let kind = |base: String, body: serde_json::Value| {
+        let client = client.clone();
+        async move {
+            let body: serde_json::Value = client
+                .post(format!("{base}/query"))
+                .json(&body)
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            body["error_kind"].clone()
+        }
+    };
+
+    let base = serve(Arc::new(Canned("SELECT nope FROM users")), &path).await;
+    assert_eq!(kind(base.clone(), json!({ "prompt": "x" })).await, "sql");
+    assert_eq!(
+        kind(base.clone(), json!({ "prompt": "x", "db": "other" })).await,
+        "request"
+    );
+
+    let base = serve(Arc::new(Vague), &path).await;
+    assert_eq!(kind(base, json!({ "prompt": "x" })).await, "clarification");
+
+    // a success has no kind at all
+    let base = serve(Arc::new(Canned("SELECT id FROM users")), &path).await;
+    assert!(kind(base, json!({ "prompt": "x" })).await.is_null());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_server_unknown_provider() {
+    let path = temp_db("generator_unknown_provider");
+    Db::open_sqlite(&path).await.unwrap();
+    let base = serve(Arc::new(Canned("SELECT 1")), &path).await;
+    let client = reqwest::Client::new();
+
+    for endpoint in ["query", "explain"] {
+        let response = client
+            .post(format!("{base}/{endpoint}"))
+            .json(&json!({ "prompt": "x", "provider": "claud" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 400, "{endpoint}");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"], "unknown provider: claud");
+        assert_eq!(body["error_kind"], "request");
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_server_explain() {
+    let path = temp_db("generator_explain");
+    Db::open_sqlite(&path)
+        .await
+        .unwrap()
+        .execute("CREATE TABLE users (id INTEGER)")
+        .await
+        .unwrap();
+
+    let base = serve(Arc::new(Canned("SELECT id FROM users")), &path).await;
+    let client = reqwest::Client::new();
+
+    let body: serde_json::Value = client
+        .post(format!("{base}/explain"))
+        .json(&json!({ "prompt": "all users" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["sql"], "SELECT id FROM users");
+    assert_eq!(body["dialect"], "sqlite");
+    assert!(!body["plan"].as_array().unwrap().is_empty());
+
+    let response = client
+        .post(format!("{base}/explain"))
+        .json(&json!({ "sql": "DROP TABLE users" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().starts_with("blocked"));
+    assert!(body.get("plan").is_none());
+
+    // a second statement or an ANALYZE would run for real
+    for sql in ["SELECT 1; DELETE FROM users", "ANALYZE DELETE FROM users"] {
+        let response = client
+            .post(format!("{base}/explain"))
+            .json(&json!({ "sql": sql }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400, "{sql}");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error_kind"], "safety", "{sql}");
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_server_rejects_large_bodies() {
+    let path = temp_db("generator_body_limit");
+    Db::open_sqlite(&path)
+        .await
+        .unwrap()
+        .execute("CREATE TABLE users (id INTEGER)")
+        .await
+        .unwrap();
+
+    let options = ServeOptions {
+        max_body_size: 1024,
+        ..Default::default()
+    };
+    let base = serve_with(Some(Arc::new(Canned("SELECT 1"))), &path, &options).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "x".repeat(2048), "dry_run": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+
+    let response = client
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "one", "dry_run": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_tui_compare_shows_both_sides() {
+    let mut app = app(SCHEMA, false);
+    compare_prompt(
+        &mut app,
+        [&Canned("SELECT id FROM users"), &Vague],
+        ["claude".to_string(), "openai".to_string()],
+        "users",
+        "",
+        false,
+    )
+    .await;
+
+    let comparison = app.comparison.clone().unwrap();
+    assert_eq!(app.popup, Popup::Compare);
+    assert_eq!(
+        comparison.sides[0].sql.as_deref(),
+        Ok("SELECT id FROM users")
+    );
+    assert!(comparison.sides[1].sql.is_err());
+    // nothing runs until a side is picked
+    assert!(app.sql.is_none());
+
+    // the side without sql can't be picked
+    app.compare_pick_side(1);
+    assert_eq!(app.pick_comparison(), None);
+    app.compare_pick_side(0);
+    assert_eq!(
+        app.pick_comparison().as_deref(),
+        Some("SELECT id FROM users")
+    );
+    assert!(app.comparison.is_none());
+}
+
+#[test]
+fn test_compare_command_needs_a_second_provider() {
+    let mut app = app(SCHEMA, false);
+    app.toggle_compare();
+    assert!(!app.compare_enabled);
+
+    app.compare_label = Some("openai (gpt-4o)".to_string());
+    app.toggle_compare();
+    assert!(app.compare_enabled);
+}
+
+#[tokio::test]
+async fn test_tui_submit_adds_limit() {
+    let mut app = app(SCHEMA, false);
+    app.row_limit = Some(100);
+    let sql = submit_prompt(
+        &mut app,
+        &Canned("SELECT id FROM users"),
+        "users",
+        "",
+        false,
+    )
+    .await;
+
+    assert_eq!(sql.as_deref(), Some("SELECT id FROM users LIMIT 100"));
+    assert!(
+        app.logs
+            .iter()
+            .any(|l| l.message.contains("added LIMIT 100"))
+    );
+
+    // one the model wrote itself is kept
+    let sql = submit_prompt(
+        &mut app,
+        &Canned("SELECT id FROM users LIMIT 3"),
+        "users",
+        "",
+        false,
+    )
+    .await;
+    assert_eq!(sql.as_deref(), Some("SELECT id FROM users LIMIT 3"));
+}
+
+#[tokio::test]
+async fn test_server_history_keeps_recent_queries() {
+    let path = temp_db("generator_history");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER)").await.unwrap();
+    db.execute("INSERT INTO users VALUES (1), (2)")
+        .await
+        .unwrap();
+
+    let options = ServeOptions {
+        history_size: 2,
+        ..Default::default()
+    };
+    let base = serve_with(
+        Some(Arc::new(Canned("SELECT id FROM users"))),
+        &path,
+        &options,
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    for body in [
+        json!({ "prompt": "first", "dry_run": true }),
+        json!({ "prompt": "second" }),
+        json!({ "prompt": "third", "db": "missing" }),
+    ] {
+        client
+            .post(format!("{base}/query"))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let history: serde_json::Value = reqwest::get(format!("{base}/history"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let history = history.as_array().unwrap();
+
+    // the first call fell out, the rest are oldest first
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["prompt"], "second");
+    assert_eq!(history[0]["sql"], "SELECT id FROM users");
+    assert_eq!(history[0]["row_count"], 2);
+    assert_eq!(history[0]["error"], json!(null));
+    assert_eq!(history[1]["prompt"], "third");
+    assert_eq!(history[1]["error"], "unknown database: missing");
+    assert!(history[1]["duration_ms"].is_u64());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+// `nlql <args>` through the cli with `generator` answering, and no user config
+async fn run_cli(generator: &'static str, name: &str, args: &[&str]) -> miette::Result<()> {
+    let config = std::env::temp_dir().join(format!("nlql_{name}_{}.toml", std::process::id()));
+    std::fs::write(&config, "").unwrap();
+    let config = config.to_string_lossy().to_string();
+    let args = ["nlql", "--config", &config]
+        .into_iter()
+        .chain(args.iter().copied());
+    let result = cli::run_with(args, Some(Arc::new(Canned(generator)))).await;
+    let _ = std::fs::remove_file(&config);
+    result
+}
+
+#[tokio::test]
+async fn test_cli_query_runs_generated_sql() {
+    let path = temp_db("generator_cli_query");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER)").await.unwrap();
+    db.execute("INSERT INTO users VALUES (1), (2), (3)")
+        .await
+        .unwrap();
+
+    let url = format!("sqlite:{path}");
+    let args = [
+        "--db",
+        &url,
+        "--limit",
+        "2",
+        "query",
+        "users",
+        "--save-table",
+        "picked",
+    ];
+    run_cli("SELECT id FROM users ORDER BY id", "cli_query", &args)
+        .await
+        .unwrap();
+
+    // --limit went onto the generated sql before it ran
+    let saved = db.execute("SELECT id FROM picked").await.unwrap();
+    assert_eq!(saved.rows, vec![vec![json!(1)], vec![json!(2)]]);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_cli_query_read_only_refuses_writes() {
+    let path = temp_db("generator_cli_read_only");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER)").await.unwrap();
+
+    let url = format!("sqlite:{path}");
+    let args = ["--db", &url, "--read-only", "query", "add a user"];
+    let err = run_cli("INSERT INTO users VALUES (1)", "cli_read_only", &args)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::Blocked(_))
+    ));
+    let left = db.execute("SELECT id FROM users").await.unwrap();
+    assert_eq!(left.row_count, 0);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_cli_show_request_needs_a_provider() {
+    let args = ["query", "users", "--no-schema", "--show-request"];
+    let err = run_cli("SELECT 1", "cli_show_request", &args)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::Config(_))
+    ));
+}
@@ -0,0 +1,80 @@
+// tests for prompt history kept across tui sessions
+
+mod common;
+
+use common::app;
+use nlql::tui::{App, history_path};
+use std::path::PathBuf;
+
+fn temp_history(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nlql_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("nlql").join("history")
+}
+
+fn submit(app: &mut App, prompt: &str) {
+    app.prompt = prompt.to_string();
+    app.submit();
+}
+
+#[test]
+fn test_history_survives_restart() {
+    let path = temp_history("history_restart");
+
+    let mut first = app("", false);
+    first.load_history(path.clone(), 1000);
+    assert!(first.history.is_empty());
+    submit(&mut first, "all users");
+    submit(&mut first, "all users");
+    submit(&mut first, "orders\nby day");
+
+    let mut second = app("", false);
+    second.load_history(path.clone(), 1000);
+    assert_eq!(second.history, ["all users", "orders\nby day"]);
+    second.history_up();
+    assert_eq!(second.prompt, "orders\nby day");
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+}
+
+#[test]
+fn test_history_keeps_the_last_entries() {
+    let path = temp_history("history_cap");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "one\ntwo\ntwo\nthree\nfour\n").unwrap();
+
+    let mut app = app("", false);
+    app.load_history(path.clone(), 2);
+    assert_eq!(app.history, ["three", "four"]);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "three\nfour\n");
+
+    submit(&mut app, "five");
+    assert_eq!(app.history, ["four", "five"]);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+}
+
+#[test]
+fn test_history_unreadable_file_starts_empty() {
+    // a directory where the file should be
+    let path = temp_history("history_unreadable");
+    std::fs::create_dir_all(&path).unwrap();
+
+    let mut app = app("", false);
+    app.load_history(path.clone(), 1000);
+    assert!(app.history.is_empty());
+    submit(&mut app, "still works");
+    assert_eq!(app.history, ["still works"]);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+}
+
+#[test]
+fn test_history_path_uses_xdg_data_home() {
+    // SAFETY: only this test touches this variable
+    unsafe { std::env::set_var("XDG_DATA_HOME", "/tmp/nlql-data") };
+    assert_eq!(
+        history_path(),
+        Some(PathBuf::from("/tmp/nlql-data/nlql/history"))
+    );
+}
@@ -0,0 +1,155 @@
+// tests for tui keybinding config
+
+mod common;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use nlql::tui::{KeyAction, KeyBinding, KeyMap, Panel};
+use std::collections::HashMap;
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+fn keys(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_defaults() {
+    let map = KeyMap::default();
+
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+        Some(KeyAction::Quit)
+    );
+    // capitals arrive with shift set
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('Y'), KeyModifiers::SHIFT)),
+        Some(KeyAction::CopyOutput)
+    );
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('p'), KeyModifiers::CONTROL)),
+        Some(KeyAction::HistoryUp)
+    );
+}
+
+#[test]
+fn test_override_replaces_default() {
+    let (map, warnings) =
+        KeyMap::from_config(&keys(&[("Quit", "ctrl+q"), ("scroll_down", "n, down")]));
+
+    assert!(warnings.is_empty());
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+        None
+    );
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+        Some(KeyAction::Quit)
+    );
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('n'), KeyModifiers::NONE)),
+        Some(KeyAction::ScrollDown)
+    );
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('j'), KeyModifiers::NONE)),
+        None
+    );
+}
+
+#[test]
+fn test_invalid_bindings_warn_and_fall_back() {
+    let (map, warnings) = KeyMap::from_config(&keys(&[("explain", "hyper+e"), ("launch", "l")]));
+
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('e'), KeyModifiers::NONE)),
+        Some(KeyAction::Explain)
+    );
+}
+
+#[test]
+fn test_parse_binding() {
+    assert_eq!(KeyBinding::parse("shift+g"), KeyBinding::parse("G"));
+    assert!(KeyBinding::parse("f5").is_ok());
+    assert!(KeyBinding::parse("ctrl+").is_err());
+    assert!(KeyBinding::parse("bogus").is_err());
+}
+
+#[test]
+fn test_log_filter_key() {
+    let map = KeyMap::default();
+
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('L'), KeyModifiers::SHIFT)),
+        Some(KeyAction::LogFilter)
+    );
+}
+
+#[test]
+fn test_help_follows_keymap() {
+    let (map, _) = KeyMap::from_config(&keys(&[("quit", "ctrl+q"), ("scroll_down", "down, j")]));
+    let help = map.help();
+
+    assert_eq!(help[0].title, "normal mode");
+    let normal = &help[0].entries;
+    assert!(normal.contains(&("ctrl+q".to_string(), "quit")));
+    assert!(normal.contains(&("j, down".to_string(), "scroll down")));
+    assert!(normal.contains(&("?".to_string(), "this help")));
+    // every action shows up once
+    assert_eq!(normal.len(), KeyAction::ALL.len());
+
+    let titles: Vec<&str> = help.iter().map(|s| s.title).collect();
+    assert!(titles.contains(&"insert mode"));
+    assert!(titles.contains(&"confirm"));
+}
+
+#[test]
+fn test_binding_display_round_trips() {
+    for spec in ["q", "G", "ctrl+p", "alt+enter", "f5", "space", "?", "down"] {
+        let binding = KeyBinding::parse(spec).unwrap();
+        assert_eq!(binding.to_string(), spec);
+        assert_eq!(KeyBinding::parse(&binding.to_string()).unwrap(), binding);
+    }
+}
+
+#[test]
+fn test_default_keys_do_not_clash() {
+    let map = KeyMap::default();
+
+    // a clash would take an action's only key away from it
+    for action in KeyAction::ALL {
+        assert!(!map.keys_for(action).is_empty(), "{action:?} has no key");
+    }
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('3'), KeyModifiers::NONE)),
+        Some(KeyAction::FocusResults)
+    );
+    assert_eq!(
+        map.lookup(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+        Some(KeyAction::ScrollBottom)
+    );
+}
+
+#[test]
+fn test_scroll_top_and_bottom() {
+    let mut app = common::app("", false);
+    app.set_result(common::result(
+        &["n"],
+        (0..50).map(|n| vec![serde_json::json!(n)]).collect(),
+    ));
+
+    app.panel = Panel::Results;
+    app.scroll_bottom();
+    assert_eq!(app.result_scroll, 42);
+    app.scroll_top();
+    assert_eq!(app.result_scroll, 0);
+
+    // the prompt doesn't scroll
+    app.panel = Panel::Prompt;
+    app.scroll_bottom();
+    assert_eq!(app.result_scroll, 0);
+}
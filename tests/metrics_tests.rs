@@ -0,0 +1,38 @@
+// tests for the prometheus text served at /metrics
+
+use nlql::Metrics;
+use std::time::Duration;
+
+#[test]
+fn test_counters() {
+    let metrics = Metrics::default();
+    metrics.query();
+    metrics.query();
+    metrics.blocked();
+    metrics.error("ai");
+    metrics.error("ai");
+    metrics.error("database");
+    let text = metrics.render();
+
+    assert!(text.contains("# TYPE nlql_queries_total counter\nnlql_queries_total 2\n"));
+    assert!(text.contains("nlql_queries_blocked_total 1\n"));
+    assert!(text.contains("nlql_errors_total{kind=\"ai\"} 2\n"));
+    assert!(text.contains("nlql_errors_total{kind=\"database\"} 1\n"));
+}
+
+#[test]
+fn test_histogram_buckets_are_cumulative() {
+    let metrics = Metrics::default();
+    metrics.observe_db(Duration::from_millis(3));
+    metrics.observe_db(Duration::from_millis(200));
+    metrics.observe_db(Duration::from_secs(60));
+    let text = metrics.render();
+
+    assert!(text.contains("nlql_db_latency_seconds_bucket{le=\"0.005\"} 1\n"));
+    assert!(text.contains("nlql_db_latency_seconds_bucket{le=\"0.25\"} 2\n"));
+    assert!(text.contains("nlql_db_latency_seconds_bucket{le=\"30\"} 2\n"));
+    assert!(text.contains("nlql_db_latency_seconds_bucket{le=\"+Inf\"} 3\n"));
+    assert!(text.contains("nlql_db_latency_seconds_count 3\n"));
+    assert!(text.contains("nlql_db_latency_seconds_sum 60.203\n"));
+    assert!(text.contains("nlql_ai_latency_seconds_count 0\n"));
+}
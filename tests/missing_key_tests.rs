@@ -0,0 +1,43 @@
+// a server whose provider has no api key, in a binary of its own: it removes
+// the key variables, and changing the environment isn't sound while other
+// tests run beside it
+
+mod common;
+
+use common::{serve_with, temp_db};
+use nlql::{Db, Provider, ServeOptions};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_server_without_ai_client() {
+    let path = temp_db("missing_key");
+    Db::open_sqlite(&path).await.unwrap();
+
+    // SAFETY: the only test in this binary, nothing else reads the environment
+    unsafe {
+        std::env::remove_var("GEMINI_API_KEY");
+        std::env::remove_var("GOOGLE_API_KEY");
+    }
+    let options = ServeOptions {
+        provider: Provider::Gemini,
+        ..Default::default()
+    };
+    // still boots, the missing key only fails the prompts
+    let base = serve_with(None, &path, &options).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "all users" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 500);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let error = body["error"].as_str().unwrap();
+    assert!(error.contains("gemini client isn't set up"), "{error}");
+    assert!(error.contains("GEMINI_API_KEY"), "{error}");
+    assert_eq!(body["error_kind"], "ai");
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,45 @@
+// streaming sql from ollama, in a binary of its own: it sets OLLAMA_HOST, and
+// changing the environment isn't sound while other tests run beside it
+
+use nlql::{Ai, Provider, Usage};
+
+#[tokio::test]
+async fn test_ai_stream_from_ollama() {
+    // ndjson the way ollama sends it, plus noise a proxy might add
+    async fn generate(axum::Json(body): axum::Json<serde_json::Value>) -> String {
+        assert_eq!(body["stream"], true);
+        [
+            r#"{"response":"```sql\nSELECT","done":false}"#,
+            "not json",
+            r#"{"response":" 1\n```","done":false}"#,
+            "data: [DONE]",
+            r#"{"response":"","done":true,"prompt_eval_count":30,"eval_count":5}"#,
+        ]
+        .join("\n")
+    }
+    let app = axum::Router::new().route("/api/generate", axum::routing::post(generate));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // SAFETY: the only test in this binary, nothing else reads the environment
+    unsafe { std::env::set_var("OLLAMA_HOST", addr.to_string()) };
+    let ai = Ai::new(Provider::Ollama, None).unwrap();
+    let mut stream = ai.generate_sql_stream("one", "").await.unwrap();
+    let mut pieces = Vec::new();
+    while let Some(piece) = stream.next().await {
+        pieces.push(piece.unwrap());
+    }
+    let generated = stream.finish().await.unwrap();
+
+    assert_eq!(pieces, ["```sql\nSELECT", " 1\n```"]);
+    assert_eq!(generated.sql, "SELECT 1");
+    assert_eq!(
+        generated.usage,
+        Some(Usage {
+            input_tokens: 30,
+            output_tokens: 5,
+        })
+    );
+    assert_eq!(ai.session_usage().output_tokens, 5);
+}
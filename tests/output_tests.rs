@@ -0,0 +1,177 @@
+// tests for cli output formatting
+
+mod common;
+
+use common::result;
+use nlql::{Output, Usage};
+use serde_json::json;
+
+#[test]
+fn test_pretty_col_width_override() {
+    let r = result(&["name"], vec![vec![json!("abcdefghijklmnop")]]);
+    let out = Output::pretty("SELECT name FROM t", &r, Some(8));
+
+    assert!(out.contains("abcde..."));
+    assert!(!out.contains("abcdefghijklmnop"));
+}
+
+#[test]
+fn test_pretty_multibyte_truncation() {
+    let r = result(&["note"], vec![vec![json!("café☕☕☕☕☕☕")]]);
+    let out = Output::pretty("SELECT note FROM t", &r, Some(6));
+
+    assert!(out.contains("caf..."));
+}
+
+#[test]
+fn test_pretty_empty() {
+    let r = result(&[], vec![]);
+    let out = Output::pretty("SELECT 1 WHERE false", &r, None);
+
+    assert!(out.contains("no rows"));
+}
+
+#[test]
+fn test_pretty_right_aligns_numbers() {
+    let r = result(
+        &["name", "total", "note"],
+        vec![
+            vec![json!("alice"), json!(5), json!(null)],
+            vec![json!("bob"), json!("1234.50"), json!(null)],
+        ],
+    );
+    let out = Output::pretty("SELECT name, total, note FROM t", &r, None);
+    let lines: Vec<&str> = out.lines().collect();
+
+    assert_eq!(lines[2], "name     total  note");
+    assert_eq!(lines[4], "alice        5  null");
+    assert_eq!(lines[5], "bob    1234.50  null");
+}
+
+#[test]
+fn test_raw_includes_sql() {
+    let r = result(&["id"], vec![vec![json!(1)]]);
+    let out = Output::raw("SELECT id FROM t", &r);
+    let parsed: serde_json::Value = serde_json::from_str(&out).unwrap();
+
+    assert_eq!(parsed["sql"], "SELECT id FROM t");
+    assert_eq!(parsed["result"]["row_count"], 1);
+    assert!(parsed.get("usage").is_none());
+}
+
+#[test]
+fn test_raw_with_usage() {
+    let r = result(&["id"], vec![vec![json!(1)]]);
+    let usage = Usage {
+        input_tokens: 1200,
+        output_tokens: 56,
+    };
+    let out = Output::raw_with_usage("SELECT id FROM t", &r, Some(usage));
+    let parsed: serde_json::Value = serde_json::from_str(&out).unwrap();
+
+    assert_eq!(parsed["usage"]["input_tokens"], 1200);
+    assert_eq!(parsed["usage"]["output_tokens"], 56);
+}
+
+#[test]
+fn test_pretty_truncated_footer() {
+    let mut r = result(&["id"], vec![vec![json!(1)]]);
+    r.truncated = true;
+    let out = Output::pretty("SELECT id FROM t", &r, None);
+
+    assert!(out.contains("(1 rows, truncated)"));
+}
+
+#[test]
+fn test_html_escapes_values() {
+    let r = result(
+        &["name", "n"],
+        vec![
+            vec![json!("<script>alert('x')</script>"), json!(3)],
+            vec![json!("a & b"), json!(null)],
+        ],
+    );
+    let out = Output::html("SELECT name FROM t WHERE n < 5", &r);
+
+    assert!(out.starts_with("<!DOCTYPE html>"));
+    assert!(out.contains("<pre>SELECT name FROM t WHERE n &lt; 5</pre>"));
+    assert!(out.contains("<th>name</th><th>n</th>"));
+    assert!(out.contains("<td>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;</td>"));
+    assert!(!out.contains("<script>"));
+    assert!(out.contains("<td class=\"num\">3</td>"));
+    assert!(out.contains("<td>a &amp; b</td><td class=\"null\">null</td>"));
+    assert!(out.contains("(2 rows)"));
+}
+
+#[test]
+fn test_pretty_statement_without_result_set() {
+    let r = result(&[], vec![]);
+
+    let out = Output::pretty("CREATE TABLE t (id INT)", &r, None);
+    assert!(out.contains("CREATE executed successfully"));
+    assert!(!out.contains("no rows"));
+
+    let out = Output::pretty("delete from t", &r, None);
+    assert!(out.contains("DELETE executed successfully"));
+}
+
+#[test]
+fn test_show_prompt() {
+    let r = result(&["n"], vec![vec![json!(1)]]);
+
+    assert_eq!(
+        Output::prompt_comment("top customers\nby revenue"),
+        "-- top customers\n-- by revenue\n"
+    );
+    let out = Output::html_with_prompt(Some("orders < 5 days"), "SELECT 1", &r);
+    assert!(out.contains("<blockquote>orders &lt; 5 days</blockquote>\n<pre>SELECT 1</pre>"));
+    assert!(!Output::html("SELECT 1", &r).contains("<blockquote>"));
+}
+
+#[test]
+fn test_markdown_table() {
+    let r = result(
+        &["name", "note"],
+        vec![
+            vec![json!("a|b"), json!(null)],
+            vec![json!("bob"), json!("two\nlines")],
+        ],
+    );
+    let out = Output::markdown("SELECT name, note FROM t", &r);
+
+    assert_eq!(
+        out,
+        "```sql\nSELECT name, note FROM t\n```\n\n\
+         | name | note |\n| --- | --- |\n| a\\|b | null |\n| bob | two lines |\n"
+    );
+}
+
+#[test]
+fn test_markdown_empty_and_prompt() {
+    let r = result(&["id"], vec![]);
+    let out = Output::markdown_with_prompt(Some("nobody"), "SELECT id FROM t", &r);
+
+    assert!(out.starts_with("> nobody\n\n```sql\n"));
+    assert!(out.ends_with("```\n\n_no rows_\n"));
+}
+
+#[test]
+fn test_jsonl_one_object_per_row() {
+    let r = result(
+        &["name", "id"],
+        vec![
+            vec![json!("ada"), json!(1)],
+            vec![json!("say \"hi\""), json!(null)],
+        ],
+    );
+    let out = Output::jsonl(&r);
+
+    assert_eq!(
+        out,
+        "{\"name\":\"ada\",\"id\":1}\n{\"name\":\"say \\\"hi\\\"\",\"id\":null}\n"
+    );
+    for line in out.lines() {
+        serde_json::from_str::<serde_json::Value>(line).unwrap();
+    }
+    assert_eq!(Output::jsonl(&result(&["id"], vec![])), "");
+}
@@ -0,0 +1,20 @@
+// tests for the --db-ping-interval keepalive query
+
+mod common;
+
+use nlql::Db;
+
+#[tokio::test]
+async fn test_ping_runs_detached() {
+    let path = common::temp_db("ping");
+    let db = Db::open_sqlite(&path).await.unwrap();
+
+    // the future owns its pool handle, so it can outlive the borrow
+    let ping = tokio::spawn(db.ping());
+    assert!(ping.await.unwrap().is_ok());
+
+    db.pool().close().await;
+    assert!(db.ping().await.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,43 @@
+// tests for --pivot
+
+mod common;
+
+use common::result;
+use serde_json::json;
+
+#[test]
+fn test_pivot_long_to_wide() {
+    let r = result(
+        &["region", "month", "total"],
+        vec![
+            vec![json!("eu"), json!("jan"), json!(10)],
+            vec![json!("eu"), json!("feb"), json!(12)],
+            vec![json!("us"), json!("jan"), json!(7)],
+        ],
+    );
+    let wide = r.pivot("month").unwrap();
+
+    assert_eq!(wide.columns, vec!["region", "jan", "feb"]);
+    assert_eq!(
+        wide.rows,
+        vec![
+            vec![json!("eu"), json!(10), json!(12)],
+            vec![json!("us"), json!(7), json!("")],
+        ]
+    );
+    assert_eq!(wide.row_count, 2);
+}
+
+#[test]
+fn test_pivot_unknown_column() {
+    let r = result(&["a", "b"], vec![vec![json!(1), json!(2)]]);
+
+    assert!(r.pivot("c").is_err());
+}
+
+#[test]
+fn test_pivot_needs_value_column() {
+    let r = result(&["a"], vec![vec![json!(1)]]);
+
+    assert!(r.pivot("a").is_err());
+}
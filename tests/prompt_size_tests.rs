@@ -0,0 +1,29 @@
+// tests for the tui prompt size counter
+
+mod common;
+
+use common::app;
+use nlql::tui::PromptSize;
+
+#[test]
+fn test_counts_chars_not_bytes() {
+    let mut app = app("", false);
+    app.prompt = "héllo wörld".to_string();
+    assert_eq!(app.prompt_chars(), 11);
+    assert_eq!(app.prompt_tokens(), 3);
+}
+
+#[test]
+fn test_size_levels() {
+    let mut app = app("", false);
+    app.max_prompt_chars = 10;
+
+    app.prompt = "x".repeat(8);
+    assert_eq!(app.prompt_size(), PromptSize::Fine);
+    app.prompt = "x".repeat(9);
+    assert_eq!(app.prompt_size(), PromptSize::Near);
+    app.prompt = "x".repeat(10);
+    assert_eq!(app.prompt_size(), PromptSize::Near);
+    app.prompt = "x".repeat(11);
+    assert_eq!(app.prompt_size(), PromptSize::Over);
+}
@@ -0,0 +1,52 @@
+// tests for quoting reserved-word identifiers in generated sql
+
+use nlql::quote_reserved;
+
+const SCHEMA: &str = "TABLE order (\n  id INTEGER\n  user TEXT\n  total REAL\n)\n\nTABLE items (\n  id INTEGER\n  key TEXT\n)";
+
+#[test]
+fn test_quotes_table_and_columns() {
+    let sql = "SELECT id, user FROM order WHERE user = 'bob' ORDER BY total";
+
+    assert_eq!(
+        quote_reserved(sql, SCHEMA, "postgres"),
+        "SELECT id, \"user\" FROM \"order\" WHERE \"user\" = 'bob' ORDER BY total"
+    );
+}
+
+#[test]
+fn test_mysql_backticks_and_dotted() {
+    let sql = "SELECT o.id, i.key FROM order o JOIN items i ON i.id = o.id";
+
+    assert_eq!(
+        quote_reserved(sql, SCHEMA, "mysql"),
+        "SELECT o.id, i.`key` FROM `order` o JOIN items i ON i.id = o.id"
+    );
+}
+
+#[test]
+fn test_leaves_keywords_strings_and_quoted_names() {
+    let sql =
+        "SELECT \"order\".id FROM \"order\" WHERE id > 0 GROUP BY id ORDER BY id LIMIT 5 -- order";
+
+    assert_eq!(quote_reserved(sql, SCHEMA, "postgres"), sql);
+}
+
+#[test]
+fn test_no_clashes_is_untouched() {
+    let schema = "TABLE users (\n  id INTEGER\n)";
+    let sql = "SELECT id FROM users ORDER BY id";
+
+    assert_eq!(quote_reserved(sql, schema, "sqlite"), sql);
+}
+
+#[test]
+fn test_view_names_and_key_lines() {
+    let schema = "TABLE t (\n  id INTEGER\n  PRIMARY KEY (id)\n)\n\nVIEW user (\n  id INTEGER\n)";
+    let sql = "SELECT id FROM user";
+
+    assert_eq!(
+        quote_reserved(sql, schema, "postgres"),
+        "SELECT id FROM \"user\""
+    );
+}
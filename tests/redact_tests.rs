@@ -0,0 +1,24 @@
+// tests for stripping literals out of logged sql
+
+use nlql::redact_literals;
+
+#[test]
+fn test_strings_and_numbers() {
+    assert_eq!(
+        redact_literals("SELECT * FROM users WHERE email = 'a@b.c' AND id > 42 LIMIT 10"),
+        "SELECT * FROM users WHERE email = ? AND id > ? LIMIT ?"
+    );
+    assert_eq!(
+        redact_literals("INSERT INTO t VALUES ('it''s', 3.14, -1e5)"),
+        "INSERT INTO t VALUES (?, ?, -?)"
+    );
+}
+
+#[test]
+fn test_names_and_comments_kept() {
+    assert_eq!(
+        redact_literals("SELECT \"col 1\", t2.x FROM t2 -- top 5\nWHERE y = 7"),
+        "SELECT \"col 1\", t2.x FROM t2 -- top 5\nWHERE y = ?"
+    );
+    assert_eq!(redact_literals("SELECT $1, `k9`"), "SELECT $1, `k9`");
+}
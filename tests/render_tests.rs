@@ -0,0 +1,29 @@
+// tests that redrawing over an old frame leaves nothing of it behind
+
+mod common;
+
+use common::app;
+use nlql::tui::{App, ThemeKind, render};
+use ratatui::{Terminal, backend::TestBackend};
+
+// what a terminal that never drew anything else shows for `app`
+fn fresh(app: &mut App) -> ratatui::buffer::Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|frame| render(frame, app)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+#[test]
+fn test_no_leftovers_after_popup_theme_and_panel_changes() {
+    let mut app = app("", false);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+    app.open_theme_popup();
+    terminal.draw(|frame| render(frame, &mut app)).unwrap();
+    app.close_popup();
+    app.set_theme(ThemeKind::ALL[ThemeKind::ALL.len() - 1]);
+    app.cycle_panel();
+    terminal.draw(|frame| render(frame, &mut app)).unwrap();
+
+    assert_eq!(*terminal.backend().buffer(), fresh(&mut app));
+}
@@ -0,0 +1,93 @@
+// tests for `nlql repl`, driven by canned input and a canned generator
+
+mod common;
+
+use nlql::{Db, Repl, ReplOptions, SqlFuture, SqlGenerator};
+
+// answers every prompt with the same sql
+struct Canned(&'static str);
+
+impl SqlGenerator for Canned {
+    fn generate_sql<'a>(&'a self, _prompt: &'a str, _schema: &'a str) -> SqlFuture<'a, String> {
+        let sql = self.0.to_string();
+        Box::pin(async move { Ok(sql) })
+    }
+}
+
+async fn open_repl(name: &str, sql: &'static str, options: ReplOptions) -> (Repl, String) {
+    let path = common::temp_db(&format!("repl_{name}"));
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER)").await.unwrap();
+    db.execute("INSERT INTO users VALUES (1), (2)")
+        .await
+        .unwrap();
+    let schema = db.schema().await.unwrap();
+    (Repl::new(db, schema, Box::new(Canned(sql)), options), path)
+}
+
+async fn session(repl: &mut Repl, input: &str) -> String {
+    let mut out = Vec::new();
+    repl.run(input.as_bytes(), &mut out).await.unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[tokio::test]
+async fn test_repl_runs_prompts_and_meta_commands() {
+    let (mut repl, path) = open_repl("meta", "SELECT id FROM users", ReplOptions::default()).await;
+    let out = session(
+        &mut repl,
+        "all users\n\\sql\n\\dry\nusers again\n\\history\n\\schema\n\\q\nnever read\n",
+    )
+    .await;
+
+    assert!(out.contains("(2 rows)"), "{out}");
+    assert!(out.contains("dry run on\nSELECT id FROM users\n"), "{out}");
+    assert!(out.contains("  1  all users\n  2  users again\n"), "{out}");
+    assert!(out.contains("TABLE users"), "{out}");
+    assert_eq!(repl.history, ["all users", "users again"]);
+    assert_eq!(repl.last_sql.as_deref(), Some("SELECT id FROM users"));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_repl_asks_before_dangerous_sql() {
+    let (mut repl, path) = open_repl("danger", "DELETE FROM users", ReplOptions::default()).await;
+    let out = session(&mut repl, "clear users\nn\n\\q\n").await;
+    assert!(
+        out.contains("run anyway? [y/N] blocked: DELETE without WHERE"),
+        "{out}"
+    );
+
+    let out = session(&mut repl, "clear users\ny\n").await;
+    assert!(!out.contains("blocked"), "{out}");
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_repl_read_only_and_limit() {
+    let options = ReplOptions {
+        read_only: true,
+        limit: Some(1),
+        ..ReplOptions::default()
+    };
+    let (mut repl, path) = open_repl("limit", "SELECT id FROM users", options).await;
+    let out = session(&mut repl, "users\n\\bogus\n").await;
+
+    assert_eq!(
+        repl.last_sql.as_deref(),
+        Some("SELECT id FROM users LIMIT 1")
+    );
+    assert!(out.contains("unknown command \\bogus"), "{out}");
+
+    let options = ReplOptions {
+        read_only: true,
+        ..ReplOptions::default()
+    };
+    let (mut repl, path2) = open_repl("readonly", "DROP TABLE users", options).await;
+    let out = session(&mut repl, "drop it\n").await;
+    assert!(out.contains("blocked: read-only mode"), "{out}");
+
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(path2);
+}
@@ -0,0 +1,60 @@
+// tests for routing reads to a --read-url replica
+
+mod common;
+
+use common::temp_db;
+use nlql::Db;
+use serde_json::json;
+
+async fn seeded(path: &str, value: i64) {
+    let db = Db::open_sqlite(path).await.unwrap();
+    db.execute("CREATE TABLE t (v INTEGER)").await.unwrap();
+    db.execute(&format!("INSERT INTO t VALUES ({value})"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_reads_hit_replica_writes_hit_primary() {
+    let primary = temp_db("primary");
+    let replica = temp_db("replica");
+    seeded(&primary, 1).await;
+    seeded(&replica, 2).await;
+
+    let db = Db::connect(&format!("sqlite:{primary}"))
+        .await
+        .unwrap()
+        .with_read_url(&format!("sqlite:{replica}"))
+        .await
+        .unwrap();
+    assert!(db.has_replica());
+
+    let read = db.execute("SELECT v FROM t").await.unwrap();
+    assert_eq!(read.rows, vec![vec![json!(2)]]);
+
+    db.execute("INSERT INTO t VALUES (3)").await.unwrap();
+    let primary_rows = Db::open_sqlite(&primary)
+        .await
+        .unwrap()
+        .execute("SELECT v FROM t ORDER BY v")
+        .await
+        .unwrap();
+    assert_eq!(primary_rows.rows, vec![vec![json!(1)], vec![json!(3)]]);
+
+    let _ = std::fs::remove_file(&primary);
+    let _ = std::fs::remove_file(&replica);
+}
+
+#[tokio::test]
+async fn test_replica_must_match_dialect() {
+    let primary = temp_db("mismatch");
+    let db = Db::open_sqlite(&primary).await.unwrap();
+
+    assert!(
+        db.with_read_url("postgres://user@localhost/db")
+            .await
+            .is_err()
+    );
+
+    let _ = std::fs::remove_file(&primary);
+}
@@ -0,0 +1,65 @@
+// tests for capping how many rows a result holds
+
+mod common;
+
+use nlql::{Db, DbConfig};
+use serde_json::json;
+
+async fn open_db(name: &str, max_rows: Option<usize>) -> (Db, String) {
+    let path = common::temp_db(&format!("cap_{name}"));
+    let config = DbConfig {
+        max_rows,
+        ..DbConfig::default()
+    };
+    let url = format!("sqlite:{path}?mode=rwc");
+    let db = Db::connect_with(&url, &config).await.unwrap();
+    db.execute("CREATE TABLE n (id INTEGER)").await.unwrap();
+    db.execute("INSERT INTO n VALUES (1), (2), (3)")
+        .await
+        .unwrap();
+    (db, path)
+}
+
+#[tokio::test]
+async fn test_result_stops_at_max_rows() {
+    let (db, path) = open_db("stops", Some(2)).await;
+    let result = db.execute("SELECT id FROM n ORDER BY id").await.unwrap();
+
+    assert_eq!(result.rows, vec![vec![json!(1)], vec![json!(2)]]);
+    assert_eq!(result.row_count, 2);
+    assert!(result.truncated);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_result_at_max_rows_is_whole() {
+    let (db, path) = open_db("whole", Some(3)).await;
+    let result = db.execute("SELECT id FROM n").await.unwrap();
+
+    assert_eq!(result.row_count, 3);
+    assert!(!result.truncated);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_no_max_rows_keeps_everything() {
+    let (db, path) = open_db("all", None).await;
+    let result = db.execute("SELECT id FROM n").await.unwrap();
+
+    assert_eq!(result.row_count, 3);
+    assert!(!result.truncated);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_script_result_is_capped() {
+    let (db, path) = open_db("script", Some(1)).await;
+    let result = db
+        .execute_script("INSERT INTO n VALUES (4); SELECT id FROM n ORDER BY id")
+        .await
+        .unwrap();
+
+    assert_eq!(result.rows, vec![vec![json!(1)]]);
+    assert!(result.truncated);
+    let _ = std::fs::remove_file(path);
+}
@@ -67,7 +67,238 @@ fn test_sql_injection_pattern() {
 }
 
 #[test]
-fn test_comment_injection() {
-    let safety = Safety::check("SELECT * FROM users -- comment");
+fn test_comment_is_a_warning() {
+    let safety = Safety::check("SELECT * FROM users -- limit applied");
+    assert!(!safety.is_dangerous);
+    assert!(safety.warning.unwrap().contains("comment"));
+
+    // a commented out WHERE doesn't count
+    assert!(Safety::check("DELETE FROM users -- WHERE id = 1").is_destructive);
+}
+
+#[test]
+fn test_dashes_in_string_are_not_a_comment() {
+    let safety = Safety::check("SELECT name FROM products WHERE note = 'x--y'");
+    assert!(!safety.is_dangerous);
+    assert!(safety.warning.is_none());
+}
+
+#[test]
+fn test_whitespace_between_keyword_and_object() {
+    for sql in [
+        "DROP\tTABLE users",
+        "drop\nTABLE users",
+        "SELECT 1;DROP TABLE users",
+        "SELECT 1 ;\n\tdrop table users",
+        "TRUNCATE\r\nusers",
+        "alter\t\ttable users add column x int",
+    ] {
+        assert!(
+            Safety::check(sql).is_dangerous,
+            "{sql:?} should be dangerous"
+        );
+    }
+}
+
+#[test]
+fn test_keyword_inside_identifier_is_fine() {
+    let safety = Safety::check("SELECT backdrop FROM scenes");
+    assert!(!safety.is_dangerous);
+}
+
+#[test]
+fn test_destructive() {
+    assert!(Safety::check("DROP TABLE users").is_destructive);
+    assert!(Safety::check("truncate users").is_destructive);
+    assert!(Safety::check("DELETE FROM users").is_destructive);
+    // dangerous, but nothing is lost
+    assert!(!Safety::check("ALTER TABLE users ADD COLUMN x INT").is_destructive);
+    assert!(!Safety::check("UPDATE users SET x = 1").is_destructive);
+}
+
+#[test]
+fn test_target_table() {
+    assert_eq!(
+        Safety::target_table("DROP TABLE IF EXISTS Users;").as_deref(),
+        Some("Users")
+    );
+    assert_eq!(
+        Safety::target_table("truncate table \"orders\"").as_deref(),
+        Some("orders")
+    );
+    assert_eq!(
+        Safety::target_table("delete from logs").as_deref(),
+        Some("logs")
+    );
+    assert_eq!(Safety::target_table("SELECT 1"), None);
+}
+
+#[test]
+fn test_column_names_are_not_statements() {
+    let safety = Safety::check("SELECT id, is_deleted, last_update FROM users");
+    assert!(!safety.is_dangerous);
+    assert!(safety.warning.is_none());
+
+    assert!(Safety::check("SELECT 1; DELETE FROM users").is_destructive);
+}
+
+#[test]
+fn test_keywords_in_strings_are_not_statements() {
+    for sql in [
+        "SELECT * FROM notes WHERE body = 'DROP it'",
+        "SELECT * FROM notes WHERE body = 'a; DROP TABLE users'",
+        "SELECT \"truncate\" FROM t",
+    ] {
+        let safety = Safety::check(sql);
+        assert!(!safety.is_dangerous, "{sql:?}: {}", safety.reason);
+    }
+}
+
+#[test]
+fn test_write_hidden_in_cte() {
+    let safety = Safety::check("WITH gone AS (DELETE FROM users RETURNING id) SELECT * FROM gone");
+    assert!(safety.is_destructive);
+
+    let safety = Safety::check(
+        "WITH gone AS (DELETE FROM users WHERE id = 1 RETURNING id) SELECT * FROM gone",
+    );
+    assert!(!safety.is_dangerous);
+    assert!(safety.warning.unwrap().contains("delete"));
+}
+
+#[test]
+fn test_where_in_subquery_does_not_count() {
+    let safety =
+        Safety::check("UPDATE users SET score = (SELECT max(score) FROM games WHERE id = 1)");
     assert!(safety.is_dangerous);
+    assert!(safety.reason.contains("UPDATE"));
+}
+
+#[test]
+fn test_read_only() {
+    for sql in [
+        "SELECT * FROM users",
+        "with active as (select * from users) select * from active;",
+        "EXPLAIN SELECT * FROM users",
+        "SELECT * FROM logs WHERE message = 'DELETE FROM users'",
+    ] {
+        assert!(Safety::is_read_only(sql), "{sql:?} should be read-only");
+    }
+    for sql in [
+        "UPDATE users SET name = 'x' WHERE id = 1",
+        "WITH gone AS (DELETE FROM users RETURNING id) SELECT * FROM gone",
+        "EXPLAIN ANALYZE DELETE FROM users",
+        "SELECT * INTO backup FROM users",
+        "SELECT 1; DROP TABLE users",
+        "PRAGMA journal_mode = WAL",
+    ] {
+        assert!(
+            !Safety::is_read_only(sql),
+            "{sql:?} should not be read-only"
+        );
+    }
+
+    let reason = Safety::read_only_refusal("INSERT INTO users VALUES (1)").unwrap();
+    assert!(reason.contains("read-only mode"));
+    assert!(reason.contains("INSERT"));
+    assert!(Safety::read_only_refusal("SELECT 1").is_none());
+}
+
+#[test]
+fn test_warning_text() {
+    let warning = |sql| Safety::check(sql).warning;
+    assert_eq!(
+        warning("DELETE FROM users WHERE id = 1").as_deref(),
+        Some("this will delete data")
+    );
+    assert_eq!(
+        warning("UPDATE users SET name = 'x' WHERE id = 1").as_deref(),
+        Some("this will update data")
+    );
+    assert_eq!(
+        warning("INSERT INTO users (name) VALUES ('x')").as_deref(),
+        Some("this will insert data")
+    );
+    assert_eq!(warning("SELECT 1"), None);
+}
+
+#[test]
+fn test_with_limit_adds_limit_to_select() {
+    assert_eq!(
+        Safety::with_limit("SELECT * FROM users;", 100).as_deref(),
+        Some("SELECT * FROM users LIMIT 100")
+    );
+    // a limit in a subquery or cte doesn't bound the outer query
+    assert_eq!(
+        Safety::with_limit("SELECT * FROM (SELECT id FROM users LIMIT 5) u", 10).as_deref(),
+        Some("SELECT * FROM (SELECT id FROM users LIMIT 5) u LIMIT 10")
+    );
+    assert_eq!(
+        Safety::with_limit("SELECT id FROM users -- everyone", 10).as_deref(),
+        Some("SELECT id FROM users -- everyone\nLIMIT 10")
+    );
+}
+
+#[test]
+fn test_with_limit_leaves_bounded_and_writes_alone() {
+    for sql in [
+        "SELECT id FROM users LIMIT 5",
+        "SELECT id FROM users ORDER BY id FETCH FIRST 5 ROWS ONLY",
+        "SELECT id FROM users OFFSET 10",
+        "SELECT id FROM users FOR SHARE",
+        "select 'no limit here' from users limit 1",
+        "DELETE FROM users WHERE id = 1",
+        "EXPLAIN SELECT id FROM users",
+        "SELECT 1; SELECT 2",
+    ] {
+        assert_eq!(Safety::with_limit(sql, 100), None, "{sql}");
+    }
+}
+
+#[test]
+fn test_paginate_wraps_select() {
+    assert_eq!(
+        Safety::paginate("SELECT id FROM users;", Some(10), Some(20)).as_deref(),
+        Some("SELECT * FROM (\nSELECT id FROM users\n) AS page LIMIT 10 OFFSET 20")
+    );
+    assert_eq!(
+        Safety::paginate("SELECT id FROM users -- everyone", Some(5), None).as_deref(),
+        Some("SELECT * FROM (\nSELECT id FROM users -- everyone\n) AS page LIMIT 5")
+    );
+    // an offset alone still needs a limit in front of it
+    let paged = Safety::paginate("SELECT id FROM users", None, Some(3)).unwrap();
+    assert!(
+        paged.ends_with(&format!("LIMIT {} OFFSET 3", i64::MAX)),
+        "{paged}"
+    );
+}
+
+#[test]
+fn test_paginate_refuses_anything_but_one_select() {
+    for sql in [
+        "DELETE FROM users",
+        "SELECT 1; SELECT 2",
+        "EXPLAIN SELECT id FROM users",
+    ] {
+        assert_eq!(Safety::paginate(sql, Some(10), None), None, "{sql}");
+    }
+}
+
+#[test]
+fn test_explain_refusal() {
+    assert_eq!(Safety::explain_refusal("SELECT id FROM users;"), None);
+    assert_eq!(
+        Safety::explain_refusal("EXPLAIN SELECT id FROM users"),
+        None
+    );
+    assert_eq!(
+        Safety::explain_refusal("SELECT 1; DELETE FROM users").as_deref(),
+        Some("the sql has 2 statements, EXPLAIN takes one")
+    );
+    assert_eq!(
+        Safety::explain_refusal("ANALYZE DELETE FROM users").as_deref(),
+        Some("only a SELECT is explained, not QUERY")
+    );
+    assert!(Safety::explain_refusal("DELETE FROM users WHERE id = 1").is_some());
+    assert!(Safety::explain_refusal("  ").is_some());
 }
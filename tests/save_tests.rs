@@ -0,0 +1,39 @@
+// tests for saving results into a sqlite table
+
+mod common;
+
+use common::temp_db;
+use nlql::Db;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_save_table_roundtrip() {
+    let path = temp_db("save");
+    let db = Db::open_sqlite(&path).await.unwrap();
+
+    let result = common::result(
+        &["id", "name", "score"],
+        vec![
+            vec![json!(1), json!("ann"), json!(2.5)],
+            vec![json!(2), json!(null), json!(3)],
+        ],
+    );
+    db.save_table("top users", &result).await.unwrap();
+
+    let back = db
+        .execute("SELECT id, name, score FROM \"top users\" ORDER BY id")
+        .await
+        .unwrap();
+    assert_eq!(
+        back.rows,
+        vec![
+            vec![json!(1), json!("ann"), json!(2.5)],
+            vec![json!(2), json!(null), json!(3.0)],
+        ]
+    );
+
+    // a second save into the same name must not clobber it
+    assert!(db.save_table("top users", &result).await.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
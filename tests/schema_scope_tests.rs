@@ -0,0 +1,74 @@
+// tests for narrowing the schema to picked tables (--interactive-schema)
+
+mod common;
+
+use common::app;
+use nlql::tui::Popup;
+use nlql::{schema_tables, scope_schema, timestamp_columns};
+
+const SCHEMA: &str = "TABLE users (\n  id INTEGER\n)\n\nTABLE orders (\n  id INTEGER\n  user_id INTEGER\n)\n\nTABLE audit_log (\n  id INTEGER\n)";
+
+#[test]
+fn test_schema_tables() {
+    assert_eq!(schema_tables(SCHEMA), ["users", "orders", "audit_log"]);
+    assert!(schema_tables("").is_empty());
+}
+
+#[test]
+fn test_scope_schema() {
+    let scoped = scope_schema(SCHEMA, &["ORDERS".to_string(), "users".to_string()]);
+
+    assert_eq!(schema_tables(&scoped), ["users", "orders"]);
+    assert!(scoped.contains("user_id INTEGER"));
+    assert!(!scoped.contains("audit_log"));
+}
+
+#[test]
+fn test_tui_table_picker() {
+    let mut app = app(SCHEMA, false);
+    app.open_table_picker();
+    assert_eq!(app.popup, Popup::Tables);
+    assert_eq!(app.table_picks.len(), 3);
+
+    app.table_pick_down();
+    app.toggle_table_pick();
+    app.apply_table_picks();
+
+    assert_eq!(app.popup, Popup::None);
+    assert_eq!(
+        app.schema_scope.as_deref(),
+        Some(&["orders".to_string()][..])
+    );
+    assert_eq!(schema_tables(&app.scoped_schema(SCHEMA)), ["orders"]);
+
+    // reopening keeps the picks, checking everything drops the scope
+    app.open_table_picker();
+    assert!(app.table_picks[1].1);
+    app.toggle_all_table_picks();
+    app.apply_table_picks();
+    assert!(app.schema_scope.is_none());
+    assert_eq!(app.scoped_schema(SCHEMA), SCHEMA);
+}
+
+#[test]
+fn test_tui_table_picker_none_checked_means_all() {
+    let mut app = app(SCHEMA, false);
+    app.open_table_picker();
+    app.apply_table_picks();
+
+    assert!(app.schema_scope.is_none());
+}
+
+#[test]
+fn test_timestamp_columns() {
+    let schema = "TABLE users (\n  id INTEGER\n  created_at TEXT\n)\n\nTABLE tags (\n  name TEXT\n)\n\nTABLE orders (\n  Updated_At TIMESTAMP\n  inserted_at TIMESTAMP\n)";
+    assert_eq!(
+        timestamp_columns(schema),
+        [
+            "users.created_at",
+            "orders.Updated_At",
+            "orders.inserted_at"
+        ]
+    );
+    assert!(timestamp_columns("TABLE t (\n  created TEXT\n)").is_empty());
+}
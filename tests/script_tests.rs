@@ -0,0 +1,90 @@
+// tests for running several statements as one transaction
+
+mod common;
+
+use nlql::{Db, Error};
+use serde_json::json;
+
+async fn open_db(name: &str) -> (Db, String) {
+    let path = common::temp_db(&format!("script_{name}"));
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+        .await
+        .unwrap();
+    (db, path)
+}
+
+#[tokio::test]
+async fn test_script_returns_last_result() {
+    let (db, path) = open_db("last").await;
+    let result = db
+        .execute_script("INSERT INTO users VALUES (1, 'a;b'); SELECT id, name FROM users;")
+        .await
+        .unwrap();
+
+    // the `;` in the string didn't split it
+    assert_eq!(result.rows, vec![vec![json!(1), json!("a;b")]]);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_script_rolls_back_on_error() {
+    let (db, path) = open_db("rollback").await;
+    let failed = db
+        .execute_script("INSERT INTO users VALUES (1, 'a'); INSERT INTO missing VALUES (2)")
+        .await;
+    assert!(failed.is_err());
+
+    let left = db.execute("SELECT id FROM users").await.unwrap();
+    assert_eq!(left.row_count, 0);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_statements_need_multi_statement() {
+    let (db, path) = open_db("gate").await;
+    let sql = "INSERT INTO users VALUES (1, 'a'); SELECT id FROM users";
+
+    let refused = db.execute_statements(sql, false).await;
+    assert!(matches!(refused, Err(Error::Blocked(reason)) if reason.contains("2 statements")));
+    assert_eq!(
+        db.execute("SELECT id FROM users").await.unwrap().row_count,
+        0
+    );
+
+    let result = db.execute_statements(sql, true).await.unwrap();
+    assert_eq!(result.rows, vec![vec![json!(1)]]);
+
+    // a trailing `;` or comment is still one statement
+    let result = db
+        .execute_statements("SELECT id FROM users; -- everyone", false)
+        .await
+        .unwrap();
+    assert_eq!(result.row_count, 1);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_every_path_refuses_several_statements() {
+    let (db, path) = open_db("refused").await;
+    let sql = "SELECT id FROM users; DELETE FROM users";
+    db.execute("INSERT INTO users VALUES (1, 'a')")
+        .await
+        .unwrap();
+
+    assert!(matches!(db.execute(sql).await, Err(Error::Blocked(_))));
+    let mut stream = db.execute_stream(sql);
+    assert!(matches!(stream.next().await, Some(Err(Error::Blocked(_)))));
+    assert!(stream.next().await.is_none());
+    // sqlite would run the DELETE after the EXPLAIN
+    assert!(matches!(
+        db.explain_lines(sql).await,
+        Err(Error::Blocked(_))
+    ));
+
+    assert_eq!(
+        db.execute("SELECT id FROM users").await.unwrap().row_count,
+        1
+    );
+    let _ = std::fs::remove_file(path);
+}
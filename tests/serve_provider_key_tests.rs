@@ -0,0 +1,52 @@
+// a request naming another provider gets the config file's key for it, in a
+// binary of its own: it sets OLLAMA_HOST, and changing the environment isn't
+// sound while other tests run beside it
+
+mod common;
+
+use common::{serve_with, temp_db};
+use nlql::{Config, Db, Provider, ServeOptions};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_request_provider_uses_config_key() {
+    // an ollama behind an auth proxy, answering only with the configured key
+    async fn generate(headers: axum::http::HeaderMap) -> axum::Json<serde_json::Value> {
+        let sql = match headers.get("authorization") {
+            Some(value) if value == "Bearer from-config" => "SELECT 1 AS keyed",
+            _ => "SELECT 0 AS keyed",
+        };
+        axum::Json(json!({ "response": sql, "done": true }))
+    }
+    let app = axum::Router::new().route("/api/generate", axum::routing::post(generate));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // SAFETY: the only test in this binary, nothing else reads the environment
+    unsafe { std::env::set_var("OLLAMA_HOST", addr.to_string()) };
+
+    let path = temp_db("serve_provider_key");
+    Db::open_sqlite(&path).await.unwrap();
+    let config: Config = toml::from_str("[providers.ollama]\napi_key = \"from-config\"").unwrap();
+    let options = ServeOptions {
+        provider: Provider::Gemini,
+        config,
+        ..Default::default()
+    };
+    let base = serve_with(None, &path, &options).await;
+
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(format!("{base}/query"))
+        .json(&json!({ "prompt": "x", "provider": "ollama" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(body["sql"], "SELECT 1 AS keyed", "{body}");
+
+    let _ = std::fs::remove_file(&path);
+}
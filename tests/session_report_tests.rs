@@ -0,0 +1,64 @@
+// tests for the markdown session report
+
+mod common;
+
+use common::app;
+use nlql::QueryResult;
+use nlql::tui::SESSION_ROWS;
+use serde_json::json;
+
+fn result(rows: usize) -> QueryResult {
+    common::result(
+        &["id", "name"],
+        (0..rows)
+            .map(|i| vec![json!(i), json!(format!("a|b {i}"))])
+            .collect(),
+    )
+}
+
+#[test]
+fn test_empty_session() {
+    assert!(app("", false).session_report().is_none());
+}
+
+#[test]
+fn test_report_has_prompt_sql_and_rows() {
+    let mut app = app("", false);
+    app.prompt = "all users".to_string();
+    app.submit();
+    app.set_sql("SELECT id, name FROM users".to_string());
+    app.set_result(result(2));
+
+    let report = app.session_report().unwrap();
+    assert!(report.contains("sqlite · test.db"));
+    assert!(report.contains("## 1. all users"));
+    assert!(report.contains("```sql\nSELECT id, name FROM users\n```"));
+    assert!(report.contains("| id | name |"));
+    assert!(report.contains("| 1 | a\\|b 1 |"));
+    assert!(report.contains("_2 rows_"));
+}
+
+#[test]
+fn test_report_caps_rows_and_keeps_errors() {
+    let mut app = app("", false);
+    app.set_sql("SELECT id, name FROM users".to_string());
+    app.set_result(result(SESSION_ROWS + 5));
+    // the result panel keeps every row, only the report is capped
+    assert_eq!(
+        app.result.as_ref().map(|r| r.rows.len()),
+        Some(SESSION_ROWS + 5)
+    );
+    app.set_sql("SELECT * FROM nope".to_string());
+    app.set_sql_error("no such table: nope".to_string());
+
+    assert_eq!(app.session.len(), 2);
+
+    let report = app.session_report().unwrap();
+    assert!(report.contains("## 1. sql"));
+    assert!(report.contains(&format!(
+        "_first {SESSION_ROWS} of {} rows_",
+        SESSION_ROWS + 5
+    )));
+    assert!(report.contains("## 2. sql"));
+    assert!(report.contains("**failed:** no such table: nope"));
+}
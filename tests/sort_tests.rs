@@ -0,0 +1,58 @@
+// tests for client-side sorting
+
+mod common;
+
+use common::result;
+use nlql::QueryResult;
+use serde_json::json;
+
+fn column(r: &QueryResult, i: usize) -> Vec<serde_json::Value> {
+    r.rows.iter().map(|row| row[i].clone()).collect()
+}
+
+#[test]
+fn test_numeric_text_sorts_as_numbers() {
+    let mut r = result(
+        &["name", "total"],
+        vec![
+            vec![json!("a"), json!("10")],
+            vec![json!("b"), json!("9")],
+            vec![json!("c"), json!(null)],
+            vec![json!("d"), json!("-2.5")],
+        ],
+    );
+    assert_eq!(r.numeric_columns(), vec![false, true]);
+
+    r.sort_by("TOTAL", false).unwrap();
+    // values stay as the driver gave them, nulls last
+    assert_eq!(
+        column(&r, 1),
+        vec![json!("-2.5"), json!("9"), json!("10"), json!(null)]
+    );
+
+    r.sort_by("total", true).unwrap();
+    assert_eq!(
+        column(&r, 1),
+        vec![json!("10"), json!("9"), json!("-2.5"), json!(null)]
+    );
+}
+
+#[test]
+fn test_mixed_column_sorts_as_text() {
+    let mut r = result(
+        &["code"],
+        vec![vec![json!("10")], vec![json!("9")], vec![json!("x1")]],
+    );
+    assert_eq!(r.numeric_columns(), vec![false]);
+
+    r.sort_by("code", false).unwrap();
+    assert_eq!(column(&r, 0), vec![json!("10"), json!("9"), json!("x1")]);
+
+    assert!(r.sort_by("missing", false).is_err());
+}
+
+#[test]
+fn test_all_null_column_is_not_numeric() {
+    let r = result(&["n"], vec![vec![json!(null)]]);
+    assert_eq!(r.numeric_columns(), vec![false]);
+}
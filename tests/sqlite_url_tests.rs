@@ -0,0 +1,70 @@
+// tests for the sqlite url forms sqlx accepts
+
+use nlql::{Db, connection_url, sqlite_path};
+
+#[test]
+fn test_sqlite_path_forms() {
+    assert_eq!(sqlite_path("sqlite:data.db"), Some("data.db"));
+    assert_eq!(sqlite_path("sqlite:./data.db"), Some("./data.db"));
+    assert_eq!(sqlite_path("sqlite://data.db"), Some("data.db"));
+    assert_eq!(sqlite_path("sqlite://./data.db"), Some("./data.db"));
+    assert_eq!(sqlite_path("sqlite:///abs/data.db"), Some("/abs/data.db"));
+    assert_eq!(
+        sqlite_path("sqlite:///abs/data.db?mode=rwc"),
+        Some("/abs/data.db")
+    );
+    assert_eq!(sqlite_path("sqlite::memory:"), Some(":memory:"));
+    assert_eq!(sqlite_path("postgres://localhost/app"), None);
+}
+
+#[tokio::test]
+async fn test_connect_each_form() {
+    let dir = std::env::temp_dir().join(format!("nlql_sqlite_url_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("data.db");
+    let path = file.to_str().unwrap();
+
+    for url in [
+        format!("sqlite:{path}?mode=rwc"),
+        format!("sqlite://{path}?mode=rwc"),
+        format!("sqlite:///{}?mode=rwc", path.trim_start_matches('/')),
+    ] {
+        let db = Db::connect(&url).await.unwrap();
+        assert_eq!(db.dialect_name(), "sqlite", "{url}");
+        assert_eq!(db.host(), "local", "{url}");
+        assert_eq!(db.database(), "data.db", "{url}");
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_connection_url_rejects_missing_scheme() {
+    assert_eq!(
+        connection_url("postgres://localhost/app").unwrap(),
+        "postgres://localhost/app"
+    );
+    assert_eq!(connection_url("sqlite:data.db").unwrap(), "sqlite:data.db");
+    // a bare file that's clearly a sqlite database
+    assert_eq!(connection_url("./data.db").unwrap(), "sqlite:./data.db");
+    assert_eq!(connection_url("app.SQLITE3").unwrap(), "sqlite:app.SQLITE3");
+
+    for url in ["localhost/app", "db.internal:5432", "redis://localhost"] {
+        let err = connection_url(url).unwrap_err().to_string();
+        assert!(
+            err.contains("unrecognized connection string"),
+            "{url}: {err}"
+        );
+        assert!(err.contains("expected postgres://, mysql://, or sqlite:<path>"));
+    }
+    let err = connection_url("me:pw@db/app").unwrap_err().to_string();
+    assert!(!err.contains("pw"), "{err}");
+
+    // and connect says so before sqlx tries to open `localhost/app` as a file
+    let err = Db::connect("localhost/app")
+        .await
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("unrecognized connection string"));
+}
@@ -0,0 +1,45 @@
+// tests for noticing a stale schema after a table goes away
+
+mod common;
+
+use nlql::Db;
+use nlql::tui::refresh_schema;
+
+async fn open_db(name: &str) -> (Db, String) {
+    let path = common::temp_db(name);
+    let db = Db::open_sqlite(&path).await.unwrap();
+    (db, path)
+}
+
+#[tokio::test]
+async fn test_missing_table_detected() {
+    let (db, path) = open_db("stale_missing").await;
+    db.execute("CREATE TABLE t (a INTEGER)").await.unwrap();
+
+    let err = db.execute("SELECT * FROM gone").await.unwrap_err();
+    assert!(err.is_missing_table());
+
+    // a bad column is a different problem
+    let err = db.execute("SELECT nope FROM t").await.unwrap_err();
+    assert!(!err.is_missing_table());
+
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn test_refresh_after_rename() {
+    let (db, path) = open_db("stale_rename").await;
+    db.execute("CREATE TABLE users (id INTEGER)").await.unwrap();
+
+    let mut app = common::app(&db.schema().await.unwrap(), false);
+    assert!(!refresh_schema(&mut app, &db).await);
+
+    db.execute("ALTER TABLE users RENAME TO accounts")
+        .await
+        .unwrap();
+    assert!(refresh_schema(&mut app, &db).await);
+    assert!(app.schema.contains("accounts"));
+    assert!(!app.schema.contains("users"));
+
+    std::fs::remove_file(path).ok();
+}
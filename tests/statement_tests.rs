@@ -0,0 +1,87 @@
+// tests for statement classification
+
+use nlql::StatementKind;
+use nlql::tui::RiskLevel;
+
+#[test]
+fn test_plain_statements() {
+    let cases = [
+        ("SELECT * FROM users", StatementKind::Select),
+        ("insert into users values (1)", StatementKind::Insert),
+        ("Update users SET x = 1", StatementKind::Update),
+        ("DELETE FROM users", StatementKind::Delete),
+        ("DROP TABLE users", StatementKind::Drop),
+        ("TRUNCATE users", StatementKind::Truncate),
+        ("ALTER TABLE users ADD x INT", StatementKind::Alter),
+        ("CREATE TABLE t (id INT)", StatementKind::Create),
+        ("EXPLAIN SELECT 1", StatementKind::Explain),
+        ("PRAGMA table_info(users)", StatementKind::Other),
+        ("", StatementKind::Other),
+    ];
+    for (sql, kind) in cases {
+        assert_eq!(StatementKind::from_sql(sql), kind, "{sql}");
+    }
+}
+
+#[test]
+fn test_leading_comments_and_parens() {
+    assert_eq!(
+        StatementKind::from_sql("-- top customers\nSELECT * FROM users"),
+        StatementKind::Select
+    );
+    assert_eq!(
+        StatementKind::from_sql("/* cleanup */ DELETE FROM users"),
+        StatementKind::Delete
+    );
+    assert_eq!(
+        StatementKind::from_sql("(SELECT 1) UNION (SELECT 2)"),
+        StatementKind::Select
+    );
+}
+
+#[test]
+fn test_ctes() {
+    assert_eq!(
+        StatementKind::from_sql("WITH recent AS (SELECT * FROM orders) SELECT * FROM recent"),
+        StatementKind::Select
+    );
+    assert_eq!(
+        StatementKind::from_sql(
+            "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT x FROM n"
+        ),
+        StatementKind::Select
+    );
+    assert_eq!(
+        StatementKind::from_sql("WITH old AS (SELECT id FROM users) DELETE FROM users"),
+        StatementKind::Delete
+    );
+    // a write hidden in a CTE still writes
+    assert_eq!(
+        StatementKind::from_sql("WITH gone AS (DELETE FROM users RETURNING *) SELECT * FROM gone"),
+        StatementKind::Delete
+    );
+    // keywords in strings don't count
+    assert_eq!(
+        StatementKind::from_sql("WITH t AS (SELECT 'delete') SELECT * FROM t"),
+        StatementKind::Select
+    );
+}
+
+#[test]
+fn test_labels_and_reads() {
+    assert_eq!(StatementKind::Select.label(), "SELECT");
+    assert_eq!(StatementKind::Other.label(), "QUERY");
+    assert!(StatementKind::Explain.is_read());
+    assert!(!StatementKind::Insert.is_read());
+}
+
+#[test]
+fn test_risk_uses_kind() {
+    let risk = RiskLevel::from_sql("-- tidy up\nDROP TABLE users");
+    assert_eq!(risk, RiskLevel::Danger);
+    assert_eq!(risk.sql_type("-- tidy up\nDROP TABLE users"), "DROP");
+    assert_eq!(
+        RiskLevel::from_sql("SELECT * FROM users WHERE is_deleted"),
+        RiskLevel::Safe
+    );
+}
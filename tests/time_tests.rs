@@ -0,0 +1,46 @@
+// tests for --since/--until parsing and the date given to the model
+
+use chrono::NaiveDate;
+use nlql::{date_context, parse_time_bound};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn test_relative_bounds() {
+    let today = date(2024, 3, 31);
+
+    assert_eq!(parse_time_bound("7d", today), Ok(date(2024, 3, 24)));
+    assert_eq!(parse_time_bound("2w", today), Ok(date(2024, 3, 17)));
+    assert_eq!(parse_time_bound("1m", today), Ok(date(2024, 2, 29)));
+    assert_eq!(parse_time_bound("1y", today), Ok(date(2023, 3, 31)));
+}
+
+#[test]
+fn test_absolute_bound() {
+    let today = date(2024, 3, 31);
+
+    assert_eq!(parse_time_bound("2023-01-15", today), Ok(date(2023, 1, 15)));
+}
+
+#[test]
+fn test_invalid_bound() {
+    let today = date(2024, 3, 31);
+
+    assert!(parse_time_bound("7", today).is_err());
+    assert!(parse_time_bound("d", today).is_err());
+    assert!(parse_time_bound("7x", today).is_err());
+}
+
+#[test]
+fn test_date_context() {
+    let offset = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+    let now = date(2024, 3, 31).and_hms_opt(9, 5, 0).unwrap();
+    let now = now.and_local_timezone(offset).unwrap();
+
+    assert_eq!(
+        date_context(&now),
+        "Current date is 2024-03-31 (Sunday), local time 09:05, timezone UTC+02:00."
+    );
+}
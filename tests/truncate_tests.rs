@@ -0,0 +1,55 @@
+// tests for the shared truncate helper
+
+use nlql::{display_width, pad, pad_left, truncate};
+
+#[test]
+fn test_truncate_ascii() {
+    assert_eq!(truncate("hello", 10, "..."), "hello");
+    assert_eq!(truncate("hello world", 8, "..."), "hello...");
+    assert_eq!(truncate("hello", 2, "..."), "he");
+}
+
+#[test]
+fn test_truncate_emoji_does_not_panic() {
+    let s = "🦀🦀🦀🦀🦀";
+    // each crab is 4 bytes and 2 columns wide
+    assert_eq!(truncate(s, 7, "..."), "🦀🦀...");
+    assert_eq!(truncate(s, 3, "…"), "🦀…");
+    assert!(display_width(&truncate(s, 5, "...")) <= 5);
+}
+
+#[test]
+fn test_truncate_accent_and_wide_symbol() {
+    let s = "café☕extra";
+    assert_eq!(truncate(s, 6, "..."), "caf...");
+    assert_eq!(truncate(s, 7, "..."), "café...");
+    // the cup is two columns, one spare column isn't enough for it
+    assert_eq!(truncate(s, 8, "..."), "café...");
+    assert_eq!(truncate(s, 9, "..."), "café☕...");
+}
+
+#[test]
+fn test_truncate_cjk_counts_columns() {
+    let s = "数据库查询";
+    assert_eq!(display_width(s), 10);
+    assert_eq!(truncate(s, 10, "..."), s);
+    assert_eq!(truncate(s, 7, "..."), "数据...");
+    // a wide char that would overflow is dropped, not split
+    assert_eq!(truncate(s, 6, "..."), "数...");
+}
+
+#[test]
+fn test_truncate_every_width() {
+    let s = "naïve 日本 🦀 done";
+    for max in 0..=display_width(s) + 1 {
+        assert!(display_width(&truncate(s, max, "...")) <= max);
+    }
+}
+
+#[test]
+fn test_pad_uses_display_width() {
+    assert_eq!(pad("日本", 6), "日本  ");
+    assert_eq!(pad("toolong", 3), "toolong");
+    assert_eq!(pad_left("42", 5), "   42");
+    assert_eq!(pad_left("日本", 6), "  日本");
+}
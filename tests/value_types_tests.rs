@@ -0,0 +1,45 @@
+// tests for turning sql values into json
+
+mod common;
+
+use nlql::{Db, Error};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_timestamp_uuid_and_blob_values() {
+    let path = common::temp_db("value_types");
+    let db = Db::open_sqlite(&path).await.unwrap();
+    db.execute("CREATE TABLE events (id UUID, at TIMESTAMP, amount NUMERIC, raw BLOB)")
+        .await
+        .unwrap();
+    db.execute(
+        "INSERT INTO events VALUES \
+         ('6f1c2a4e-8d3b-4c1a-9e2f-0a1b2c3d4e5f', '2024-01-02 03:04:05', 12.5, x'deadbeef')",
+    )
+    .await
+    .unwrap();
+
+    let result = db
+        .execute("SELECT id, CAST(at AS TEXT) AS at, amount, raw FROM events")
+        .await
+        .unwrap();
+    assert_eq!(
+        result.rows[0],
+        [
+            json!("6f1c2a4e-8d3b-4c1a-9e2f-0a1b2c3d4e5f"),
+            json!("2024-01-02 03:04:05"),
+            json!(12.5),
+            json!("\\xdeadbeef"),
+        ]
+    );
+
+    // the driver refuses a timestamp column as is, the error says what to do
+    let err = db.execute("SELECT at FROM events").await.unwrap_err();
+    let Error::UnsupportedType { column, type_name } = &err else {
+        panic!("{err}");
+    };
+    assert_eq!((column.as_str(), type_name.as_str()), ("at", "datetime"));
+    assert_eq!(err.exit_code(), 5);
+
+    std::fs::remove_file(path).ok();
+}